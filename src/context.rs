@@ -1,17 +1,49 @@
 //! Context management for terminal pane state.
 //!
 //! This module provides the `PaneContext` struct for representing
-//! the current working directory and git branch of a terminal pane.
+//! the current working directory and git branch of a terminal pane,
+//! the [`NameSource`] fallback chain used to pick a tab's base name, and
+//! helpers for resolving `~` against the user's home directory.
+
+use std::collections::BTreeMap;
+
+use crate::git::GitStatus;
 
 /// Parsed context information from a terminal pane.
 ///
-/// Contains the current working directory and optional git branch.
-#[derive(Debug, Clone)]
+/// Contains the current working directory and what git reported about it.
+#[derive(Debug, Clone, Default)]
 pub struct PaneContext {
     /// The full path to the current working directory.
     pub cwd: String,
     /// The current git branch name, or `None` if not in a git repository.
     pub branch: Option<String>,
+    /// The git repository's top-level directory, or `None` if unknown.
+    pub repo_root: Option<String>,
+    /// Working tree status, or `None` if it was not requested or is unknown.
+    pub status: Option<GitStatus>,
+    /// The remote the branch tracks, or `None` if it has no upstream or the
+    /// remote was not requested.
+    pub remote: Option<String>,
+    /// Whether the cwd itself was modified within `fresh_minutes`.
+    pub fresh: bool,
+    /// Whether the working tree has uncommitted changes, once status is known.
+    pub dirty: bool,
+    /// The short label of the forge hosting `origin`, e.g. `gh`.
+    pub forge: Option<String>,
+    /// Commits made since diverging from `base_branch`, when it is set and
+    /// exists.
+    pub base_ahead: Option<usize>,
+    /// Whether every branch lookup reported the cwd is not in a repository.
+    pub outside_vcs: bool,
+    /// The active kubernetes context, or `None` if it is unknown or unset.
+    pub kube_context: Option<String>,
+    /// The icon for the project type found in the cwd, if any.
+    pub icon: Option<String>,
+    /// The first heading of the repo's `README.md`, once its lookup finds one.
+    pub readme_title: Option<String>,
+    /// The `name` field of the cwd's `package.json`, once its lookup finds one.
+    pub package_name: Option<String>,
 }
 
 impl PaneContext {
@@ -24,11 +56,445 @@ impl PaneContext {
     ///
     /// For a path `/home/user/project`, returns `"project"`.
     /// For the root path `/`, returns `"/"`.
+    /// For a UNC path `\\server\share\project`, returns `"project"`.
     pub fn folder_name(&self) -> &str {
-        std::path::Path::new(&self.cwd)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(&self.cwd)
+        if is_unc(&self.cwd) {
+            return self
+                .cwd
+                .rsplit('\\')
+                .find(|part| !part.is_empty())
+                .unwrap_or(&self.cwd);
+        }
+        path_basename(&self.cwd)
+    }
+
+    /// Returns the last `depth` components of the cwd joined by `/`, so a
+    /// depth of 2 gives `backend/src` for `/work/backend/src`.
+    ///
+    /// A depth of 0 or 1 is the same as [`PaneContext::folder_name`], and a
+    /// path with fewer components than `depth` is shown whole, without its
+    /// leading `/`.
+    pub fn parent_and_folder(&self, depth: usize) -> String {
+        let sep = if is_unc(&self.cwd) { '\\' } else { '/' };
+        let parts: Vec<&str> = self.cwd.split(sep).filter(|p| !p.is_empty()).collect();
+        if depth <= 1 || parts.is_empty() {
+            return self.folder_name().to_string();
+        }
+        parts[parts.len().saturating_sub(depth)..].join("/")
+    }
+
+    /// Returns the server of a UNC cwd, e.g. `server` for
+    /// `\\server\share\project`.
+    pub fn unc_server(&self) -> Option<&str> {
+        let rest = self.cwd.strip_prefix(r"\\")?;
+        rest.split('\\').next().filter(|server| !server.is_empty())
+    }
+}
+
+/// Returns whether `path` is a Windows UNC path such as `\\server\share`.
+pub fn is_unc(path: &str) -> bool {
+    path.strip_prefix(r"\\")
+        .is_some_and(|rest| rest.chars().next().is_some_and(|c| c != '\\'))
+}
+
+/// Returns the last component of `path`, or `path` itself if it has none.
+fn path_basename(path: &str) -> &str {
+    std::path::Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+}
+
+/// A source the tab's base name can be derived from.
+///
+/// Sources are tried in the order configured via `name_sources` until one
+/// yields a non-empty name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    /// Basename of the git repository's top-level directory.
+    RepoRoot,
+    /// Basename of the current working directory.
+    Folder,
+    /// The command running in the pane, when Zellij reports one.
+    Process,
+    /// The raw pane title.
+    Title,
+    /// The first `# Heading` of the repo's `README.md`.
+    ReadmeTitle,
+    /// The `name` field of the cwd's `package.json`.
+    PackageName,
+}
+
+impl NameSource {
+    /// Parses a single source name (`repo_root`, `folder`, `process`, `title`,
+    /// `readme_title`, `package_name`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "repo_root" => Some(Self::RepoRoot),
+            "folder" => Some(Self::Folder),
+            "process" => Some(Self::Process),
+            "title" => Some(Self::Title),
+            "readme_title" => Some(Self::ReadmeTitle),
+            "package_name" => Some(Self::PackageName),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list of sources, skipping unknown entries.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',').filter_map(|p| Self::parse(p.trim())).collect()
+    }
+}
+
+/// How a non-path pane title containing spaces is reduced to a name.
+///
+/// Titles like `vim src/main.rs` are usually the running command and its
+/// arguments rather than a useful tab name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TitleCommandMode {
+    /// Keep only the command, e.g. `vim`.
+    FirstWord,
+    /// Use the directory of the last path-like argument, e.g. `src`.
+    LastPathArg,
+    /// Keep the title unchanged.
+    #[default]
+    Whole,
+}
+
+impl TitleCommandMode {
+    /// Parses a mode name (`first_word`, `last_path_arg`, `whole`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "first_word" => Some(Self::FirstWord),
+            "last_path_arg" => Some(Self::LastPathArg),
+            "whole" => Some(Self::Whole),
+            _ => None,
+        }
+    }
+}
+
+/// Reduces a command-style pane title according to `mode`.
+///
+/// Titles that are paths or contain no spaces are returned trimmed but
+/// otherwise unchanged. In [`TitleCommandMode::LastPathArg`] mode a title
+/// without any path-like argument falls back to its first word.
+///
+/// # Examples
+///
+/// For the title `vim src/main.rs`:
+/// - `FirstWord` → `"vim"`
+/// - `LastPathArg` → `"src"`
+/// - `Whole` → `"vim src/main.rs"`
+pub fn reduce_title(title: &str, mode: TitleCommandMode) -> String {
+    let title = title.trim();
+    if title.starts_with('/') || title.starts_with('~') || !title.contains(' ') {
+        return title.to_string();
+    }
+
+    let mut words = title.split_whitespace();
+    let first_word = words.next().unwrap_or_default();
+    match mode {
+        TitleCommandMode::Whole => title.to_string(),
+        TitleCommandMode::FirstWord => first_word.to_string(),
+        TitleCommandMode::LastPathArg => words
+            .rev()
+            .find(|arg| arg.contains('/'))
+            .map(|arg| {
+                let path = std::path::Path::new(arg);
+                path.parent()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_else(|| path_basename(arg))
+                    .to_string()
+            })
+            .unwrap_or_else(|| first_word.to_string()),
+    }
+}
+
+/// Everything known about a pane that a [`NameSource`] can draw from.
+#[derive(Debug, Clone, Default)]
+pub struct NameInputs {
+    /// The pane's cwd and git details, if a cwd could be extracted.
+    pub pane: Option<PaneContext>,
+    /// The command running in the pane, if any.
+    pub command: Option<String>,
+    /// The pane title.
+    pub title: String,
+    /// Labels for exact mount point paths, preferred by the folder source.
+    pub mount_labels: Vec<(String, String)>,
+    /// The cwd's path within its repo, shown by the folder source instead of
+    /// the folder name when set.
+    pub repo_subpath: Option<String>,
+    /// Trailing path components the folder source shows; 0 counts as 1.
+    pub folder_depth: usize,
+}
+
+/// Returns the program name of a command line, e.g. `htop` for
+/// `/usr/bin/htop -d 10`, or `None` for a blank command.
+pub fn command_name(command: &str) -> Option<&str> {
+    command.split_whitespace().next().map(path_basename)
+}
+
+/// Returns the label configured for `cwd` when it is exactly a mount point.
+///
+/// Trailing slashes are ignored on both sides, so `/mnt/data/` matches a
+/// `/mnt/data` entry; paths below the mount point do not match.
+pub fn mount_label<'a>(cwd: &str, labels: &'a [(String, String)]) -> Option<&'a str> {
+    let cwd = trim_trailing_slashes(cwd);
+    labels
+        .iter()
+        .find(|(path, _)| trim_trailing_slashes(path) == cwd)
+        .map(|(_, label)| label.as_str())
+}
+
+/// Returns the path of `cwd` within the repo rooted at `repo_root`.
+///
+/// Both paths must be absolute. Returns `None` at the root itself or when
+/// `cwd` lies outside the repo.
+///
+/// # Examples
+///
+/// `repo_subpath("/work/mono/services/api", "/work/mono")` → `"services/api"`.
+pub fn repo_subpath(cwd: &str, repo_root: &str) -> Option<String> {
+    let root = trim_trailing_slashes(repo_root);
+    let rest = trim_trailing_slashes(cwd)
+        .strip_prefix(root)?
+        .strip_prefix('/')?;
+    (!rest.is_empty()).then(|| rest.to_string())
+}
+
+/// Returns the `segment`-th path component of `cwd` below `base`, counting
+/// from one, as the project family a tab belongs to.
+///
+/// An empty `base` counts from `/`. Returns `None` when `segment` is 0, when
+/// `cwd` lies outside `base` or when it is too short to reach the segment.
+///
+/// # Examples
+///
+/// `family_name("/home/me/work/acme/api", "/home/me/work", 1)` → `"acme"`.
+pub fn family_name(cwd: &str, base: &str, segment: usize) -> Option<String> {
+    let rest = match trim_trailing_slashes(base) {
+        "" | "/" => cwd.trim_start_matches('/').to_string(),
+        base => repo_subpath(cwd, base)?,
+    };
+    rest.split('/')
+        .filter(|part| !part.is_empty())
+        .nth(segment.checked_sub(1)?)
+        .map(str::to_string)
+}
+
+/// Returns whether `path` is `root` itself or lies below it.
+///
+/// Only whole path segments match, so `/work/monolith` is not within
+/// `/work/mono`.
+pub fn is_within(path: &str, root: &str) -> bool {
+    trim_trailing_slashes(path) == trim_trailing_slashes(root) || repo_subpath(path, root).is_some()
+}
+
+/// Strips trailing slashes, keeping a lone `/` for the root.
+fn trim_trailing_slashes(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Resolves a base name by trying each source in order.
+///
+/// Returns the first non-empty candidate, or `None` if no source yields one.
+///
+/// # Examples
+///
+/// With sources `folder,title`, a pane with cwd `/home/user/project` resolves
+/// to `"project"`; a pane without a cwd falls through to its title.
+pub fn resolve_name(sources: &[NameSource], inputs: &NameInputs) -> Option<String> {
+    sources.iter().find_map(|source| {
+        let candidate = match source {
+            NameSource::RepoRoot => inputs
+                .pane
+                .as_ref()
+                .and_then(|p| p.repo_root.as_deref())
+                .map(|root| path_basename(root).to_string()),
+            NameSource::Folder => inputs.pane.as_ref().map(|p| {
+                mount_label(&p.cwd, &inputs.mount_labels)
+                    .or(inputs.repo_subpath.as_deref())
+                    .map_or_else(|| p.parent_and_folder(inputs.folder_depth), str::to_string)
+            }),
+            NameSource::Process => inputs
+                .command
+                .as_deref()
+                .and_then(command_name)
+                .map(str::to_string),
+            NameSource::Title => Some(inputs.title.trim().to_string()),
+            NameSource::ReadmeTitle => inputs.pane.as_ref().and_then(|p| p.readme_title.clone()),
+            NameSource::PackageName => inputs.pane.as_ref().and_then(|p| p.package_name.clone()),
+        };
+        candidate.filter(|name| !name.is_empty())
+    })
+}
+
+/// Extracts the first `# Heading` from the text of a README.
+///
+/// Only level-one ATX headings count, and lines inside fenced code blocks
+/// are skipped so a `# comment` in a shell example is never taken. Closing
+/// `#`s are dropped. Returns `None` when there is no such heading, as for a
+/// missing README.
+///
+/// # Examples
+///
+/// `"[![ci](badge.svg)]\n\n# My Service #\n"` → `"My Service"`.
+pub fn parse_readme_title(readme: &str) -> Option<String> {
+    let mut in_fence = false;
+    for line in readme.lines() {
+        let line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(heading) = line.strip_prefix("# ") {
+            let heading = heading.trim_end_matches('#').trim();
+            if !heading.is_empty() {
+                return Some(heading.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Extracts the top-level `"name"` string from the text of a `package.json`.
+///
+/// This is a minimal scan rather than a full JSON parser: it tracks nesting
+/// and strings so a `"name"` inside `dependencies` or `repository` is never
+/// taken, and decodes the usual escapes. Returns `None` when there is no such
+/// field or it is empty, as for a missing file.
+///
+/// # Examples
+///
+/// `{"name": "@acme/billing", "version": "1.0.0"}` → `"@acme/billing"`.
+pub fn parse_package_name(json: &str) -> Option<String> {
+    let mut chars = json.chars();
+    let mut depth = 0usize;
+    let mut expect_key = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => {
+                depth += 1;
+                expect_key = c == '{' && depth == 1;
+            }
+            '}' | ']' => depth = depth.saturating_sub(1),
+            ',' => expect_key = depth == 1,
+            '"' => {
+                let key = read_json_string(&mut chars)?;
+                if !(depth == 1 && expect_key) {
+                    continue;
+                }
+                expect_key = false;
+                if key != "name" {
+                    continue;
+                }
+                let mut rest = chars.by_ref().skip_while(|c| c.is_whitespace());
+                if rest.next() != Some(':') {
+                    return None;
+                }
+                let mut rest = rest.skip_while(|c| c.is_whitespace());
+                if rest.next() != Some('"') {
+                    return None;
+                }
+                return read_json_string(&mut chars).filter(|name| !name.trim().is_empty());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads a JSON string up to its closing quote, decoding escapes. Returns
+/// `None` when the string is never closed.
+fn read_json_string(chars: &mut std::str::Chars) -> Option<String> {
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                'r' => out.push('\r'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let decoded = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                    out.push(decoded.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                // `\"`, `\\`, `\/`, and the control escapes nobody puts in a name
+                other => out.push(other),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+/// Resolves the home directory used for `~` expansion.
+///
+/// The `home_dir` plugin configuration key takes precedence when set;
+/// otherwise the `HOME` value read from the plugin environment is used.
+/// Empty values are treated as unset.
+///
+/// # Arguments
+///
+/// * `env_home` - The `HOME` environment variable, if available
+/// * `config` - The configuration map from Zellij's plugin loading
+pub fn resolve_home_dir(
+    env_home: Option<String>,
+    config: &BTreeMap<String, String>,
+) -> Option<String> {
+    config
+        .get("home_dir")
+        .cloned()
+        .filter(|h| !h.is_empty())
+        .or_else(|| env_home.filter(|h| !h.is_empty()))
+}
+
+/// Expands a leading `~` in `path` to the given home directory.
+///
+/// Only `~` on its own or followed by `/` is expanded; `~user` forms and
+/// paths without a tilde are returned unchanged, as is everything when no
+/// home directory is known.
+///
+/// # Examples
+///
+/// With home `/home/me`, `"~/project"` becomes `"/home/me/project"`.
+pub fn expand_tilde(path: &str, home: Option<&str>) -> String {
+    match (home, path.strip_prefix('~')) {
+        (Some(home), Some(rest)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home.trim_end_matches('/'), rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Abbreviates a leading home directory in `path` to `~`, the reverse of
+/// [`expand_tilde`].
+///
+/// Only whole path segments match, so `/home/me2` is not under `/home/me`.
+/// Paths outside the home directory are returned unchanged, as is everything
+/// when no home directory is known or it is `/`.
+///
+/// # Examples
+///
+/// With home `/home/me`, `"/home/me/project"` becomes `"~/project"`.
+pub fn abbreviate_home(path: &str, home: Option<&str>) -> String {
+    let home = match home.map(trim_trailing_slashes) {
+        Some(home) if home != "/" => home,
+        _ => return path.to_string(),
+    };
+    if trim_trailing_slashes(path) == home {
+        return "~".to_string();
+    }
+    match repo_subpath(path, home) {
+        Some(rest) => format!("~/{}", rest),
+        None => path.to_string(),
     }
 }
 
@@ -41,6 +507,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/project".to_string(),
             branch: Some("main".to_string()),
+            ..Default::default()
         };
         assert_eq!(ctx.folder_name(), "project");
     }
@@ -50,6 +517,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/deeply/nested/folder".to_string(),
             branch: Some("main".to_string()),
+            ..Default::default()
         };
         assert_eq!(ctx.folder_name(), "folder");
     }
@@ -59,6 +527,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/".to_string(),
             branch: Some("main".to_string()),
+            ..Default::default()
         };
         assert_eq!(ctx.folder_name(), "/");
     }
@@ -68,6 +537,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/project/".to_string(),
             branch: None,
+            ..Default::default()
         };
         assert_eq!(ctx.folder_name(), "project");
     }
@@ -77,8 +547,101 @@ mod tests {
         let ctx = PaneContext {
             cwd: "project".to_string(),
             branch: None,
+            ..Default::default()
+        };
+        assert_eq!(ctx.folder_name(), "project");
+    }
+
+    #[test]
+    fn test_folder_name_unc() {
+        let ctx = PaneContext {
+            cwd: r"\\fs01\share\project\".to_string(),
+            ..Default::default()
         };
         assert_eq!(ctx.folder_name(), "project");
+        assert_eq!(ctx.unc_server(), Some("fs01"));
+        let share = PaneContext {
+            cwd: r"\\fs01\share".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(share.folder_name(), "share");
+        assert!(!is_unc(r"\\\triple"));
+        assert!(!is_unc("/home/user/project"));
+    }
+
+    #[test]
+    fn test_parent_and_folder() {
+        let ctx = PaneContext {
+            cwd: "/work/backend/src/".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(ctx.parent_and_folder(2), "backend/src");
+        assert_eq!(ctx.parent_and_folder(3), "work/backend/src");
+        assert_eq!(ctx.parent_and_folder(1), "src");
+        assert_eq!(ctx.parent_and_folder(0), "src");
+    }
+
+    #[test]
+    fn test_parent_and_folder_shallow_paths() {
+        let at = |cwd: &str| PaneContext {
+            cwd: cwd.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(at("/").parent_and_folder(2), "/");
+        assert_eq!(at("/work").parent_and_folder(3), "work");
+        assert_eq!(at("~/proj").parent_and_folder(5), "~/proj");
+        assert_eq!(at(r"\\fs01\share\api").parent_and_folder(2), "share/api");
+    }
+
+    #[test]
+    fn test_parse_readme_title() {
+        let readme = "[![ci](https://ci/badge.svg)](https://ci)\n\n# My Service #\n\n## Usage\n";
+        assert_eq!(parse_readme_title(readme), Some("My Service".to_string()));
+        assert_eq!(
+            parse_readme_title("## Setup\n#not-a-heading\n# Billing API\n"),
+            Some("Billing API".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_readme_title_skips_code_and_missing_readme() {
+        let readme = "Intro\n\n```sh\n# install deps\nmake\n```\n# Real Title\n";
+        assert_eq!(parse_readme_title(readme), Some("Real Title".to_string()));
+        assert_eq!(parse_readme_title("Just text\n#\n"), None);
+        // A missing README produces no output at all
+        assert_eq!(parse_readme_title(""), None);
+    }
+
+    #[test]
+    fn test_parse_package_name() {
+        let json = r#"{
+  "name": "@acme/billing",
+  "version": "1.4.0",
+  "scripts": { "build": "tsc" }
+}"#;
+        assert_eq!(parse_package_name(json), Some("@acme/billing".to_string()));
+        assert_eq!(
+            parse_package_name(r#"{"private":true,"name":"caf\u00e9 \"ui\""}"#),
+            Some("café \"ui\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_package_name_only_top_level() {
+        let json =
+            r#"{"repository": {"name": "not-me"}, "keywords": ["name", "x"], "name": "web"}"#;
+        assert_eq!(parse_package_name(json), Some("web".to_string()));
+        // A value that merely reads "name" is not a key
+        assert_eq!(parse_package_name(r#"{"description": "name"}"#), None);
+    }
+
+    #[test]
+    fn test_parse_package_name_absent() {
+        assert_eq!(parse_package_name(r#"{"version": "1.0.0"}"#), None);
+        assert_eq!(parse_package_name(r#"{"name": ""}"#), None);
+        assert_eq!(parse_package_name(r#"{"name": 3}"#), None);
+        // A missing package.json produces no output at all
+        assert_eq!(parse_package_name(""), None);
     }
 
     #[test]
@@ -86,6 +649,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/project".to_string(),
             branch: Some("main".to_string()),
+            ..Default::default()
         };
         let cloned = ctx.clone();
         assert_eq!(cloned.cwd, ctx.cwd);
@@ -97,6 +661,7 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/project".to_string(),
             branch: Some("main".to_string()),
+            ..Default::default()
         };
         let debug_str = format!("{:?}", ctx);
         assert!(debug_str.contains("project"));
@@ -108,7 +673,349 @@ mod tests {
         let ctx = PaneContext {
             cwd: "/home/user/project".to_string(),
             branch: None,
+            ..Default::default()
         };
         assert!(ctx.branch.is_none());
     }
+
+    #[test]
+    fn test_resolve_home_dir_from_env() {
+        let config = BTreeMap::new();
+        assert_eq!(
+            resolve_home_dir(Some("/home/me".to_string()), &config),
+            Some("/home/me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_home_dir_config_override() {
+        let config = BTreeMap::from([("home_dir".to_string(), "/Users/me".to_string())]);
+        assert_eq!(
+            resolve_home_dir(Some("/home/me".to_string()), &config),
+            Some("/Users/me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_home_dir_config_without_env() {
+        let config = BTreeMap::from([("home_dir".to_string(), "/Users/me".to_string())]);
+        assert_eq!(
+            resolve_home_dir(None, &config),
+            Some("/Users/me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_home_dir_empty_values_ignored() {
+        let config = BTreeMap::from([("home_dir".to_string(), String::new())]);
+        assert_eq!(resolve_home_dir(Some(String::new()), &config), None);
+        assert_eq!(
+            resolve_home_dir(Some("/home/me".to_string()), &config),
+            Some("/home/me".to_string())
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde_subpath() {
+        assert_eq!(
+            expand_tilde("~/project", Some("/home/me")),
+            "/home/me/project"
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde_bare() {
+        assert_eq!(expand_tilde("~", Some("/home/me/")), "/home/me");
+    }
+
+    #[test]
+    fn test_expand_tilde_unchanged() {
+        assert_eq!(expand_tilde("/srv/app", Some("/home/me")), "/srv/app");
+        assert_eq!(expand_tilde("~other/app", Some("/home/me")), "~other/app");
+        assert_eq!(expand_tilde("~/project", None), "~/project");
+    }
+
+    #[test]
+    fn test_abbreviate_home_subpath() {
+        let home = Some("/home/me");
+        assert_eq!(abbreviate_home("/home/me/project", home), "~/project");
+        assert_eq!(
+            abbreviate_home("/home/me/work/api/", Some("/home/me/")),
+            "~/work/api"
+        );
+        assert_eq!(abbreviate_home("/home/me", home), "~");
+    }
+
+    #[test]
+    fn test_abbreviate_home_outside_home_unchanged() {
+        let home = Some("/home/me");
+        assert_eq!(abbreviate_home("/srv/app", home), "/srv/app");
+        assert_eq!(
+            abbreviate_home("/home/me2/project", home),
+            "/home/me2/project"
+        );
+        assert_eq!(abbreviate_home("~/project", home), "~/project");
+        assert_eq!(
+            abbreviate_home("/home/me/project", None),
+            "/home/me/project"
+        );
+        assert_eq!(abbreviate_home("/srv/app", Some("/")), "/srv/app");
+    }
+
+    #[test]
+    fn test_name_source_parse_list() {
+        assert_eq!(
+            NameSource::parse_list("repo_root, folder,process,title"),
+            vec![
+                NameSource::RepoRoot,
+                NameSource::Folder,
+                NameSource::Process,
+                NameSource::Title,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_source_parse_list_skips_unknown() {
+        assert_eq!(
+            NameSource::parse_list("bogus,title,"),
+            vec![NameSource::Title]
+        );
+        assert!(NameSource::parse_list("").is_empty());
+    }
+
+    fn sample_inputs() -> NameInputs {
+        NameInputs {
+            pane: Some(PaneContext {
+                cwd: "/home/user/monorepo/src".to_string(),
+                branch: Some("main".to_string()),
+                repo_root: Some("/home/user/monorepo".to_string()),
+                ..Default::default()
+            }),
+            command: Some("/usr/bin/htop -d 10".to_string()),
+            title: "  my title ".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_name_first_source_wins() {
+        let inputs = sample_inputs();
+        let order = |s: &str| resolve_name(&NameSource::parse_list(s), &inputs);
+        assert_eq!(order("repo_root,folder"), Some("monorepo".to_string()));
+        assert_eq!(order("folder,repo_root"), Some("src".to_string()));
+        assert_eq!(order("process,folder"), Some("htop".to_string()));
+        assert_eq!(order("title,folder"), Some("my title".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_name_falls_through_missing_sources() {
+        let inputs = NameInputs {
+            title: "vim".to_string(),
+            ..Default::default()
+        };
+        let sources = NameSource::parse_list("repo_root,folder,process,title");
+        assert_eq!(resolve_name(&sources, &inputs), Some("vim".to_string()));
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name("/usr/bin/htop -d 10"), Some("htop"));
+        assert_eq!(command_name("watch"), Some("watch"));
+        assert_eq!(command_name("  "), None);
+    }
+
+    #[test]
+    fn test_resolve_name_skips_blank_command() {
+        let inputs = NameInputs {
+            pane: Some(PaneContext {
+                cwd: "/srv/app".to_string(),
+                ..Default::default()
+            }),
+            command: Some("   ".to_string()),
+            ..Default::default()
+        };
+        let sources = NameSource::parse_list("process,folder");
+        assert_eq!(resolve_name(&sources, &inputs), Some("app".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_name_nothing_resolves() {
+        let inputs = NameInputs {
+            title: "   ".to_string(),
+            ..Default::default()
+        };
+        let sources = NameSource::parse_list("folder,title");
+        assert_eq!(resolve_name(&sources, &inputs), None);
+        assert_eq!(resolve_name(&[], &sample_inputs()), None);
+    }
+
+    #[test]
+    fn test_title_command_mode_parse() {
+        assert_eq!(
+            TitleCommandMode::parse("first_word"),
+            Some(TitleCommandMode::FirstWord)
+        );
+        assert_eq!(
+            TitleCommandMode::parse("last_path_arg"),
+            Some(TitleCommandMode::LastPathArg)
+        );
+        assert_eq!(
+            TitleCommandMode::parse("whole"),
+            Some(TitleCommandMode::Whole)
+        );
+        assert_eq!(TitleCommandMode::parse("bogus"), None);
+    }
+
+    #[test]
+    fn test_reduce_title_first_word() {
+        assert_eq!(
+            reduce_title("vim src/main.rs", TitleCommandMode::FirstWord),
+            "vim"
+        );
+    }
+
+    #[test]
+    fn test_reduce_title_last_path_arg() {
+        assert_eq!(
+            reduce_title("vim src/main.rs", TitleCommandMode::LastPathArg),
+            "src"
+        );
+        assert_eq!(
+            reduce_title("cat a/b.txt docs/", TitleCommandMode::LastPathArg),
+            "docs"
+        );
+    }
+
+    #[test]
+    fn test_reduce_title_last_path_arg_without_path() {
+        assert_eq!(
+            reduce_title("cargo test --release", TitleCommandMode::LastPathArg),
+            "cargo"
+        );
+    }
+
+    #[test]
+    fn test_reduce_title_whole() {
+        assert_eq!(
+            reduce_title(" vim src/main.rs ", TitleCommandMode::Whole),
+            "vim src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_reduce_title_leaves_paths_and_single_words() {
+        assert_eq!(
+            reduce_title("/home/me/my project", TitleCommandMode::FirstWord),
+            "/home/me/my project"
+        );
+        assert_eq!(reduce_title("htop", TitleCommandMode::LastPathArg), "htop");
+    }
+
+    fn labels() -> Vec<(String, String)> {
+        vec![
+            ("/mnt/data".to_string(), "data-disk".to_string()),
+            ("/".to_string(), "root".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_mount_label_mapped() {
+        assert_eq!(mount_label("/mnt/data", &labels()), Some("data-disk"));
+        assert_eq!(mount_label("/mnt/data/", &labels()), Some("data-disk"));
+        assert_eq!(mount_label("/", &labels()), Some("root"));
+    }
+
+    #[test]
+    fn test_mount_label_unmapped() {
+        assert_eq!(mount_label("/mnt/data/photos", &labels()), None);
+        assert_eq!(mount_label("/mnt", &labels()), None);
+        assert_eq!(mount_label("/mnt/data", &[]), None);
+    }
+
+    #[test]
+    fn test_repo_subpath() {
+        assert_eq!(
+            repo_subpath("/work/mono/services/api", "/work/mono"),
+            Some("services/api".to_string())
+        );
+        assert_eq!(
+            repo_subpath("/work/mono/web/", "/work/mono/"),
+            Some("web".to_string())
+        );
+        assert_eq!(repo_subpath("/work/mono", "/work/mono"), None);
+        assert_eq!(repo_subpath("/work/monolith/api", "/work/mono"), None);
+    }
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("/work/mono", "/work/mono/"));
+        assert!(is_within("/work/mono/services/api", "/work/mono"));
+        assert!(!is_within("/work/monolith/api", "/work/mono"));
+        assert!(!is_within("/work", "/work/mono"));
+    }
+
+    #[test]
+    fn test_family_name_from_structured_path() {
+        let base = "/home/me/work";
+        assert_eq!(
+            family_name("/home/me/work/acme/api/src", base, 1),
+            Some("acme".to_string())
+        );
+        assert_eq!(
+            family_name("/home/me/work/acme/api", "/home/me/work/", 2),
+            Some("api".to_string())
+        );
+        // Without a base the segment counts from the root
+        assert_eq!(
+            family_name("/home/me/work/acme/api", "", 4),
+            Some("acme".to_string())
+        );
+    }
+
+    #[test]
+    fn test_family_name_path_too_short() {
+        let base = "/home/me/work";
+        assert_eq!(family_name("/home/me/work", base, 1), None);
+        assert_eq!(family_name("/home/me/work/acme", base, 2), None);
+        assert_eq!(family_name("/srv/acme/api", base, 1), None);
+        assert_eq!(family_name("/home/me/work/acme/api", base, 0), None);
+    }
+
+    #[test]
+    fn test_resolve_name_folder_prefers_repo_subpath() {
+        let inputs = NameInputs {
+            repo_subpath: Some("services/api".to_string()),
+            ..sample_inputs()
+        };
+        assert_eq!(
+            resolve_name(&[NameSource::Folder], &inputs),
+            Some("services/api".to_string())
+        );
+        assert_eq!(
+            resolve_name(&[NameSource::RepoRoot], &inputs),
+            Some("monorepo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_name_folder_prefers_mount_label() {
+        let inputs = |cwd: &str| NameInputs {
+            pane: Some(PaneContext {
+                cwd: cwd.to_string(),
+                ..Default::default()
+            }),
+            mount_labels: labels(),
+            ..Default::default()
+        };
+        let sources = [NameSource::Folder];
+        assert_eq!(
+            resolve_name(&sources, &inputs("/mnt/data")),
+            Some("data-disk".to_string())
+        );
+        assert_eq!(
+            resolve_name(&sources, &inputs("/mnt/data/photos")),
+            Some("photos".to_string())
+        );
+    }
 }
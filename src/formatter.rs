@@ -3,7 +3,14 @@
 //! This module provides configuration and functions for formatting tab names
 //! from folder paths and git branch names, with support for intelligent truncation.
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::context::{command_name, NameSource, TitleCommandMode};
+use crate::git::{GitStatus, Vcs};
 
 /// Configuration for tab name formatting.
 ///
@@ -29,6 +36,107 @@ use std::collections::BTreeMap;
 /// | `branch_suffix_len` | 4 |
 /// | `separator` | `":"` |
 /// | `show_branch` | `true` |
+/// | `show_busy` | `false` |
+/// | `busy_symbol` | `"⋯"` |
+/// | `collapse_separators` | `false` |
+/// | `name_sources` | `folder,title` |
+/// | `title_command_mode` | `whole` |
+/// | `show_change_counts` | `false` |
+/// | `staged_symbol` | `"+"` |
+/// | `unstaged_symbol` | `"~"` |
+/// | `reset_to` | `keep` |
+/// | `branch_folder_override` | _(empty)_ |
+/// | `group_size` | `0` (disabled) |
+/// | `smart_truncate` | `false` |
+/// | `truncate_delimiters` | `"_-/."` |
+/// | `show_clean` | `false` |
+/// | `clean_symbol` | `"✓"` |
+/// | `empty_folder_fallback` | `original_basename` |
+/// | `truncated_branch_suffix` | _(empty)_ |
+/// | `pr_format` | `"PR#{n}"` |
+/// | `collapse_branch_folder_overlap` | `false` |
+/// | `strip_copy_suffixes` | `false` |
+/// | `keep_numeric_suffix` | `false` |
+/// | `narrow_threshold` | `0` (disabled) |
+/// | `narrow_max_len` | 6 |
+/// | `folder_fixed_width` | `0` (disabled) |
+/// | `branch_fixed_width` | `0` (disabled) |
+/// | `skip_commands` | _(empty)_ |
+/// | `name_command` | _(empty)_ (disabled) |
+/// | `grapheme_mode` | `false` |
+/// | `show_nonprimary_remote` | `false` |
+/// | `primary_remote` | `"origin"` |
+/// | `rename_on` | `any_update` |
+/// | `title_trailing_separators` | `—,–,•` |
+/// | `branch_word_max` | `0` (disabled) |
+/// | `mount_labels` | _(empty)_ |
+/// | `show_dir_freshness` | `false` |
+/// | `fresh_minutes` | 10 |
+/// | `fresh_symbol` | `"~"` |
+/// | `branch_space_replacement` | `"-"` |
+/// | `sticky_cwd_ms` | 0 (disabled) |
+/// | `compact_status` | `false` |
+/// | `tab_prefix` | _(empty)_ |
+/// | `total_max_len` | 0 (no cap) |
+/// | `initial_name` | _(empty)_ |
+/// | `show_kube_context` | `false` |
+/// | `kube_symbol` | `"⎈"` |
+/// | `folder_keep_keywords` | _(empty)_ |
+/// | `monorepo_mode` | `false` |
+/// | `monorepo_min_tabs` | 3 |
+/// | `git_binary` | `"git"` |
+/// | `git_extra_args` | _(empty)_ |
+/// | `vcs_order` | `git` |
+/// | `detect_vcs` | `false` |
+/// | `show_ahead_behind` | `false` |
+/// | `compact_ahead_behind` | `true` |
+/// | `rename_session` | `false` |
+/// | `new_pane_name` | _(empty)_ |
+/// | `folder_truncate_mode` | `middle` |
+/// | `branch_truncate_mode` | `middle` |
+/// | `ellipsis` | `…` |
+/// | `strip_folder_prefix_from_branch` | `false` |
+/// | `branch_abbreviations` | _(empty)_ |
+/// | `name_prefix` | _(empty)_ |
+/// | `name_suffix` | _(empty)_ |
+/// | `show_dirty` | `false` |
+/// | `dirty_marker` | `*` |
+/// | `name_ttl_secs` | `0` (disabled) |
+/// | `machine_format` | _(empty)_ |
+/// | `slow_repos` | _(empty)_ |
+/// | `git_cache_ttl_ms` | `5000` |
+/// | `settle_ms` | `0` (disabled) |
+/// | `eager_folder` | `false` |
+/// | `folder_case` | `none` |
+/// | `respect_manual_rename` | `true` |
+/// | `show_forge` | `false` |
+/// | `family_segment` | `0` (disabled) |
+/// | `family_base` | _(empty)_ |
+/// | `name_template` | _(empty)_ |
+/// | `strip_emoji` | `false` |
+/// | `base_branch` | _(empty)_ |
+/// | `show_unc_server` | `false` |
+/// | `single_rename` | `false` |
+/// | `single_rename_timeout_ms` | `300` |
+/// | `folder_depth` | `1` |
+/// | `show_icon` | `false` |
+/// | `project_icons` | Nerd Font glyphs for `Cargo.toml`, `package.json`, `go.mod`, `pyproject.toml` |
+/// | `show_shell_icon` | `false` |
+/// | `shell_icons` | Nerd Font glyphs for `bash`, `zsh`, `fish`, `nu` |
+/// | `show_layout` | `false` |
+/// | `branch_case` | `none` |
+/// | `folder_replace` | _(empty)_ |
+/// | `hide_default_branches` | `main,master` |
+/// | `non_vcs_symbol` | _(empty)_ |
+/// | `git_branch_command` | _(empty, built-in script)_ |
+/// | `separator_wide` | _(empty, uses `separator`)_ |
+/// | `separator_narrow` | _(empty, uses `separator`)_ |
+/// | `separator_width_threshold` | `0` (disabled) |
+/// | `max_badges` | `0` (no cap) |
+/// | `badge_priority` | `status,dirty,kube,forge,server,base_ahead,non_vcs,icon,shell,layout` |
+/// | `attention_symbol` | _(empty)_ |
+/// | `title_separators` | `": ,:"` |
+/// | `stale_days` | `0` (off) |
 #[derive(Debug, Clone)]
 pub struct FormatterConfig {
     /// Maximum length for the folder name display.
@@ -47,6 +155,251 @@ pub struct FormatterConfig {
     pub separator: String,
     /// Whether to include the git branch in the tab name.
     pub show_branch: bool,
+    /// Whether to show an interim name while a git lookup is in flight.
+    pub show_busy: bool,
+    /// Symbol appended to the interim folder-only name while git is pending.
+    pub busy_symbol: String,
+    /// Whether to collapse repeated separators and spaces in the final name,
+    /// including where a decoration meets it.
+    pub collapse_separators: bool,
+    /// Ordered sources tried when picking the base name of a tab.
+    pub name_sources: Vec<NameSource>,
+    /// How a command-style pane title (`vim src/main.rs`) is reduced.
+    pub title_command_mode: TitleCommandMode,
+    /// Whether to show staged/unstaged change counts after the branch.
+    pub show_change_counts: bool,
+    /// Symbol placed before the staged change count.
+    pub staged_symbol: String,
+    /// Symbol placed before the unstaged change count.
+    pub unstaged_symbol: String,
+    /// What to rename the tab to when no name can be resolved at all.
+    pub reset_to: ResetMode,
+    /// Branch patterns whose folder segment is replaced by a fixed label.
+    pub branch_folder_override: Vec<(String, String)>,
+    /// Number of tabs per position group; `0` disables the group prefix.
+    pub group_size: usize,
+    /// Whether to truncate at a delimiter boundary instead of prefix/suffix.
+    pub smart_truncate: bool,
+    /// Characters treated as token boundaries by `smart_truncate`.
+    pub truncate_delimiters: String,
+    /// Whether to mark a clean repository with `clean_symbol`.
+    pub show_clean: bool,
+    /// Symbol appended after the branch when the working tree is clean.
+    pub clean_symbol: String,
+    /// What to show when the folder display would otherwise be empty.
+    pub empty_folder_fallback: EmptyFolderFallback,
+    /// Text appended after the branch only when the branch was truncated.
+    pub truncated_branch_suffix: String,
+    /// Template for pull/merge request refs, with `{n}` for the number.
+    /// Empty leaves such refs as they are.
+    pub pr_format: String,
+    /// Whether to drop a trailing branch segment that repeats the folder name.
+    pub collapse_branch_folder_overlap: bool,
+    /// Whether to strip copy suffixes like `-copy` or ` (1)` from the folder.
+    pub strip_copy_suffixes: bool,
+    /// Whether `strip_copy_suffixes` keeps a trailing `-N` such as `python-3`.
+    pub keep_numeric_suffix: bool,
+    /// Width in columns below which the narrow profile applies; `0` disables it.
+    pub narrow_threshold: usize,
+    /// Folder and branch length cap used by the narrow profile.
+    pub narrow_max_len: usize,
+    /// Exact display width the folder is padded or truncated to; `0` disables.
+    pub folder_fixed_width: usize,
+    /// Exact display width the branch is padded or truncated to; `0` disables.
+    pub branch_fixed_width: usize,
+    /// Programs whose panes never rename their tab, e.g. `htop`.
+    pub skip_commands: Vec<String>,
+    /// Shell template whose first output line becomes the tab name; empty
+    /// disables it. `{path}` and `{branch}` are filled in after the git lookup.
+    pub name_command: String,
+    /// Whether truncation counts grapheme clusters instead of `char`s, so
+    /// flags and combining sequences are never split.
+    pub grapheme_mode: bool,
+    /// Whether to append `@<remote>` when the branch tracks a non-primary remote.
+    pub show_nonprimary_remote: bool,
+    /// The remote that is not called out by `show_nonprimary_remote`.
+    pub primary_remote: String,
+    /// Which pane events re-evaluate a tab's name.
+    pub rename_on: RenameOn,
+    /// Separators after which trailing title metadata is dropped before the
+    /// cwd is extracted, e.g. the `— 80×24` in `~/proj — 80×24`.
+    pub title_trailing_separators: Vec<String>,
+    /// Characters kept from each `-`/`/`-delimited branch word; `0` disables.
+    pub branch_word_max: usize,
+    /// Labels used instead of the basename when the cwd is exactly one of
+    /// these mount points.
+    pub mount_labels: Vec<(String, String)>,
+    /// Whether to mark a cwd that was modified within `fresh_minutes`.
+    pub show_dir_freshness: bool,
+    /// How recently the cwd must have changed to count as fresh.
+    pub fresh_minutes: usize,
+    /// Symbol appended to the folder of a fresh cwd.
+    pub fresh_symbol: String,
+    /// Replaces spaces within the branch, e.g. in jj bookmarks. `" "` keeps them.
+    pub branch_space_replacement: String,
+    /// How long a tab keeps its last cwd while its pane shows a non-path
+    /// title, in milliseconds. 0 switches to the title straight away.
+    pub sticky_cwd_ms: u64,
+    /// Whether to summarize the status as one badge, e.g. `⇡*`.
+    pub compact_status: bool,
+    /// Text prepended to every computed name, e.g. a session-level label.
+    pub tab_prefix: String,
+    /// Maximum length of the whole name, prefix included. 0 means no cap.
+    pub total_max_len: usize,
+    /// Placeholder shown on the active tab once the plugin loads. Empty
+    /// leaves the tab alone until the first pane update.
+    pub initial_name: String,
+    /// Whether to prepend the active kubernetes context as a badge.
+    pub show_kube_context: bool,
+    /// Symbol starting the kubernetes context badge.
+    pub kube_symbol: String,
+    /// Keywords kept visible when truncating the folder; the first one found
+    /// centers the retained window.
+    pub folder_keep_keywords: Vec<String>,
+    /// Whether to show the path within the repo instead of the folder once
+    /// enough tabs share a repo.
+    pub monorepo_mode: bool,
+    /// How many tabs must share a repo before `monorepo_mode` applies.
+    pub monorepo_min_tabs: usize,
+    /// The git executable to run, by name or path.
+    pub git_binary: String,
+    /// Arguments passed to git before every subcommand, e.g. `-c safe.directory=*`.
+    pub git_extra_args: Vec<String>,
+    /// Version control systems tried in order until one reports a branch.
+    pub vcs_order: Vec<Vcs>,
+    /// Whether to probe for `.jj`, `.hg` or `.git` first and look the branch
+    /// up with the system found, instead of following `vcs_order`.
+    pub detect_vcs: bool,
+    /// Whether to show commits ahead of and behind the upstream, e.g. `↑2↓1`.
+    pub show_ahead_behind: bool,
+    /// Whether to leave out zero ahead/behind counts instead of always
+    /// showing both.
+    pub compact_ahead_behind: bool,
+    /// Whether to rename the session after the active tab's folder.
+    pub rename_session: bool,
+    /// Name for a new tab whose only pane has no title yet. Empty leaves
+    /// the tab to the usual fallback.
+    pub new_pane_name: String,
+    /// Which part of an overlong folder name is cut.
+    pub folder_truncate_mode: TruncateMode,
+    /// Which part of an overlong branch name is cut.
+    pub branch_truncate_mode: TruncateMode,
+    /// Marker inserted where truncation cut text, e.g. `..` for fonts
+    /// without the `…` glyph.
+    pub ellipsis: String,
+    /// Drop a leading `<folder>-` or `<folder>/` from the branch, so
+    /// `proj:proj-feature` becomes `proj:feature`.
+    pub strip_folder_prefix_from_branch: bool,
+    /// `word=abbr` pairs applied to the words of a branch longer than
+    /// `branch_max_len`, e.g. `authentication=auth`.
+    pub branch_abbreviations: Vec<(String, String)>,
+    /// Text placed before every formatted name, never truncated.
+    pub name_prefix: String,
+    /// Text placed after every formatted name, never truncated.
+    pub name_suffix: String,
+    /// Whether to mark a working tree with uncommitted changes.
+    pub show_dirty: bool,
+    /// Marker appended to the branch of a dirty working tree.
+    pub dirty_marker: String,
+    /// Seconds between re-renders of every tab from its cached cwd, so
+    /// time-based details stay current. `0` disables the refresh.
+    pub name_ttl_secs: u64,
+    /// Template such as `{folder}|{branch}|{dirty}` that replaces all human
+    /// formatting with untruncated values for scripts. Empty disables it.
+    pub machine_format: String,
+    /// Paths whose cwds, at or below them, never get a branch lookup and
+    /// show the folder only.
+    pub slow_repos: Vec<String>,
+    /// How long a finished branch lookup is reused for the same cwd, in
+    /// milliseconds. `0` disables the cache.
+    pub git_cache_ttl_ms: u64,
+    /// Quiet period, in milliseconds, that any naming-relevant change (a
+    /// title, cwd or focus change) waits for before it is applied, so a burst
+    /// of mixed changes settles to its latest state. `0` applies every change
+    /// immediately.
+    pub settle_ms: u64,
+    /// Whether a new cwd is named from its folder right away, before the
+    /// branch lookup returns.
+    pub eager_folder: bool,
+    /// Case transform applied to the folder before truncation.
+    pub folder_case: Case,
+    /// Whether a tab the user renamed keeps that name until its cwd changes.
+    pub respect_manual_rename: bool,
+    /// Whether to prepend a badge naming the forge that hosts `origin`.
+    pub show_forge: bool,
+    /// One-based index of the path component, below `family_base`, whose
+    /// name prefixes the tab as its project family; `0` disables it.
+    pub family_segment: usize,
+    /// Directory `family_segment` counts from; empty counts from `/`.
+    pub family_base: String,
+    /// Layout such as `{index}:{folder}@{branch}` replacing the
+    /// `folder{separator}branch` layout. Empty keeps the default layout.
+    pub name_template: String,
+    /// Whether to remove emoji from a pane title before it is used as a name.
+    pub strip_emoji: bool,
+    /// Local branch to count the commits made since diverging from, shown
+    /// after the name as `↑7`. Empty disables the count.
+    pub base_branch: String,
+    /// Whether to prepend the server of a Windows UNC cwd as a badge.
+    pub show_unc_server: bool,
+    /// Whether to skip the interim folder-only name and rename once, when the
+    /// branch lookup returns, unless it takes `single_rename_timeout_ms`.
+    pub single_rename: bool,
+    /// Milliseconds `single_rename` waits for the branch lookup before naming
+    /// the tab from its folder anyway.
+    pub single_rename_timeout_ms: u64,
+    /// Number of trailing path components shown as the folder, e.g. `2` for
+    /// `backend/src`.
+    pub folder_depth: usize,
+    /// Whether to prepend an icon for the project type found in the cwd.
+    pub show_icon: bool,
+    /// Marker files checked in order for `show_icon`, each with the icon its
+    /// project type gets, e.g. `Cargo.toml` for Rust.
+    pub project_icons: Vec<(String, String)>,
+    /// Whether to prepend an icon for the shell running in the focused pane.
+    pub show_shell_icon: bool,
+    /// Shell program names, each with the icon `show_shell_icon` shows for it.
+    pub shell_icons: Vec<(String, String)>,
+    /// Whether to append a badge for how the tab's panes are arranged.
+    pub show_layout: bool,
+    /// Case transform applied to the branch before truncation.
+    pub branch_case: Case,
+    /// `from=to` replacements applied to the folder before truncation, e.g.
+    /// `_` to a space.
+    pub folder_replace: Vec<(String, String)>,
+    /// Branches shown as just the folder, since naming them on every tab is
+    /// noise. Matched exactly; empty shows every branch.
+    pub hide_default_branches: Vec<String>,
+    /// Marker appended when the lookup confirmed the cwd is under no version
+    /// control, as opposed to a repo without a branch. Empty shows nothing.
+    pub non_vcs_symbol: String,
+    /// A bash script run in place of the built-in git lookup, given only the
+    /// path as `$1`. Empty uses the built-in script.
+    pub git_branch_command: String,
+    /// Separator used at or above `separator_width_threshold` columns, e.g.
+    /// ` : `. Empty keeps `separator`.
+    pub separator_wide: String,
+    /// Separator used below `separator_width_threshold` columns. Empty keeps
+    /// `separator`.
+    pub separator_narrow: String,
+    /// Width in columns that picks between the wide and narrow separators;
+    /// `0` always uses `separator`.
+    pub separator_width_threshold: usize,
+    /// Most badges shown on a tab; lower-priority ones beyond it are dropped.
+    /// `0` shows them all.
+    pub max_badges: usize,
+    /// Badges from most to least important, deciding which survive
+    /// `max_badges`. Badges left out rank below every listed one.
+    pub badge_priority: Vec<Badge>,
+    /// Marker appended to a background tab whose command finished, until the
+    /// tab is focused. Empty disables it.
+    pub attention_symbol: String,
+    /// Separators a cwd follows in a pane title, tried in order until one
+    /// leads to a path, e.g. `: ` for `zsh: /path`. Kept exactly as written, spaces included.
+    pub title_separators: Vec<String>,
+    /// Days without a commit after which the branch is wrapped in
+    /// [`STALE_BRANCH_STYLE`]. 0 never marks a branch stale.
+    pub stale_days: u64,
 }
 
 impl Default for FormatterConfig {
@@ -60,16 +413,284 @@ impl Default for FormatterConfig {
             branch_suffix_len: 4,
             separator: ":".to_string(),
             show_branch: true,
+            show_busy: false,
+            busy_symbol: "⋯".to_string(),
+            collapse_separators: false,
+            name_sources: vec![NameSource::Folder, NameSource::Title],
+            title_command_mode: TitleCommandMode::Whole,
+            show_change_counts: false,
+            staged_symbol: "+".to_string(),
+            unstaged_symbol: "~".to_string(),
+            reset_to: ResetMode::Keep,
+            branch_folder_override: Vec::new(),
+            group_size: 0,
+            smart_truncate: false,
+            truncate_delimiters: "_-/.".to_string(),
+            show_clean: false,
+            clean_symbol: "✓".to_string(),
+            empty_folder_fallback: EmptyFolderFallback::OriginalBasename,
+            truncated_branch_suffix: String::new(),
+            pr_format: "PR#{n}".to_string(),
+            collapse_branch_folder_overlap: false,
+            strip_copy_suffixes: false,
+            keep_numeric_suffix: false,
+            narrow_threshold: 0,
+            narrow_max_len: 6,
+            folder_fixed_width: 0,
+            branch_fixed_width: 0,
+            skip_commands: Vec::new(),
+            name_command: String::new(),
+            grapheme_mode: false,
+            show_nonprimary_remote: false,
+            primary_remote: "origin".to_string(),
+            rename_on: RenameOn::AnyUpdate,
+            title_trailing_separators: vec!["—".to_string(), "–".to_string(), "•".to_string()],
+            branch_word_max: 0,
+            mount_labels: Vec::new(),
+            show_dir_freshness: false,
+            fresh_minutes: 10,
+            fresh_symbol: "~".to_string(),
+            branch_space_replacement: "-".to_string(),
+            sticky_cwd_ms: 0,
+            compact_status: false,
+            tab_prefix: String::new(),
+            total_max_len: 0,
+            initial_name: String::new(),
+            show_kube_context: false,
+            kube_symbol: "⎈".to_string(),
+            folder_keep_keywords: Vec::new(),
+            monorepo_mode: false,
+            monorepo_min_tabs: 3,
+            git_binary: "git".to_string(),
+            git_extra_args: Vec::new(),
+            vcs_order: vec![Vcs::Git],
+            detect_vcs: false,
+            show_ahead_behind: false,
+            compact_ahead_behind: true,
+            rename_session: false,
+            new_pane_name: String::new(),
+            folder_truncate_mode: TruncateMode::Middle,
+            branch_truncate_mode: TruncateMode::Middle,
+            ellipsis: "…".to_string(),
+            strip_folder_prefix_from_branch: false,
+            branch_abbreviations: Vec::new(),
+            name_prefix: String::new(),
+            name_suffix: String::new(),
+            show_dirty: false,
+            dirty_marker: "*".to_string(),
+            name_ttl_secs: 0,
+            machine_format: String::new(),
+            slow_repos: Vec::new(),
+            git_cache_ttl_ms: 5000,
+            settle_ms: 0,
+            eager_folder: false,
+            folder_case: Case::None,
+            respect_manual_rename: true,
+            show_forge: false,
+            family_segment: 0,
+            family_base: String::new(),
+            name_template: String::new(),
+            strip_emoji: false,
+            base_branch: String::new(),
+            show_unc_server: false,
+            single_rename: false,
+            single_rename_timeout_ms: 300,
+            folder_depth: 1,
+            show_icon: false,
+            project_icons: parse_pairs(DEFAULT_PROJECT_ICONS),
+            show_shell_icon: false,
+            shell_icons: parse_pairs(DEFAULT_SHELL_ICONS),
+            show_layout: false,
+            branch_case: Case::None,
+            folder_replace: Vec::new(),
+            hide_default_branches: vec!["main".to_string(), "master".to_string()],
+            non_vcs_symbol: String::new(),
+            git_branch_command: String::new(),
+            separator_wide: String::new(),
+            separator_narrow: String::new(),
+            separator_width_threshold: 0,
+            max_badges: 0,
+            badge_priority: Badge::ALL.to_vec(),
+            attention_symbol: String::new(),
+            title_separators: vec![": ".to_string(), ":".to_string()],
+            stale_days: 0,
         }
     }
 }
 
+/// Builds a [`FormatterConfig`] with chained setters, starting from the
+/// defaults, e.g. `FormatterConfig::builder().folder_max_len(20).separator(" | ").build()`.
+///
+/// Every field has a setter of the same name. String fields take anything
+/// that converts into a `String`, and list fields any iterator of strings.
+#[derive(Debug, Clone, Default)]
+pub struct FormatterConfigBuilder {
+    config: FormatterConfig,
+}
+
+impl FormatterConfigBuilder {
+    /// Returns the finished configuration.
+    pub fn build(self) -> FormatterConfig {
+        self.config
+    }
+}
+
+/// Generates one [`FormatterConfigBuilder`] setter per field. `value` fields
+/// take the field's type, `into` fields anything that converts into it, and
+/// `list` fields, `Vec<String>`s, any iterator of strings.
+macro_rules! builder_setters {
+    (@setter value $field:ident: $ty:ty) => {
+        #[doc = concat!("Sets `", stringify!($field), "`.")]
+        pub fn $field(mut self, value: $ty) -> Self {
+            self.config.$field = value;
+            self
+        }
+    };
+    (@setter into $field:ident: $ty:ty) => {
+        #[doc = concat!("Sets `", stringify!($field), "` from anything that converts into it.")]
+        pub fn $field(mut self, value: impl Into<$ty>) -> Self {
+            self.config.$field = value.into();
+            self
+        }
+    };
+    (@setter list $field:ident: $ty:ty) => {
+        #[doc = concat!("Sets `", stringify!($field), "` from any list of strings.")]
+        pub fn $field(mut self, items: impl IntoIterator<Item = impl Into<String>>) -> Self {
+            self.config.$field = items.into_iter().map(Into::into).collect();
+            self
+        }
+    };
+    ($($kind:ident $field:ident: $ty:ty,)*) => {
+        impl FormatterConfigBuilder {
+            $(builder_setters!(@setter $kind $field: $ty);)*
+        }
+    };
+}
+
+builder_setters! {
+    value folder_max_len: usize,
+    value folder_prefix_len: usize,
+    value folder_suffix_len: usize,
+    value branch_max_len: usize,
+    value branch_prefix_len: usize,
+    value branch_suffix_len: usize,
+    into separator: String,
+    value show_branch: bool,
+    value show_busy: bool,
+    into busy_symbol: String,
+    value collapse_separators: bool,
+    value name_sources: Vec<NameSource>,
+    value title_command_mode: TitleCommandMode,
+    value show_change_counts: bool,
+    into staged_symbol: String,
+    into unstaged_symbol: String,
+    value reset_to: ResetMode,
+    value branch_folder_override: Vec<(String, String)>,
+    value group_size: usize,
+    value smart_truncate: bool,
+    into truncate_delimiters: String,
+    value show_clean: bool,
+    into clean_symbol: String,
+    value empty_folder_fallback: EmptyFolderFallback,
+    into truncated_branch_suffix: String,
+    into pr_format: String,
+    value collapse_branch_folder_overlap: bool,
+    value strip_copy_suffixes: bool,
+    value keep_numeric_suffix: bool,
+    value narrow_threshold: usize,
+    value narrow_max_len: usize,
+    value folder_fixed_width: usize,
+    value branch_fixed_width: usize,
+    list skip_commands: Vec<String>,
+    into name_command: String,
+    value grapheme_mode: bool,
+    value show_nonprimary_remote: bool,
+    into primary_remote: String,
+    value rename_on: RenameOn,
+    list title_trailing_separators: Vec<String>,
+    value branch_word_max: usize,
+    value mount_labels: Vec<(String, String)>,
+    value show_dir_freshness: bool,
+    value fresh_minutes: usize,
+    into fresh_symbol: String,
+    into branch_space_replacement: String,
+    value sticky_cwd_ms: u64,
+    value compact_status: bool,
+    into tab_prefix: String,
+    value total_max_len: usize,
+    into initial_name: String,
+    value show_kube_context: bool,
+    into kube_symbol: String,
+    list folder_keep_keywords: Vec<String>,
+    value monorepo_mode: bool,
+    value monorepo_min_tabs: usize,
+    into git_binary: String,
+    list git_extra_args: Vec<String>,
+    value vcs_order: Vec<Vcs>,
+    value detect_vcs: bool,
+    value show_ahead_behind: bool,
+    value compact_ahead_behind: bool,
+    value rename_session: bool,
+    into new_pane_name: String,
+    value folder_truncate_mode: TruncateMode,
+    value branch_truncate_mode: TruncateMode,
+    into ellipsis: String,
+    value strip_folder_prefix_from_branch: bool,
+    value branch_abbreviations: Vec<(String, String)>,
+    into name_prefix: String,
+    into name_suffix: String,
+    value show_dirty: bool,
+    into dirty_marker: String,
+    value name_ttl_secs: u64,
+    into machine_format: String,
+    list slow_repos: Vec<String>,
+    value git_cache_ttl_ms: u64,
+    value settle_ms: u64,
+    value eager_folder: bool,
+    value folder_case: Case,
+    value respect_manual_rename: bool,
+    value show_forge: bool,
+    value family_segment: usize,
+    into family_base: String,
+    into name_template: String,
+    value strip_emoji: bool,
+    into base_branch: String,
+    value show_unc_server: bool,
+    value single_rename: bool,
+    value single_rename_timeout_ms: u64,
+    value folder_depth: usize,
+    value show_icon: bool,
+    value project_icons: Vec<(String, String)>,
+    value show_shell_icon: bool,
+    value shell_icons: Vec<(String, String)>,
+    value show_layout: bool,
+    value branch_case: Case,
+    value folder_replace: Vec<(String, String)>,
+    list hide_default_branches: Vec<String>,
+    into non_vcs_symbol: String,
+    into git_branch_command: String,
+    into separator_wide: String,
+    into separator_narrow: String,
+    value separator_width_threshold: usize,
+    value max_badges: usize,
+    value badge_priority: Vec<Badge>,
+    into attention_symbol: String,
+    list title_separators: Vec<String>,
+    value stale_days: u64,
+}
+
 impl FormatterConfig {
+    /// Starts a [`FormatterConfigBuilder`] from the default configuration.
+    pub fn builder() -> FormatterConfigBuilder {
+        FormatterConfigBuilder::default()
+    }
+
     /// Creates a configuration from Zellij plugin settings.
     ///
     /// Parses the plugin configuration map and applies any specified overrides
     /// to the default values. Invalid or unparseable values are silently ignored,
-    /// preserving the defaults.
+    /// preserving the defaults; [`FormatterConfig::try_from_config`] reports
+    /// them instead.
     ///
     /// # Arguments
     ///
@@ -77,6 +698,8 @@ impl FormatterConfig {
     ///
     /// # Supported Keys
     ///
+    /// - `preset` - `minimal`, `full` or `compact`, applied before every other
+    ///   key (see [`apply_preset`])
     /// - `folder_max_len` - Maximum folder name length (usize)
     /// - `folder_prefix_len` - Folder truncation prefix length (usize)
     /// - `folder_suffix_len` - Folder truncation suffix length (usize)
@@ -85,330 +708,5608 @@ impl FormatterConfig {
     /// - `branch_suffix_len` - Branch truncation suffix length (usize)
     /// - `separator` - String between folder and branch
     /// - `show_branch` - `"false"` to hide branch, any other value shows it
+    /// - `show_busy` - `"true"` to show an interim name while git is pending
+    /// - `busy_symbol` - Symbol appended to the interim name
+    /// - `collapse_separators` - `"true"` to collapse doubled separators/spaces
+    /// - `name_sources` - Comma-separated fallback chain of `repo_root`,
+    ///   `folder`, `process`, `title`, `readme_title` and `package_name`
+    /// - `title_command_mode` - `first_word`, `last_path_arg` or `whole`
+    /// - `show_change_counts` - `"true"` to show staged/unstaged counts
+    /// - `staged_symbol` - Symbol before the staged count
+    /// - `unstaged_symbol` - Symbol before the unstaged count
+    /// - `reset_to` - `index`, `blank` or `keep` when nothing resolves
+    /// - `branch_folder_override` - Comma-separated `pattern=label` pairs; `*`
+    ///   in a pattern matches any run of characters
+    /// - `group_size` - Tabs per group for the `G1:` style prefix (usize)
+    /// - `smart_truncate` - `"true"` to cut names at a delimiter boundary
+    /// - `truncate_delimiters` - Characters `smart_truncate` may cut at
+    /// - `show_clean` - `"true"` to mark clean repositories
+    /// - `clean_symbol` - Symbol shown for a clean working tree
+    /// - `empty_folder_fallback` - `original_basename` or a literal shown when
+    ///   the folder display ends up empty
+    /// - `truncated_branch_suffix` - Text appended to a truncated branch
+    /// - `pr_format` - Template for PR refs like `pull/42/merge`; `{n}` is the
+    ///   number, empty disables
+    /// - `collapse_branch_folder_overlap` - `"true"` to turn `auth:pkg/auth`
+    ///   into `auth:pkg`
+    /// - `strip_copy_suffixes` - `"true"` to strip ` (N)`, `-N` and `-copy`
+    ///   from the folder
+    /// - `keep_numeric_suffix` - `"true"` to keep `-N` when stripping copies
+    /// - `narrow_threshold` - Columns below which the narrow profile applies (usize)
+    /// - `narrow_max_len` - Folder and branch cap in the narrow profile (usize)
+    /// - `folder_fixed_width` - Exact folder width in columns (usize)
+    /// - `branch_fixed_width` - Exact branch width in columns (usize)
+    /// - `skip_commands` - Comma-separated programs whose panes never rename
+    /// - `name_command` - Shell template with `{path}` and `{branch}` whose
+    ///   output names the tab
+    /// - `grapheme_mode` - `"true"` to truncate by grapheme cluster
+    /// - `show_nonprimary_remote` - `"true"` to show `@<remote>` for branches
+    ///   tracking a remote other than `primary_remote`
+    /// - `primary_remote` - The remote left unmarked
+    /// - `rename_on` - `any_update` or `focus_change`
+    /// - `title_trailing_separators` - Comma-separated separators that start
+    ///   trailing title metadata; empty disables stripping
+    /// - `branch_word_max` - Characters kept per branch word (usize)
+    /// - `mount_labels` - Comma-separated `path=label` pairs for mount points
+    /// - `show_dir_freshness` - `"true"` to mark recently modified cwds
+    /// - `fresh_minutes` - Minutes within which a cwd counts as fresh (usize)
+    /// - `fresh_symbol` - Symbol appended to a fresh folder
+    /// - `branch_space_replacement` - Replacement for spaces in the branch
+    /// - `sticky_cwd_ms` - Milliseconds to keep the last cwd over a non-path title (u64)
+    /// - `compact_status` - `"true"` to show one ahead/behind/dirty badge
+    /// - `tab_prefix` - Text prepended to every computed name
+    /// - `total_max_len` - Maximum length of the whole name (usize)
+    /// - `initial_name` - Placeholder name shown on load
+    /// - `show_kube_context` - `"true"` to prepend the kubernetes context
+    /// - `kube_symbol` - Symbol starting the kubernetes context badge
+    /// - `folder_keep_keywords` - Comma-separated keywords kept when truncating the folder
+    /// - `monorepo_mode` - `"true"` to show the subpath when tabs share a repo
+    /// - `monorepo_min_tabs` - Tabs sharing a repo before `monorepo_mode` applies (usize)
+    /// - `git_binary` - The git executable to run
+    /// - `git_extra_args` - Whitespace-separated arguments passed to git first
+    /// - `vcs` - The one system to use: `git`, `hg` or `jj`, or `auto` to probe
+    ///   the cwd for a repository of each
+    /// - `vcs_order` - Comma-separated systems to try in order: `git`, `hg`, `jj`;
+    ///   takes precedence over `vcs`
+    /// - `show_ahead_behind` - `"true"` to show commits ahead/behind the upstream
+    /// - `show_upstream` - Alias of `show_ahead_behind`, used only when it is unset
+    /// - `compact_ahead_behind` - `"false"` to always show both ahead/behind counts
+    /// - `rename_session` - `"true"` to name the session after the active tab's folder
+    /// - `new_pane_name` - Name for a tab whose only pane is still untitled
+    /// - `folder_truncate_mode` - `middle`, `start`, `end` or `hard_cut`
+    /// - `branch_truncate_mode` - `middle`, `start`, `end` or `hard_cut`
+    /// - `ellipsis` - Marker for truncated text
+    /// - `strip_folder_prefix_from_branch` - `"true"` to turn `proj:proj-feature`
+    ///   into `proj:feature`
+    /// - `branch_abbreviations` - Comma-separated `word=abbr` pairs for long branches
+    /// - `name_prefix` - Text before every formatted name
+    /// - `name_suffix` - Text after every formatted name
+    /// - `show_dirty` - `"true"` to mark uncommitted changes
+    /// - `dirty_marker` - Marker for uncommitted changes
+    /// - `name_ttl_secs` - Seconds between periodic re-renders (u64)
+    /// - `machine_format` - Template with `{folder}`, `{branch}` and `{dirty}`
+    /// - `slow_repos` - Comma-separated paths to skip branch lookups under
+    /// - `git_cache_ttl_ms` - Milliseconds to reuse a branch lookup (u64)
+    /// - `settle_ms` - Milliseconds of quiet before a title, cwd or focus
+    ///   change applies (u64)
+    /// - `debounce_ms`, `title_stability_ms` - Deprecated aliases of
+    ///   `settle_ms`, used only when it is unset
+    /// - `eager_folder` - `"true"` to show the folder before the branch lookup returns
+    /// - `folder_case` - `none`, `lower`, `upper`, `title` or `sentence`
+    /// - `respect_manual_rename` - `"false"` to overwrite tabs the user renamed
+    /// - `show_forge` - `"true"` to prepend the forge of `origin`, e.g. `gh`
+    /// - `family_segment` - Path component below `family_base` naming the project family (usize)
+    /// - `family_base` - Directory `family_segment` counts from, e.g. `~/work`
+    /// - `name_template` - Layout with `{folder}`, `{branch}`, `{index}`, `{host}`
+    ///   and `{sep}`
+    /// - `strip_emoji` - `"true"` to drop emoji from titles used as names
+    /// - `base_branch` - Branch to count commits ahead of, e.g. `main`
+    /// - `show_unc_server` - `"true"` to prepend the server of a UNC cwd
+    /// - `single_rename` - `"true"` to rename once, when the branch is known
+    /// - `single_rename_timeout_ms` - Milliseconds to wait for the branch before naming from the folder (u64)
+    /// - `folder_depth` - Trailing path components shown as the folder (usize, at least 1)
+    /// - `show_icon` - `"true"` to prepend an icon for the detected project type
+    /// - `project_icons` - `marker=icon` pairs, checked in order
+    /// - `show_shell_icon` - `"true"` to prepend an icon for the running shell
+    /// - `shell_icons` - `shell=icon` pairs, e.g. `fish=><>`
+    /// - `show_layout` - `"true"` to append a split, stacked or floating badge
+    /// - `branch_case` - `none`, `lower`, `upper`, `title` or `sentence`
+    /// - `folder_replace` - `from=to` pairs replaced in the folder, e.g. `_= ,-= `
+    /// - `hide_default_branches` - Comma-separated branches to leave out of the
+    ///   name; empty shows every branch
+    /// - `non_vcs_symbol` - Marker appended outside any repository
+    /// - `git_branch_command` - Script replacing the built-in git lookup; it
+    ///   gets the path as `$1` and prints the branch, then the repository root
+    /// - `separator_wide` - Separator at or above `separator_width_threshold`
+    /// - `separator_narrow` - Separator below `separator_width_threshold`
+    /// - `separator_width_threshold` - Columns that pick the separator (usize)
+    /// - `max_badges` - Most badges shown on a tab (usize, 0 for no cap)
+    /// - `badge_priority` - Comma-separated badges, most important first
+    /// - `attention_symbol` - Marker for a background tab whose command finished
+    /// - `title_separators` - Comma-separated separators before the cwd in a
+    ///   title, spaces kept
+    /// - `stale_days` - Days since the last commit before the branch is dimmed red (u64, 0 for off)
     pub fn from_config(config: &BTreeMap<String, String>) -> Self {
-        let mut result = Self::default();
+        Self::read_config(config).0
+    }
+
+    /// Creates a configuration like [`FormatterConfig::from_config`], but
+    /// returns every problem found instead of ignoring it.
+    ///
+    /// Unknown keys, values an option cannot parse and contradictory settings
+    /// are all reported, so the plugin can log them on load.
+    pub fn try_from_config(config: &BTreeMap<String, String>) -> Result<Self, Vec<ConfigError>> {
+        match Self::read_config(config) {
+            (result, errors) if errors.is_empty() => Ok(result),
+            (_, errors) => Err(errors),
+        }
+    }
 
-        if let Some(v) = config.get("folder_max_len").and_then(|s| s.parse().ok()) {
+    /// Reads every option, returning the lenient configuration together with
+    /// the problems found along the way.
+    fn read_config(map: &BTreeMap<String, String>) -> (Self, Vec<ConfigError>) {
+        let config = ConfigReader::new(map);
+        let mut result = config
+            .get("preset")
+            .map_or_else(Self::default, |name| apply_preset(name));
+
+        if let Some(v) = config.parse("folder_max_len") {
             result.folder_max_len = v;
         }
-        if let Some(v) = config.get("folder_prefix_len").and_then(|s| s.parse().ok()) {
+        if let Some(v) = config.parse("folder_prefix_len") {
             result.folder_prefix_len = v;
         }
-        if let Some(v) = config.get("folder_suffix_len").and_then(|s| s.parse().ok()) {
+        if let Some(v) = config.parse("folder_suffix_len") {
             result.folder_suffix_len = v;
         }
-        if let Some(v) = config.get("branch_max_len").and_then(|s| s.parse().ok()) {
+        if let Some(v) = config.parse("branch_max_len") {
             result.branch_max_len = v;
         }
-        if let Some(v) = config.get("branch_prefix_len").and_then(|s| s.parse().ok()) {
+        if let Some(v) = config.parse("branch_prefix_len") {
             result.branch_prefix_len = v;
         }
-        if let Some(v) = config.get("branch_suffix_len").and_then(|s| s.parse().ok()) {
+        if let Some(v) = config.parse("branch_suffix_len") {
             result.branch_suffix_len = v;
         }
         if let Some(v) = config.get("separator") {
             result.separator = v.clone();
         }
-        if let Some(v) = config.get("show_branch") {
-            result.show_branch = v != "false";
+        if let Some(v) = config.flag("show_branch") {
+            result.show_branch = v;
+        }
+        if let Some(v) = config.flag("show_busy") {
+            result.show_busy = v;
+        }
+        if let Some(v) = config.get("busy_symbol") {
+            result.busy_symbol = v.clone();
+        }
+        if let Some(v) = config.flag("collapse_separators") {
+            result.collapse_separators = v;
+        }
+        if let Some(v) = config.parse_with("name_sources", |s| {
+            Some(NameSource::parse_list(s)).filter(|sources| !sources.is_empty())
+        }) {
+            result.name_sources = v;
+        }
+        if let Some(v) = config.parse_with("title_command_mode", TitleCommandMode::parse) {
+            result.title_command_mode = v;
+        }
+        if let Some(v) = config.flag("show_change_counts") {
+            result.show_change_counts = v;
+        }
+        if let Some(v) = config.get("staged_symbol") {
+            result.staged_symbol = v.clone();
+        }
+        if let Some(v) = config.get("unstaged_symbol") {
+            result.unstaged_symbol = v.clone();
+        }
+        if let Some(v) = config.parse_with("reset_to", ResetMode::parse) {
+            result.reset_to = v;
+        }
+        if let Some(v) = config.get("branch_folder_override") {
+            result.branch_folder_override = parse_pairs(v);
+        }
+        if let Some(v) = config.parse("group_size") {
+            result.group_size = v;
+        }
+        if let Some(v) = config.flag("smart_truncate") {
+            result.smart_truncate = v;
+        }
+        if let Some(v) = config.get("truncate_delimiters") {
+            result.truncate_delimiters = v.clone();
+        }
+        if let Some(v) = config.flag("show_clean") {
+            result.show_clean = v;
+        }
+        if let Some(v) = config.get("clean_symbol") {
+            result.clean_symbol = v.clone();
+        }
+        if let Some(v) = config.get("empty_folder_fallback") {
+            result.empty_folder_fallback = EmptyFolderFallback::parse(v);
+        }
+        if let Some(v) = config.get("truncated_branch_suffix") {
+            result.truncated_branch_suffix = v.clone();
+        }
+        if let Some(v) = config.get("pr_format") {
+            result.pr_format = v.clone();
+        }
+        if let Some(v) = config.flag("collapse_branch_folder_overlap") {
+            result.collapse_branch_folder_overlap = v;
+        }
+        if let Some(v) = config.flag("strip_copy_suffixes") {
+            result.strip_copy_suffixes = v;
+        }
+        if let Some(v) = config.flag("keep_numeric_suffix") {
+            result.keep_numeric_suffix = v;
+        }
+        if let Some(v) = config.parse("narrow_threshold") {
+            result.narrow_threshold = v;
+        }
+        if let Some(v) = config.parse("narrow_max_len") {
+            result.narrow_max_len = v;
+        }
+        if let Some(v) = config.parse("folder_fixed_width") {
+            result.folder_fixed_width = v;
+        }
+        if let Some(v) = config.parse("branch_fixed_width") {
+            result.branch_fixed_width = v;
+        }
+        if let Some(v) = config.get("skip_commands") {
+            result.skip_commands = parse_list(v);
+        }
+        if let Some(v) = config.get("name_command") {
+            result.name_command = v.clone();
+        }
+        if let Some(v) = config.flag("grapheme_mode") {
+            result.grapheme_mode = v;
+        }
+        if let Some(v) = config.flag("show_nonprimary_remote") {
+            result.show_nonprimary_remote = v;
+        }
+        if let Some(v) = config.get("primary_remote") {
+            result.primary_remote = v.clone();
+        }
+        if let Some(v) = config.parse_with("rename_on", RenameOn::parse) {
+            result.rename_on = v;
+        }
+        if let Some(v) = config.get("title_trailing_separators") {
+            result.title_trailing_separators = parse_list(v);
+        }
+        if let Some(v) = config.parse("branch_word_max") {
+            result.branch_word_max = v;
+        }
+        if let Some(v) = config.get("mount_labels") {
+            result.mount_labels = parse_pairs(v);
+        }
+        if let Some(v) = config.flag("show_dir_freshness") {
+            result.show_dir_freshness = v;
+        }
+        if let Some(v) = config.parse("fresh_minutes") {
+            result.fresh_minutes = v;
+        }
+        if let Some(v) = config.get("fresh_symbol") {
+            result.fresh_symbol = v.clone();
+        }
+        if let Some(v) = config.get("branch_space_replacement") {
+            result.branch_space_replacement = v.clone();
+        }
+        if let Some(v) = config.parse("sticky_cwd_ms") {
+            result.sticky_cwd_ms = v;
+        }
+        if let Some(v) = config.flag("compact_status") {
+            result.compact_status = v;
+        }
+        if let Some(v) = config.get("tab_prefix") {
+            result.tab_prefix = v.clone();
+        }
+        if let Some(v) = config.parse("total_max_len") {
+            result.total_max_len = v;
+        }
+        if let Some(v) = config.get("initial_name") {
+            result.initial_name = v.clone();
+        }
+        if let Some(v) = config.flag("show_kube_context") {
+            result.show_kube_context = v;
+        }
+        if let Some(v) = config.get("kube_symbol") {
+            result.kube_symbol = v.clone();
+        }
+        if let Some(v) = config.get("folder_keep_keywords") {
+            result.folder_keep_keywords = parse_list(v);
+        }
+        if let Some(v) = config.flag("monorepo_mode") {
+            result.monorepo_mode = v;
+        }
+        if let Some(v) = config.parse("monorepo_min_tabs") {
+            result.monorepo_min_tabs = v;
+        }
+        if let Some(v) = config.get("git_binary").filter(|v| !v.trim().is_empty()) {
+            result.git_binary = v.trim().to_string();
+        }
+        if let Some(v) = config.get("git_extra_args") {
+            result.git_extra_args = v.split_whitespace().map(str::to_string).collect();
+        }
+        let ahead_behind: Vec<bool> = ["show_ahead_behind", "show_upstream"]
+            .iter()
+            .filter_map(|key| config.flag(key))
+            .collect();
+        if let Some(v) = ahead_behind.first() {
+            result.show_ahead_behind = *v;
+        }
+        if let Some(v) = config.flag("compact_ahead_behind") {
+            result.compact_ahead_behind = v;
+        }
+        if let Some(v) = config.flag("rename_session") {
+            result.rename_session = v;
+        }
+        if let Some(v) = config.get("new_pane_name") {
+            result.new_pane_name = v.clone();
+        }
+        if let Some(v) = config.parse_with("folder_truncate_mode", TruncateMode::parse) {
+            result.folder_truncate_mode = v;
+        }
+        if let Some(v) = config.parse_with("branch_truncate_mode", TruncateMode::parse) {
+            result.branch_truncate_mode = v;
+        }
+        if let Some(v) = config.get("ellipsis") {
+            result.ellipsis = v.clone();
+        }
+        if let Some(v) = config.flag("strip_folder_prefix_from_branch") {
+            result.strip_folder_prefix_from_branch = v;
+        }
+        if let Some(v) = config.get("branch_abbreviations") {
+            result.branch_abbreviations = parse_pairs(v);
+        }
+        if let Some(v) = config.get("name_prefix") {
+            result.name_prefix = v.clone();
+        }
+        if let Some(v) = config.get("name_suffix") {
+            result.name_suffix = v.clone();
+        }
+        if let Some(v) = config.flag("show_dirty") {
+            result.show_dirty = v;
+        }
+        if let Some(v) = config.get("dirty_marker") {
+            result.dirty_marker = v.clone();
+        }
+        if let Some(v) = config.parse("name_ttl_secs") {
+            result.name_ttl_secs = v;
+        }
+        if let Some(v) = config.get("machine_format") {
+            result.machine_format = v.clone();
+        }
+        if let Some(v) = config.get("slow_repos") {
+            result.slow_repos = parse_list(v);
+        }
+        if let Some(v) = config.parse("git_cache_ttl_ms") {
+            result.git_cache_ttl_ms = v;
+        }
+        // The older debounce keys all map onto the one settle period
+        let settle: Vec<u64> = ["settle_ms", "debounce_ms", "title_stability_ms"]
+            .iter()
+            .filter_map(|key| config.parse(key))
+            .collect();
+        if let Some(v) = settle.first() {
+            result.settle_ms = *v;
+        }
+        if let Some(v) = config.flag("eager_folder") {
+            result.eager_folder = v;
+        }
+        if let Some(v) = config.parse_with("folder_case", Case::parse) {
+            result.folder_case = v;
+        }
+        if let Some(v) = config.flag("respect_manual_rename") {
+            result.respect_manual_rename = v;
+        }
+        if let Some(v) = config.flag("show_forge") {
+            result.show_forge = v;
+        }
+        if let Some(v) = config.parse("family_segment") {
+            result.family_segment = v;
+        }
+        if let Some(v) = config.get("family_base") {
+            result.family_base = v.clone();
+        }
+        if let Some(v) = config.get("name_template") {
+            result.name_template = v.clone();
+        }
+        if let Some(v) = config.flag("strip_emoji") {
+            result.strip_emoji = v;
+        }
+        if let Some(v) = config.get("base_branch") {
+            result.base_branch = v.trim().to_string();
+        }
+        if let Some(v) = config.flag("show_unc_server") {
+            result.show_unc_server = v;
+        }
+        if let Some(v) = config.flag("single_rename") {
+            result.single_rename = v;
+        }
+        if let Some(v) = config.parse("single_rename_timeout_ms") {
+            result.single_rename_timeout_ms = v;
+        }
+        if let Some(v) = config.parse_with("folder_depth", |s| s.parse().ok().filter(|v| *v > 0)) {
+            result.folder_depth = v;
+        }
+        if let Some(v) = config.flag("show_icon") {
+            result.show_icon = v;
+        }
+        if let Some(v) = config.get("project_icons") {
+            result.project_icons = parse_pairs(v);
+        }
+        if let Some(v) = config.flag("show_shell_icon") {
+            result.show_shell_icon = v;
+        }
+        if let Some(v) = config.get("shell_icons") {
+            result.shell_icons = parse_pairs(v);
+        }
+        if let Some(v) = config.flag("show_layout") {
+            result.show_layout = v;
+        }
+        if let Some(v) = config.parse_with("branch_case", Case::parse) {
+            result.branch_case = v;
+        }
+        if let Some(v) = config.get("folder_replace") {
+            result.folder_replace = parse_replacements(v);
+        }
+        if let Some(v) = config.get("hide_default_branches") {
+            result.hide_default_branches = parse_list(v);
+        }
+        if let Some(v) = config.get("vcs").map(|v| v.trim()) {
+            if v == "auto" {
+                result.detect_vcs = true;
+            } else if let Some(vcs) = Vcs::parse(v) {
+                result.vcs_order = vec![vcs];
+            } else {
+                config.reject("vcs");
+            }
+        }
+        if let Some(v) = config.get("non_vcs_symbol") {
+            result.non_vcs_symbol = v.clone();
+        }
+        if let Some(v) = config.get("git_branch_command") {
+            result.git_branch_command = v.trim().to_string();
+        }
+        if let Some(v) = config.get("separator_wide") {
+            result.separator_wide = v.clone();
+        }
+        if let Some(v) = config.get("separator_narrow") {
+            result.separator_narrow = v.clone();
+        }
+        if let Some(v) = config.parse("separator_width_threshold") {
+            result.separator_width_threshold = v;
+        }
+        if let Some(v) = config.parse("max_badges") {
+            result.max_badges = v;
+        }
+        if let Some(v) = config.parse_with("badge_priority", |s| {
+            Some(Badge::parse_list(s)).filter(|order| !order.is_empty())
+        }) {
+            result.badge_priority = v;
+        }
+        if let Some(v) = config.get("attention_symbol") {
+            result.attention_symbol = v.clone();
+        }
+        if let Some(v) = config.get("title_separators") {
+            result.title_separators = v
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(v) = config.parse("stale_days") {
+            result.stale_days = v;
+        }
+        if let Some(v) = config.parse_with("vcs_order", |s| {
+            Some(Vcs::parse_list(s)).filter(|order| !order.is_empty())
+        }) {
+            result.vcs_order = v;
         }
 
-        result
+        let mut errors = config.finish();
+        errors.extend(result.contradictions());
+        (result, errors)
     }
-}
 
-/// Truncates a string using a prefix + ellipsis + suffix strategy.
-///
-/// If the string fits within `max_len`, it is returned unchanged. Otherwise,
-/// the string is truncated by keeping `prefix_len` characters from the start
-/// and `suffix_len` characters from the end, joined by an ellipsis (`…`).
-///
-/// # Arguments
-///
-/// * `s` - The string to truncate
-/// * `max_len` - Maximum allowed length
-/// * `prefix_len` - Characters to keep from the beginning
-/// * `suffix_len` - Characters to keep from the end
-///
-/// # Returns
-///
-/// The original string if it fits, or a truncated version with an ellipsis.
-fn truncate(s: &str, max_len: usize, prefix_len: usize, suffix_len: usize) -> String {
-    let char_count = s.chars().count();
+    /// Returns the settings that cannot all hold at once.
+    fn contradictions(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        if self.folder_prefix_len + self.folder_suffix_len > self.folder_max_len {
+            errors.push(ConfigError::Contradiction(format!(
+                "folder_prefix_len + folder_suffix_len ({} + {}) exceeds folder_max_len ({})",
+                self.folder_prefix_len, self.folder_suffix_len, self.folder_max_len
+            )));
+        }
+        if self.branch_prefix_len + self.branch_suffix_len > self.branch_max_len {
+            errors.push(ConfigError::Contradiction(format!(
+                "branch_prefix_len + branch_suffix_len ({} + {}) exceeds branch_max_len ({})",
+                self.branch_prefix_len, self.branch_suffix_len, self.branch_max_len
+            )));
+        }
+        errors
+    }
 
-    if char_count <= max_len {
-        return s.to_string();
+    /// Returns whether any enabled setting requires a git lookup.
+    ///
+    /// When this is `false` the plugin names tabs from the folder alone and
+    /// never spawns a git command.
+    pub fn needs_git(&self) -> bool {
+        self.show_branch
+            || self.name_sources.contains(&NameSource::RepoRoot)
+            || !self.name_command.is_empty()
+            || self.monorepo_mode
+            || self.machine_format.contains("{branch}")
+            || self.name_template.contains("{branch}")
     }
 
-    // If no prefix/suffix specified, just take first max_len chars
-    if prefix_len == 0 && suffix_len == 0 {
-        return s.chars().take(max_len).collect();
+    /// Returns the system to try after `vcs` failed to report a branch, if any.
+    pub fn next_vcs(&self, vcs: Vcs) -> Option<Vcs> {
+        let index = self.vcs_order.iter().position(|v| *v == vcs)?;
+        self.vcs_order.get(index + 1).copied()
     }
 
-    // Ensure we have room for ellipsis
-    let ellipsis = '…';
-    let needed = prefix_len + 1 + suffix_len; // prefix + ellipsis + suffix
+    /// Returns whether the working tree status must be read alongside the branch.
+    ///
+    /// Marking a clean repo needs the status even though nothing is shown for
+    /// changes, since only the status tells a clean repo from a dirty one.
+    pub fn needs_status(&self) -> bool {
+        self.show_change_counts
+            || self.show_clean
+            || self.compact_status
+            || self.show_ahead_behind
+            || self.show_dirty
+            || self.machine_format.contains("{dirty}")
+            || self.stale_days > 0
+    }
 
-    if needed > max_len || prefix_len + suffix_len >= char_count {
-        // Just take what we can
-        return s.chars().take(max_len).collect();
+    /// Returns whether a pane running `command` must leave its tab's name alone.
+    ///
+    /// Matches the program name only, so `/usr/bin/htop -d 10` matches `htop`.
+    pub fn skips_command(&self, command: Option<&str>) -> bool {
+        command
+            .and_then(command_name)
+            .is_some_and(|name| self.skip_commands.iter().any(|s| s == name))
     }
 
-    let prefix: String = s.chars().take(prefix_len).collect();
-    let suffix: String = s.chars().skip(char_count - suffix_len).collect();
+    /// Returns whether `cols` is narrow enough for the narrow profile.
+    ///
+    /// An unknown width is never narrow.
+    pub fn is_narrow(&self, cols: Option<usize>) -> bool {
+        self.narrow_threshold > 0 && cols.is_some_and(|c| c < self.narrow_threshold)
+    }
 
-    format!("{}{}{}", prefix, ellipsis, suffix)
+    /// Returns whether `cols` is below `separator_width_threshold`, so the
+    /// narrow separator applies.
+    ///
+    /// An unknown width is never narrow.
+    pub fn is_separator_narrow(&self, cols: Option<usize>) -> bool {
+        self.separator_width_threshold > 0
+            && cols.is_some_and(|c| c < self.separator_width_threshold)
+    }
+
+    /// Returns the configuration to format with at the given width.
+    ///
+    /// Below `narrow_threshold` columns, folder and branch lengths are capped
+    /// at `narrow_max_len`, split evenly around the ellipsis. With
+    /// `separator_width_threshold`, the separator is `separator_wide` or
+    /// `separator_narrow` depending on the side of it `cols` is on.
+    pub fn for_width(&self, cols: Option<usize>) -> Cow<'_, Self> {
+        let separator = if self.separator_width_threshold == 0 {
+            ""
+        } else if self.is_separator_narrow(cols) {
+            &self.separator_narrow
+        } else {
+            &self.separator_wide
+        };
+        let mut config = Cow::Borrowed(self);
+        if !separator.is_empty() && separator != self.separator {
+            config.to_mut().separator = separator.to_string();
+        }
+        if !self.is_narrow(cols) {
+            return config;
+        }
+
+        // Keep one column for the ellipsis and split the rest around it
+        let split = |max_len: usize| {
+            let kept = max_len.saturating_sub(1);
+            (max_len, kept - kept / 2, kept / 2)
+        };
+
+        let narrow = config.to_mut();
+        (
+            narrow.folder_max_len,
+            narrow.folder_prefix_len,
+            narrow.folder_suffix_len,
+        ) = split(self.folder_max_len.min(self.narrow_max_len));
+        (
+            narrow.branch_max_len,
+            narrow.branch_prefix_len,
+            narrow.branch_suffix_len,
+        ) = split(self.branch_max_len.min(self.narrow_max_len));
+        config
+    }
 }
 
-/// Formats a tab name from a folder name and optional git branch.
-///
-/// Applies truncation rules from the configuration to both the folder and
-/// branch names, then combines them with the configured separator.
-///
-/// # Arguments
-///
-/// * `folder` - The folder name (typically the last component of the CWD)
-/// * `branch` - The current git branch, or `None` if not in a git repository
-/// * `config` - Formatting configuration
-///
-/// # Returns
-///
-/// The formatted tab name. If `show_branch` is `false` or `branch` is `None`,
-/// only the folder name is returned.
-///
-/// # Examples
-///
-/// With default config:
-/// - `format_tab_name("myproject", Some("main"), &config)` → `"myproject:main"`
-/// - `format_tab_name("myproject", None, &config)` → `"myproject"`
-pub fn format_tab_name(folder: &str, branch: Option<&str>, config: &FormatterConfig) -> String {
-    let folder_display = truncate(
-        folder,
-        config.folder_max_len,
-        config.folder_prefix_len,
-        config.folder_suffix_len,
-    );
+/// A problem found in the plugin configuration by
+/// [`FormatterConfig::try_from_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A key no option reads, usually a typo.
+    UnknownKey(String),
+    /// A value its option cannot parse; the option keeps its default.
+    InvalidValue { key: String, value: String },
+    /// Settings that cannot all hold at once.
+    Contradiction(String),
+}
 
-    match (branch, config.show_branch) {
-        (Some(branch), true) => {
-            let branch_display = truncate(
-                branch,
-                config.branch_max_len,
-                config.branch_prefix_len,
-                config.branch_suffix_len,
-            );
-            format!("{}{}{}", folder_display, config.separator, branch_display)
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            Self::InvalidValue { key, value } => {
+                write!(f, "invalid value `{}` for `{}`", value, key)
+            }
+            Self::Contradiction(message) => write!(f, "{}", message),
         }
-        _ => folder_display,
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Keys read outside [`FormatterConfig::from_config`] that are still valid
+/// plugin settings.
+const EXTERNAL_KEYS: &[&str] = &["home_dir"];
 
-    // ==================== FormatterConfig Tests ====================
+/// Wraps the configuration map while it is read, remembering which keys were
+/// asked for and which values did not parse.
+struct ConfigReader<'a> {
+    map: &'a BTreeMap<String, String>,
+    read: RefCell<BTreeSet<String>>,
+    errors: RefCell<Vec<ConfigError>>,
+}
 
-    #[test]
-    fn test_default_config() {
-        let config = FormatterConfig::default();
-        assert_eq!(config.folder_max_len, 10);
-        assert_eq!(config.folder_prefix_len, 5);
-        assert_eq!(config.folder_suffix_len, 4);
-        assert_eq!(config.branch_max_len, 5);
-        assert_eq!(config.branch_prefix_len, 1);
-        assert_eq!(config.branch_suffix_len, 4);
-        assert_eq!(config.separator, ":");
-        assert!(config.show_branch);
+impl<'a> ConfigReader<'a> {
+    fn new(map: &'a BTreeMap<String, String>) -> Self {
+        Self {
+            map,
+            read: RefCell::new(BTreeSet::new()),
+            errors: RefCell::new(Vec::new()),
+        }
     }
 
-    #[test]
-    fn test_from_config_empty() {
-        let map = BTreeMap::new();
-        let config = FormatterConfig::from_config(&map);
-        assert_eq!(config.folder_max_len, 10); // defaults preserved
+    /// Returns the raw value of `key`, marking the key as known.
+    fn get(&self, key: &str) -> Option<&'a String> {
+        self.read.borrow_mut().insert(key.to_string());
+        self.map.get(key)
     }
 
-    #[test]
-    fn test_from_config_all_values() {
-        let map = BTreeMap::from([
-            ("folder_max_len".to_string(), "20".to_string()),
-            ("folder_prefix_len".to_string(), "8".to_string()),
-            ("folder_suffix_len".to_string(), "6".to_string()),
-            ("branch_max_len".to_string(), "10".to_string()),
-            ("branch_prefix_len".to_string(), "3".to_string()),
-            ("branch_suffix_len".to_string(), "5".to_string()),
-            ("separator".to_string(), " | ".to_string()),
-            ("show_branch".to_string(), "false".to_string()),
-        ]);
-        let config = FormatterConfig::from_config(&map);
-        assert_eq!(config.folder_max_len, 20);
-        assert_eq!(config.folder_prefix_len, 8);
-        assert_eq!(config.folder_suffix_len, 6);
-        assert_eq!(config.branch_max_len, 10);
-        assert_eq!(config.branch_prefix_len, 3);
-        assert_eq!(config.branch_suffix_len, 5);
-        assert_eq!(config.separator, " | ");
-        assert!(!config.show_branch);
+    /// Parses `key` with [`str::parse`], recording a value that does not parse.
+    fn parse<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.parse_with(key, |s| s.parse().ok())
     }
 
-    #[test]
-    fn test_from_config_invalid_numbers_ignored() {
-        let map = BTreeMap::from([
-            ("folder_max_len".to_string(), "not_a_number".to_string()),
-            ("branch_max_len".to_string(), "-5".to_string()), // negative, invalid for usize
-        ]);
-        let config = FormatterConfig::from_config(&map);
-        assert_eq!(config.folder_max_len, 10); // default preserved
-        assert_eq!(config.branch_max_len, 5); // default preserved
+    /// Parses `key` with `parse`, recording a value it rejects.
+    fn parse_with<T>(&self, key: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+        let value = self.get(key)?;
+        let parsed = parse(value);
+        if parsed.is_none() {
+            self.reject(key);
+        }
+        parsed
     }
 
-    #[test]
-    fn test_from_config_show_branch_truthy() {
-        // Any value except "false" should be true
-        let map = BTreeMap::from([("show_branch".to_string(), "true".to_string())]);
-        assert!(FormatterConfig::from_config(&map).show_branch);
+    /// Parses `key` as `true` or `false`, recording anything else.
+    fn flag(&self, key: &str) -> Option<bool> {
+        self.parse_with(key, |s| match s {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        })
+    }
 
-        let map = BTreeMap::from([("show_branch".to_string(), "yes".to_string())]);
+    /// Records the value of `key` as one its option cannot use.
+    fn reject(&self, key: &str) {
+        let value = self.map.get(key).cloned().unwrap_or_default();
+        self.errors.borrow_mut().push(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value,
+        });
+    }
+
+    /// Returns the recorded errors, followed by any key that was never read.
+    fn finish(self) -> Vec<ConfigError> {
+        let read = self.read.into_inner();
+        let mut errors = self.errors.into_inner();
+        errors.extend(
+            self.map
+                .keys()
+                .filter(|key| !read.contains(*key) && !EXTERNAL_KEYS.contains(&key.as_str()))
+                .map(|key| ConfigError::UnknownKey(key.clone())),
+        );
+        errors
+    }
+}
+
+/// Returns the base configuration for a named preset.
+///
+/// Presets are a starting point; individual keys in the plugin configuration
+/// are applied on top. Unknown names yield the defaults.
+///
+/// | Preset | Fields changed from the defaults |
+/// |--------|----------------------------------|
+/// | `minimal` | `show_branch = false` (folder only) |
+/// | `full` | `show_change_counts = true` (folder, branch and changes) |
+/// | `compact` | folder `6`/`3`/`2`, branch `4`/`2`/`1` (max/prefix/suffix) |
+pub fn apply_preset(name: &str) -> FormatterConfig {
+    let defaults = FormatterConfig::default();
+    match name {
+        "minimal" => FormatterConfig {
+            show_branch: false,
+            ..defaults
+        },
+        "full" => FormatterConfig {
+            show_change_counts: true,
+            ..defaults
+        },
+        "compact" => FormatterConfig {
+            folder_max_len: 6,
+            folder_prefix_len: 3,
+            folder_suffix_len: 2,
+            branch_max_len: 4,
+            branch_prefix_len: 2,
+            branch_suffix_len: 1,
+            ..defaults
+        },
+        _ => defaults,
+    }
+}
+
+/// Parses a comma-separated list, trimming entries and skipping empty ones.
+fn parse_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Marker files and the Nerd Font glyphs `show_icon` uses for them by default:
+/// Rust, Node, Go and Python.
+const DEFAULT_PROJECT_ICONS: &str =
+    "Cargo.toml=\u{e7a8},package.json=\u{e718},go.mod=\u{e627},pyproject.toml=\u{e73c}";
+
+/// Shells and the Nerd Font glyphs `show_shell_icon` uses for them by default.
+const DEFAULT_SHELL_ICONS: &str = "bash=\u{e795},zsh=\u{e795},fish=\u{f023a},nu=\u{e795}";
+
+/// Parses a comma-separated list of `key=value` pairs, preserving order.
+///
+/// Malformed entries (no `=` or an empty key) are silently skipped, matching
+/// how other invalid configuration values are ignored.
+fn parse_pairs(s: &str) -> Vec<(String, String)> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() {
+                None
+            } else {
+                Some((key.to_string(), value.trim().to_string()))
+            }
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of `from=to` replacements.
+///
+/// Unlike [`parse_pairs`], nothing is trimmed, so `_= ` replaces underscores
+/// with spaces. Entries without `=` or with an empty `from` are skipped.
+fn parse_replacements(s: &str) -> Vec<(String, String)> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (from, to) = entry.split_once('=')?;
+            (!from.is_empty()).then(|| (from.to_string(), to.to_string()))
+        })
+        .collect()
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, rest)) => {
+            let Some(text) = text.strip_prefix(head) else {
+                return false;
+            };
+            // Try every possible length for the run matched by this `*`
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(text.len()))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
+/// What to show when the folder display is empty after truncation and overrides.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum EmptyFolderFallback {
+    /// Show the folder name as it was before any transform.
+    #[default]
+    OriginalBasename,
+    /// Show this text instead.
+    Literal(String),
+}
+
+impl EmptyFolderFallback {
+    /// Parses a fallback; anything other than `original_basename` is a literal.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "original_basename" => Self::OriginalBasename,
+            _ => Self::Literal(s.to_string()),
+        }
+    }
+
+    /// Returns the text to show in place of an empty folder display.
+    fn resolve(&self, original: &str) -> String {
+        match self {
+            Self::OriginalBasename => original.to_string(),
+            Self::Literal(text) => text.clone(),
+        }
+    }
+}
+
+/// Which pane events cause a tab's name to be re-evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenameOn {
+    /// Every pane update, so a `cd` in the focused pane renames right away.
+    #[default]
+    AnyUpdate,
+    /// Only when a different pane gains focus in the tab.
+    FocusChange,
+}
+
+impl RenameOn {
+    /// Parses a mode name (`any_update`, `focus_change`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "any_update" => Some(Self::AnyUpdate),
+            "focus_change" => Some(Self::FocusChange),
+            _ => None,
+        }
+    }
+}
+
+/// How the panes in a tab are arranged, for `show_layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaneLayout {
+    /// One tiled pane and at most as many floating ones.
+    #[default]
+    Single,
+    /// Several tiled panes side by side or above one another.
+    Split,
+    /// Several tiled panes, some collapsed into a stack.
+    Stacked,
+    /// More floating panes than tiled ones.
+    Floating,
+}
+
+impl PaneLayout {
+    /// The badge shown for this layout, empty for a single pane.
+    pub fn badge(self) -> &'static str {
+        match self {
+            Self::Single => "",
+            Self::Split => "⊞",
+            Self::Stacked => "⊟",
+            Self::Floating => "⧉",
+        }
+    }
+}
+
+/// Appends the layout badge, e.g. `api:main ⊞`, when `show_layout` is on.
+pub fn with_layout(name: &str, layout: PaneLayout, config: &FormatterConfig) -> String {
+    let badge = layout.badge();
+    if config.show_layout && !badge.is_empty() {
+        format!("{} {}", name, badge)
+    } else {
+        name.to_string()
+    }
+}
+
+/// A decoration counted against `max_badges`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    /// Change counts, the clean symbol, ahead/behind counts and the stale
+    /// branch style.
+    Status,
+    /// The `dirty_marker` after the branch.
+    Dirty,
+    /// The kubernetes context.
+    Kube,
+    /// The forge hosting `origin`.
+    Forge,
+    /// The server of a UNC cwd.
+    Server,
+    /// Commits ahead of `base_branch`.
+    BaseAhead,
+    /// The `non_vcs_symbol` outside any repository.
+    NonVcs,
+    /// The project icon.
+    Icon,
+    /// The shell icon.
+    Shell,
+    /// The pane layout.
+    Layout,
+}
+
+impl Badge {
+    /// Every badge, in the default `badge_priority` order.
+    pub const ALL: [Self; 10] = [
+        Self::Status,
+        Self::Dirty,
+        Self::Kube,
+        Self::Forge,
+        Self::Server,
+        Self::BaseAhead,
+        Self::NonVcs,
+        Self::Icon,
+        Self::Shell,
+        Self::Layout,
+    ];
+
+    /// Parses a single badge name, e.g. `kube` or `base_ahead`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "status" => Some(Self::Status),
+            "dirty" => Some(Self::Dirty),
+            "kube" => Some(Self::Kube),
+            "forge" => Some(Self::Forge),
+            "server" => Some(Self::Server),
+            "base_ahead" => Some(Self::BaseAhead),
+            "non_vcs" => Some(Self::NonVcs),
+            "icon" => Some(Self::Icon),
+            "shell" => Some(Self::Shell),
+            "layout" => Some(Self::Layout),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list of badges, skipping unknown entries.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',').filter_map(|p| Self::parse(p.trim())).collect()
+    }
+}
+
+/// Picks the badges to show from those `present` on a tab.
+///
+/// With more than `max_badges` present, the ones ranked highest in
+/// `badge_priority` are kept; badges it leaves out go last, in their `present`
+/// order. Without a cap, or within it, every present badge is kept.
+///
+/// # Examples
+///
+/// With `max_badges = 1` and the default priority, `[Layout, Kube]` → `[Kube]`.
+pub fn select_badges(present: &[Badge], config: &FormatterConfig) -> Vec<Badge> {
+    let mut shown = present.to_vec();
+    if config.max_badges == 0 || shown.len() <= config.max_badges {
+        return shown;
+    }
+    shown.sort_by_key(|badge| {
+        config
+            .badge_priority
+            .iter()
+            .position(|b| b == badge)
+            .unwrap_or(usize::MAX)
+    });
+    shown.truncate(config.max_badges);
+    shown
+}
+
+/// A case transform applied to a name before truncation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Case {
+    /// Leave the name as it is.
+    #[default]
+    None,
+    /// Capitalize the first letter only, e.g. `my_project` → `My_project`.
+    Sentence,
+    /// Lowercase everything, e.g. `MyProject` → `myproject`.
+    Lower,
+    /// Uppercase everything, e.g. `api` → `API`.
+    Upper,
+    /// Capitalize each word and lowercase the rest, e.g. `my_project` →
+    /// `My_Project`.
+    Title,
+}
+
+impl Case {
+    /// Parses a case name (`none`, `sentence`, `lower`, `upper`, `title`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Self::None),
+            "sentence" => Some(Self::Sentence),
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "title" => Some(Self::Title),
+            _ => None,
+        }
+    }
+}
+
+/// Applies `case` to `s`.
+///
+/// Full Unicode case mapping is used, so a single character can map to
+/// several, e.g. `ß` uppercases to `SS`. `Sentence` uppercases the first
+/// alphabetic character, skipping any leading digits or punctuation, so
+/// `2fa-service` becomes `2Fa-service`. `Title` starts a word after any
+/// character that is not a letter or digit.
+fn apply_case(s: &str, case: Case) -> String {
+    match case {
+        Case::None => s.to_string(),
+        Case::Lower => s.to_lowercase(),
+        Case::Upper => s.to_uppercase(),
+        Case::Title => {
+            let mut result = String::with_capacity(s.len());
+            let mut word_start = true;
+            for c in s.chars() {
+                if word_start {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
+                }
+                word_start = !c.is_alphanumeric();
+            }
+            result
+        }
+        Case::Sentence => match s.char_indices().find(|(_, c)| c.is_alphabetic()) {
+            Some((i, c)) => format!("{}{}{}", &s[..i], c.to_uppercase(), &s[i + c.len_utf8()..]),
+            None => s.to_string(),
+        },
+    }
+}
+
+/// Which part of an overlong name truncation cuts away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncateMode {
+    /// Keep both ends and cut the middle, e.g. `featu…1234`.
+    #[default]
+    Middle,
+    /// Cut the start, keeping the end, e.g. `…1234`.
+    Start,
+    /// Cut the end, keeping the start, e.g. `feat…`.
+    End,
+    /// Cut the end with no ellipsis, e.g. `featu`.
+    HardCut,
+}
+
+impl TruncateMode {
+    /// Parses a mode name (`middle`, `start`, `end`, `hard_cut`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "middle" => Some(Self::Middle),
+            "start" => Some(Self::Start),
+            "end" => Some(Self::End),
+            "hard_cut" => Some(Self::HardCut),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with a tab's name when its pane has no usable cwd or title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResetMode {
+    /// Rename to Zellij's positional style, e.g. `Tab 2`.
+    Index,
+    /// Rename to an empty name.
+    Blank,
+    /// Leave the current name untouched.
+    #[default]
+    Keep,
+}
+
+impl ResetMode {
+    /// Parses a mode name (`index`, `blank`, `keep`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "index" => Some(Self::Index),
+            "blank" => Some(Self::Blank),
+            "keep" => Some(Self::Keep),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the name a tab is reset to when nothing resolves.
+///
+/// `position` is the zero-based tab position; `Index` mode renders it
+/// one-based to match Zellij's own numbering. Returns `None` in `Keep` mode.
+pub fn reset_name(mode: ResetMode, position: usize) -> Option<String> {
+    match mode {
+        ResetMode::Index => Some(format!("Tab {}", position + 1)),
+        ResetMode::Blank => Some(String::new()),
+        ResetMode::Keep => None,
+    }
+}
+
+/// Truncates a string using a prefix + ellipsis + suffix strategy.
+///
+/// If the string fits within `max_len`, it is returned unchanged. Otherwise,
+/// the string is truncated by keeping `prefix_len` characters from the start
+/// and `suffix_len` characters from the end, joined by an ellipsis (`…`).
+///
+/// # Arguments
+///
+/// * `s` - The string to truncate
+/// * `max_len` - Maximum allowed length
+/// * `prefix_len` - Characters to keep from the beginning
+/// * `suffix_len` - Characters to keep from the end
+/// * `graphemes` - Count grapheme clusters instead of `char`s
+/// * `ellipsis` - Marker joining the prefix and suffix
+///
+/// # Returns
+///
+/// The original string if it fits, or a truncated version with an ellipsis.
+fn truncate(
+    s: &str,
+    max_len: usize,
+    prefix_len: usize,
+    suffix_len: usize,
+    graphemes: bool,
+    ellipsis: &str,
+) -> String {
+    let units = split_units(s, graphemes);
+    let char_count = units.len();
+
+    if char_count <= max_len {
+        return s.to_string();
+    }
+
+    // If no prefix/suffix specified, just take first max_len chars
+    if prefix_len == 0 && suffix_len == 0 {
+        return units[..max_len].concat();
+    }
+
+    // Ensure we have room for ellipsis
+    let needed = prefix_len + ellipsis.chars().count() + suffix_len;
+
+    if needed > max_len || prefix_len + suffix_len >= char_count {
+        // Just take what we can
+        return units[..max_len].concat();
+    }
+
+    let prefix = units[..prefix_len].concat();
+    let suffix = units[char_count - suffix_len..].concat();
+
+    format!("{}{}{}", prefix, ellipsis, suffix)
+}
+
+/// Splits a string into the units truncation counts: grapheme clusters when
+/// `graphemes` is set, otherwise single `char`s.
+fn split_units(s: &str, graphemes: bool) -> Vec<&str> {
+    if graphemes {
+        s.graphemes(true).collect()
+    } else {
+        s.char_indices()
+            .map(|(i, c)| &s[i..i + c.len_utf8()])
+            .collect()
+    }
+}
+
+/// Strips trailing copy markers from a folder name.
+///
+/// Removes ` (N)`, `-copy` and, unless `keep_numeric` is set, `-N`, repeating
+/// until none remain, so `project-copy (2)` becomes `project`. A name made up
+/// only of such a marker is returned unchanged rather than emptied.
+fn strip_copy_suffix(s: &str, keep_numeric: bool) -> String {
+    let is_number = |n: &str| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit());
+    let mut rest = s;
+
+    loop {
+        let parenthesized = rest
+            .strip_suffix(')')
+            .and_then(|r| r.rsplit_once(" ("))
+            .filter(|(_, n)| is_number(n));
+        let dashed = rest
+            .rsplit_once('-')
+            .filter(|(_, n)| !keep_numeric && is_number(n));
+
+        let stripped = parenthesized
+            .or(dashed)
+            .map(|(r, _)| r)
+            .or_else(|| rest.strip_suffix("-copy"));
+
+        match stripped {
+            Some(r) if !r.is_empty() => rest = r,
+            _ => return rest.to_string(),
+        }
+    }
+}
+
+/// Shortens every word of `s` to at most `per_word` characters.
+///
+/// Words are separated by any character in `delimiters`, and the delimiters
+/// themselves are kept, so `feature/implement-authentication-flow` with
+/// `per_word = 4` and delimiters `-/` becomes `feat/impl-auth-flow`.
+fn abbreviate_words(s: &str, per_word: usize, delimiters: &str) -> String {
+    let mut result = String::new();
+    let mut kept = 0;
+
+    for c in s.chars() {
+        if delimiters.contains(c) {
+            result.push(c);
+            kept = 0;
+        } else if kept < per_word {
+            result.push(c);
+            kept += 1;
+        }
+    }
+
+    result
+}
+
+/// Replaces every word of `s` found in `dict` with its abbreviation.
+///
+/// Words are separated by any character in `delimiters`, which are kept, and
+/// must match a dictionary entry exactly, so `feature/authentication-flow`
+/// with `authentication=auth` becomes `feature/auth-flow`.
+fn abbreviate_dict(s: &str, dict: &[(String, String)], delimiters: &str) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        match dict.iter().find(|(full, _)| full == word) {
+            Some((_, abbr)) => result.push_str(abbr),
+            None => result.push_str(word),
+        }
+        word.clear();
+    };
+
+    for c in s.chars() {
+        if delimiters.contains(c) {
+            flush(&mut word, &mut result);
+            result.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+
+    result
+}
+
+/// Truncates a string at the delimiter nearest to `max_len`.
+///
+/// Instead of cutting mid-token, the string is cut just before the last
+/// delimiter that still leaves room for the ellipsis, so
+/// `"my_long_project_name"` with `max_len = 10` becomes `"my_long…"`. When no
+/// delimiter fits, falls back to a hard cut leaving room for the ellipsis.
+fn truncate_at_delimiter(
+    s: &str,
+    max_len: usize,
+    delimiters: &str,
+    graphemes: bool,
+    ellipsis: &str,
+) -> String {
+    let chars = split_units(s, graphemes);
+
+    if chars.len() <= max_len {
+        return s.to_string();
+    }
+    let ellipsis_len = ellipsis.chars().count();
+    if max_len == 0 || ellipsis_len > max_len {
+        return chars[..max_len].concat();
+    }
+
+    // Keep room for the ellipsis
+    let budget = max_len - ellipsis_len;
+    let cut = chars[1..=budget]
+        .iter()
+        .rposition(|unit| unit.chars().all(|c| delimiters.contains(c)))
+        .map_or(budget, |i| i + 1);
+
+    format!("{}{}", chars[..cut].concat(), ellipsis)
+}
+
+/// Truncates a string to `max_len`, keeping `keyword` visible.
+///
+/// The retained window is centered on the first occurrence of `keyword`, with
+/// an ellipsis marking each side that was cut, so `"legacy-api-service"` with
+/// `max_len = 10` and keyword `api` becomes `"…y-api-se…"`. Without the
+/// keyword, or when it cannot fit alongside the ellipses, falls back to
+/// truncating the middle.
+fn truncate_keeping(
+    s: &str,
+    max_len: usize,
+    keyword: Option<&str>,
+    graphemes: bool,
+    ellipsis: &str,
+) -> String {
+    let units = split_units(s, graphemes);
+    let len = units.len();
+    if len <= max_len {
+        return s.to_string();
+    }
+
+    let found = keyword.and_then(|keyword| {
+        let byte_start = s.find(keyword)?;
+        let start = split_units(&s[..byte_start], graphemes).len();
+        Some((start, split_units(keyword, graphemes).len()))
+    });
+    let ellipsis_len = ellipsis.chars().count();
+    let Some((start, keyword_len)) = found.filter(|(_, k)| k + 2 * ellipsis_len <= max_len) else {
+        // Middle truncation: split what remains after the ellipsis between the ends
+        let keep = max_len.saturating_sub(ellipsis_len);
+        return truncate(s, max_len, keep - keep / 2, keep / 2, graphemes, ellipsis);
+    };
+
+    // Split the spare room around the keyword, handing any room one side
+    // cannot use to the other
+    let spare = max_len - keyword_len;
+    let after = len - start - keyword_len;
+    let mut left = spare / 2;
+    let mut right = spare - left;
+    if after < right {
+        left += right - after;
+        right = after;
+    }
+    if start < left {
+        right += left - start;
+        left = start;
+    }
+
+    let from = start - left;
+    let to = start + keyword_len + right;
+    // Each cut side gives up the units its ellipsis replaces
+    let head = if from > 0 { ellipsis_len } else { 0 };
+    let tail = if to < len { ellipsis_len } else { 0 };
+    format!(
+        "{}{}{}",
+        if from > 0 { ellipsis } else { "" },
+        units[from + head..to - tail].concat(),
+        if to < len { ellipsis } else { "" }
+    )
+}
+
+/// Pads or truncates `s` to exactly `width` display columns.
+///
+/// Short strings are padded with trailing spaces. Long strings are cut and
+/// end in `ellipsis`; a double-width character that would straddle the edge
+/// is dropped and the gap padded, so the result is always exactly `width`
+/// columns wide.
+fn fit_width(s: &str, width: usize, ellipsis: &str, graphemes: bool) -> String {
+    let current = s.width();
+    if current <= width {
+        return format!("{}{}", s, " ".repeat(width - current));
+    }
+
+    // Fall back to a bare cut when even the ellipsis does not fit
+    let ellipsis = if ellipsis.width() <= width {
+        ellipsis
+    } else {
+        ""
+    };
+    let budget = width - ellipsis.width();
+
+    let mut result = String::new();
+    let mut used = 0;
+    for unit in split_units(s, graphemes) {
+        let w = unit.width();
+        if used + w > budget {
+            break;
+        }
+        result.push_str(unit);
+        used += w;
+    }
+    result.push_str(ellipsis);
+    result.push_str(&" ".repeat(budget - used));
+    result
+}
+
+/// Truncates by cutting one end of the string, marking the cut with an
+/// ellipsis, so `feature/JIRA-1234` with `max_len = 4` becomes `…234` in
+/// `Start` mode and `fea…` in `End` mode. `HardCut` mode leaves the ellipsis
+/// out, giving `feat`.
+fn truncate_edge(
+    s: &str,
+    max_len: usize,
+    mode: TruncateMode,
+    graphemes: bool,
+    ellipsis: &str,
+) -> String {
+    let units = split_units(s, graphemes);
+    let len = units.len();
+    if len <= max_len {
+        return s.to_string();
+    }
+    let ellipsis_len = ellipsis.chars().count();
+    if mode == TruncateMode::HardCut || max_len == 0 || ellipsis_len > max_len {
+        return units[..max_len].concat();
+    }
+
+    let keep = max_len - ellipsis_len;
+    match mode {
+        TruncateMode::Start => format!("{}{}", ellipsis, units[len - keep..].concat()),
+        _ => format!("{}{}", units[..keep].concat(), ellipsis),
+    }
+}
+
+/// Truncates with whichever strategy the configuration selects.
+///
+/// `Start`, `End` and `HardCut` modes cut one end; `Middle` keeps the
+/// prefix/suffix or delimiter-aware strategy.
+fn truncate_with(
+    s: &str,
+    max_len: usize,
+    prefix_len: usize,
+    suffix_len: usize,
+    mode: TruncateMode,
+    config: &FormatterConfig,
+) -> String {
+    if mode != TruncateMode::Middle {
+        truncate_edge(s, max_len, mode, config.grapheme_mode, &config.ellipsis)
+    } else if config.smart_truncate {
+        truncate_at_delimiter(
+            s,
+            max_len,
+            &config.truncate_delimiters,
+            config.grapheme_mode,
+            &config.ellipsis,
+        )
+    } else {
+        truncate(
+            s,
+            max_len,
+            prefix_len,
+            suffix_len,
+            config.grapheme_mode,
+            &config.ellipsis,
+        )
+    }
+}
+
+/// Collapses repeated separators and repeated spaces into a single occurrence.
+///
+/// Used as a final normalization pass so names like `"proj::main"` or
+/// `"proj  main"` become `"proj:main"` and `"proj main"`. An empty separator
+/// only collapses spaces.
+fn collapse_separators(name: &str, sep: &str) -> String {
+    let mut result = name.to_string();
+
+    if !sep.is_empty() {
+        let doubled = sep.repeat(2);
+        while result.contains(&doubled) {
+            result = result.replace(&doubled, sep);
+        }
+    }
+
+    let mut collapsed = String::with_capacity(result.len());
+    for c in result.chars() {
+        if c == ' ' && collapsed.ends_with(' ') {
+            continue;
+        }
+        collapsed.push(c);
+    }
+    collapsed
+}
+
+/// Formats a tab name from a folder name and optional git branch.
+///
+/// Applies truncation rules from the configuration to both the folder and
+/// branch names, then combines them with the configured separator.
+///
+/// # Arguments
+///
+/// * `folder` - The folder name (typically the last component of the CWD)
+/// * `branch` - The current git branch, or `None` if not in a git repository
+/// * `config` - Formatting configuration
+///
+/// # Returns
+///
+/// The formatted tab name. If `show_branch` is `false` or `branch` is `None`,
+/// only the folder name is returned.
+///
+/// # Examples
+///
+/// With default config:
+/// - `format_tab_name("myproject", Some("main"), &config)` → `"myproject:main"`
+/// - `format_tab_name("myproject", None, &config)` → `"myproject"`
+pub fn format_tab_name(folder: &str, branch: Option<&str>, config: &FormatterConfig) -> String {
+    format_tab_name_with_status(folder, branch, None, None, false, false, config)
+}
+
+/// Formats a tab name like [`format_tab_name`], including git status details.
+///
+/// When `show_change_counts` is enabled and `status` is known, staged and
+/// unstaged counts are rendered after the branch, e.g. `"myproject:main +2~3"`.
+/// With `show_clean`, a clean working tree gets `clean_symbol` instead, e.g.
+/// `"myproject:main ✓"`. With `show_nonprimary_remote`, a `remote` other than
+/// `primary_remote` is appended to the branch, e.g. `"myproject:main@upstream"`.
+/// With `show_ahead_behind`, commits ahead/behind the upstream follow, e.g.
+/// `"myproject:main↑2"`. With `compact_status`, a single badge such as
+/// `"myproject:main ⇡*"` replaces all of these.
+/// With `show_dir_freshness`, a `fresh` cwd gets `fresh_symbol` after the
+/// folder, e.g. `"scratch~"`, whether or not it is a repo.
+/// With `show_dirty`, a `dirty` working tree gets `dirty_marker` right after
+/// the branch, e.g. `"myproject:main*"`.
+/// With `stale_days`, a branch whose last commit is at least that many days
+/// old is wrapped in [`STALE_BRANCH_STYLE`] and [`STALE_BRANCH_RESET`].
+/// Spaces in the branch are replaced with `branch_space_replacement` first.
+/// Status details only appear alongside a displayed branch, so paths outside
+/// a repo never get either.
+/// With `name_template`, the formatted folder and branch are laid out by
+/// [`render_template`] instead of being joined by `separator`.
+pub fn format_tab_name_with_status(
+    folder: &str,
+    branch: Option<&str>,
+    status: Option<&GitStatus>,
+    remote: Option<&str>,
+    fresh: bool,
+    dirty: bool,
+    config: &FormatterConfig,
+) -> String {
+    if !config.machine_format.is_empty() {
+        return format_machine_name(folder, branch, status, &config.machine_format);
+    }
+
+    let stripped;
+    let folder = if config.strip_copy_suffixes {
+        stripped = strip_copy_suffix(folder, config.keep_numeric_suffix);
+        stripped.as_str()
+    } else {
+        folder
+    };
+
+    let replaced = config
+        .folder_replace
+        .iter()
+        .fold(folder.to_string(), |name, (from, to)| {
+            name.replace(from, to)
+        });
+    let cased = apply_case(&replaced, config.folder_case);
+    let keyword = config
+        .folder_keep_keywords
+        .iter()
+        .find(|k| !k.is_empty() && cased.contains(k.as_str()));
+    let mut folder_display = match keyword {
+        Some(keyword) => truncate_keeping(
+            &cased,
+            config.folder_max_len,
+            Some(keyword),
+            config.grapheme_mode,
+            &config.ellipsis,
+        ),
+        None => truncate_with(
+            &cased,
+            config.folder_max_len,
+            config.folder_prefix_len,
+            config.folder_suffix_len,
+            config.folder_truncate_mode,
+            config,
+        ),
+    };
+
+    if let Some(branch) = branch {
+        if let Some((_, label)) = config
+            .branch_folder_override
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, branch))
+        {
+            folder_display = label.clone();
+        }
+    }
+
+    if folder_display.is_empty() {
+        folder_display = config.empty_folder_fallback.resolve(folder);
+    }
+    if fresh && config.show_dir_freshness {
+        folder_display.push_str(&config.fresh_symbol);
+    }
+    if config.folder_fixed_width > 0 {
+        folder_display = fit_width(
+            &folder_display,
+            config.folder_fixed_width,
+            &config.ellipsis,
+            config.grapheme_mode,
+        );
+    }
+
+    let default_branch =
+        branch.is_some_and(|b| config.hide_default_branches.iter().any(|d| d == b));
+    let branch = match branch {
+        Some(branch) if config.strip_folder_prefix_from_branch => {
+            Some(strip_folder_prefix(branch, folder))
+        }
+        _ => branch,
+    };
+    let branch = match branch {
+        Some(_) if default_branch => Some(BranchPart::Overlapping),
+        Some(branch) if config.collapse_branch_folder_overlap => {
+            Some(strip_folder_overlap(branch, folder))
+        }
+        _ => branch.map(BranchPart::Shown),
+    };
+
+    // A template decides on its own whether the branch appears
+    let show_branch = config.show_branch || !config.name_template.is_empty();
+    let (folder_display, branch_display) = match (branch, show_branch) {
+        // A hidden branch keeps its markers, which move onto the folder
+        (Some(BranchPart::Overlapping), true) => {
+            if dirty && config.show_dirty {
+                folder_display.push_str(&config.dirty_marker);
+            }
+            if let Some(status) = status {
+                folder_display.push_str(&format_status(status, config));
+            }
+            (folder_display, None)
+        }
+        (Some(BranchPart::Shown(branch)), true) => {
+            let pr_ref = format_pr_ref(branch, &config.pr_format);
+            let branch = pr_ref.as_deref().unwrap_or(branch);
+            let despaced = branch.replace(' ', &config.branch_space_replacement);
+            let branch = despaced.as_str();
+            let from_dict;
+            let branch = if !config.branch_abbreviations.is_empty()
+                && branch.chars().count() > config.branch_max_len
+            {
+                from_dict = abbreviate_dict(branch, &config.branch_abbreviations, "-/_");
+                from_dict.as_str()
+            } else {
+                branch
+            };
+            let abbreviated;
+            let branch = if config.branch_word_max > 0 {
+                abbreviated = abbreviate_words(branch, config.branch_word_max, "-/");
+                abbreviated.as_str()
+            } else {
+                branch
+            };
+            let cased = apply_case(branch, config.branch_case);
+            let mut branch_display = truncate_with(
+                &cased,
+                config.branch_max_len,
+                config.branch_prefix_len,
+                config.branch_suffix_len,
+                config.branch_truncate_mode,
+                config,
+            );
+            if branch_display != cased {
+                branch_display.push_str(&config.truncated_branch_suffix);
+            }
+            if let Some(remote) = remote {
+                if config.show_nonprimary_remote && remote != config.primary_remote {
+                    branch_display.push_str(&format!("@{}", remote));
+                }
+            }
+            if config.branch_fixed_width > 0 {
+                branch_display = fit_width(
+                    &branch_display,
+                    config.branch_fixed_width,
+                    &config.ellipsis,
+                    config.grapheme_mode,
+                );
+            }
+            // Styled last, so no cut above can split the escape codes
+            if is_stale(status, config) {
+                branch_display = format!(
+                    "{}{}{}",
+                    STALE_BRANCH_STYLE, branch_display, STALE_BRANCH_RESET
+                );
+            }
+            if dirty && config.show_dirty {
+                branch_display.push_str(&config.dirty_marker);
+            }
+            let counts = match status {
+                Some(status) => format_status(status, config),
+                None => String::new(),
+            };
+            (
+                folder_display,
+                Some(format!("{}{}", branch_display, counts)),
+            )
+        }
+        _ => (folder_display, None),
+    };
+
+    let name = if !config.name_template.is_empty() {
+        render_template(
+            &config.name_template,
+            &folder_display,
+            branch_display.as_deref(),
+            &config.separator,
+        )
+    } else {
+        match branch_display {
+            Some(branch) => format!("{}{}{}", folder_display, config.separator, branch),
+            None => folder_display,
+        }
+    };
+
+    let name = if config.collapse_separators {
+        collapse_separators(&name, &config.separator)
+    } else {
+        name
+    };
+
+    // An empty name stays empty so callers still see that nothing resolved
+    if name.is_empty() {
+        name
+    } else {
+        format!("{}{}{}", config.name_prefix, name, config.name_suffix)
+    }
+}
+
+/// Renders a `name_template` from the formatted folder and branch.
+///
+/// `{sep}` becomes `separator`. `{index}` is filled in per tab by
+/// [`template_for_tab`] beforehand, and `{host}` with the ssh host of the
+/// tab's title; both render empty otherwise. An empty
+/// placeholder takes the literal text joining it to the previous placeholder
+/// with it, or to the next one when it comes first, so a missing branch leaves
+/// no dangling separator. Unknown placeholders are kept as written.
+///
+/// # Examples
+///
+/// - `"{folder}@{branch}"` with `Some("main")` → `"api@main"`
+/// - `"{folder}@{branch}"` with `None` → `"api"`
+/// - `"{branch}/{folder}"` with `None` → `"api"`
+pub fn render_template(
+    template: &str,
+    folder: &str,
+    branch: Option<&str>,
+    separator: &str,
+) -> String {
+    // Literal text, or a placeholder's value when it has one
+    let mut parts: Vec<(bool, Option<String>)> = Vec::new();
+    let mut literal = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        literal.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('}').map_or(rest.len(), |end| end + 1);
+        let value = match &rest[..end] {
+            "{folder}" => Some(Some(folder)),
+            "{branch}" => Some(branch),
+            "{index}" | "{host}" => Some(None),
+            "{sep}" => {
+                literal.push_str(separator);
+                None
+            }
+            other => {
+                literal.push_str(other);
+                None
+            }
+        };
+        if let Some(value) = value {
+            parts.push((false, Some(std::mem::take(&mut literal))));
+            let value = value.filter(|v| !v.is_empty()).map(str::to_string);
+            parts.push((true, value));
+        }
+        rest = &rest[end..];
+    }
+    literal.push_str(rest);
+    parts.push((false, Some(literal)));
+
+    for i in 0..parts.len() {
+        if parts[i] != (true, None) {
+            continue;
+        }
+        let shown_before = parts[..i]
+            .iter()
+            .any(|(is_value, v)| *is_value && v.is_some());
+        let joining = if shown_before { i - 1 } else { i + 1 };
+        parts[joining].1 = None;
+    }
+    parts.into_iter().filter_map(|(_, text)| text).collect()
+}
+
+/// Fills `{index}` in `template` with the one-based tab `position`.
+pub fn template_for_tab(template: &str, position: usize) -> String {
+    template.replace("{index}", &(position + 1).to_string())
+}
+
+/// Renders `template` for `machine_format` from untouched values.
+///
+/// `{folder}` and `{branch}` are never truncated or transformed, and
+/// `{dirty}` is `1` or `0`. Unknown values, such as the branch outside a repo
+/// or the dirty state before status is known, render empty, so
+/// `{folder}|{branch}|{dirty}` gives `scratch||` outside a repo.
+fn format_machine_name(
+    folder: &str,
+    branch: Option<&str>,
+    status: Option<&GitStatus>,
+    template: &str,
+) -> String {
+    let dirty = match status {
+        Some(status) if status.is_dirty() => "1",
+        Some(_) => "0",
+        None => "",
+    };
+    template
+        .replace("{folder}", folder)
+        .replace("{branch}", branch.unwrap_or_default())
+        .replace("{dirty}", dirty)
+}
+
+/// What is left of the branch once any overlap with the folder is removed.
+enum BranchPart<'a> {
+    /// Display this branch text.
+    Shown(&'a str),
+    /// The branch is exactly the folder name or a hidden default branch, so
+    /// only the folder is shown.
+    Overlapping,
+}
+
+/// Removes a trailing `/<folder>` segment from the branch.
+///
+/// `pkg/auth` in folder `auth` becomes `pkg`; a branch named exactly like the
+/// folder overlaps entirely. Only whole segments match, so `oauth` is kept.
+fn strip_folder_overlap<'a>(branch: &'a str, folder: &str) -> BranchPart<'a> {
+    if folder.is_empty() {
+        return BranchPart::Shown(branch);
+    }
+    if branch == folder {
+        return BranchPart::Overlapping;
+    }
+    match branch
+        .strip_suffix(folder)
+        .and_then(|rest| rest.strip_suffix('/'))
+    {
+        Some(rest) if !rest.is_empty() => BranchPart::Shown(rest),
+        _ => BranchPart::Shown(branch),
+    }
+}
+
+/// Removes a leading `<folder>-` or `<folder>/` from the branch.
+///
+/// `proj-feature` in folder `proj` becomes `feature`. The folder must be
+/// followed by one of the two separators, so `project-x` is kept, and a
+/// branch that would be left empty is returned unchanged.
+fn strip_folder_prefix<'a>(branch: &'a str, folder: &str) -> &'a str {
+    if folder.is_empty() {
+        return branch;
+    }
+    match branch
+        .strip_prefix(folder)
+        .and_then(|rest| rest.strip_prefix(['-', '/']))
+    {
+        Some(rest) if !rest.is_empty() => rest,
+        _ => branch,
+    }
+}
+
+/// Renders a pull/merge request ref using `pr_format`.
+///
+/// Recognizes GitHub `pull/<n>/merge` and `pull/<n>/head` as well as GitLab
+/// `merge-requests/<n>/head`, with or without a leading `refs/`. Returns
+/// `None` for any other branch, or when `pr_format` is empty.
+///
+/// # Examples
+///
+/// - `format_pr_ref("refs/pull/42/merge", "PR#{n}")` → `Some("PR#42")`
+/// - `format_pr_ref("main", "PR#{n}")` → `None`
+pub fn format_pr_ref(branch: &str, pr_format: &str) -> Option<String> {
+    if pr_format.is_empty() {
+        return None;
+    }
+
+    let branch = branch.strip_prefix("refs/").unwrap_or(branch);
+    let mut parts = branch.split('/');
+    let kind = parts.next()?;
+    let number = parts.next()?;
+    let tail = parts.next()?;
+
+    let is_pr = matches!(
+        (kind, tail),
+        ("pull", "merge" | "head") | ("merge-requests", "head")
+    );
+    if !is_pr || parts.next().is_some() || number.is_empty() {
+        return None;
+    }
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(pr_format.replace("{n}", number))
+}
+
+/// Renders staged/unstaged counts as a space-prefixed suffix like `" +2~3"`.
+///
+/// Zero counts are omitted, so a clean working tree renders as an empty string.
+fn format_change_counts(status: &GitStatus, config: &FormatterConfig) -> String {
+    let mut counts = String::new();
+    if status.staged > 0 {
+        counts.push_str(&format!("{}{}", config.staged_symbol, status.staged));
+    }
+    if status.unstaged > 0 {
+        counts.push_str(&format!("{}{}", config.unstaged_symbol, status.unstaged));
+    }
+
+    if counts.is_empty() {
+        counts
+    } else {
+        format!(" {}", counts)
+    }
+}
+
+/// Summarizes the status as one minimal badge: `⇡` when ahead, `⇣` when
+/// behind, then `*` when dirty, always in that order.
+///
+/// A clean branch in sync with its upstream gets an empty badge.
+///
+/// # Examples
+///
+/// Ahead by two with unstaged changes → `"⇡*"`; behind only → `"⇣"`.
+pub fn compact_status_badge(status: &GitStatus) -> String {
+    let mut badge = String::new();
+    if status.ahead > 0 {
+        badge.push('⇡');
+    }
+    if status.behind > 0 {
+        badge.push('⇣');
+    }
+    if status.is_dirty() {
+        badge.push('*');
+    }
+    badge
+}
+
+/// Renders the commits ahead of and behind the upstream, e.g. `"↑2↓1"`.
+///
+/// In `compact` mode a zero count is left out, so a branch in sync with its
+/// upstream renders as an empty string; otherwise both counts always show.
+pub fn format_ahead_behind(status: &GitStatus, compact: bool) -> String {
+    let mut result = String::new();
+    if !compact || status.ahead > 0 {
+        result.push_str(&format!("↑{}", status.ahead));
+    }
+    if !compact || status.behind > 0 {
+        result.push_str(&format!("↓{}", status.behind));
+    }
+    result
+}
+
+/// ANSI codes opening a stale branch: dim, red.
+pub const STALE_BRANCH_STYLE: &str = "\x1b[2;31m";
+/// ANSI code closing [`STALE_BRANCH_STYLE`].
+pub const STALE_BRANCH_RESET: &str = "\x1b[0m";
+
+/// Returns whether the branch's last commit is at least `stale_days` old.
+///
+/// An unknown commit age is never stale, nor is anything with `stale_days`
+/// at 0.
+pub fn is_stale(status: Option<&GitStatus>, config: &FormatterConfig) -> bool {
+    config.stale_days > 0
+        && status
+            .and_then(|s| s.commit_age_days)
+            .is_some_and(|days| days >= config.stale_days)
+}
+
+/// Renders the status suffix: change counts when dirty, the clean symbol when
+/// clean, then the ahead/behind counts when `show_ahead_behind` is on. The
+/// counts sit right against the branch unless something precedes them, so a
+/// clean branch reads `main↑2↓1` and a dirty one `main ~3 ↑2`. With
+/// `compact_status`, the single [`compact_status_badge`] replaces all of them.
+pub fn format_status(status: &GitStatus, config: &FormatterConfig) -> String {
+    if config.compact_status {
+        let badge = compact_status_badge(status);
+        return if badge.is_empty() {
+            badge
+        } else {
+            format!(" {}", badge)
+        };
+    }
+    let mut suffix = if config.show_clean && !status.is_dirty() {
+        format!(" {}", config.clean_symbol)
+    } else if config.show_change_counts {
+        format_change_counts(status, config)
+    } else {
+        String::new()
+    };
+    if config.show_ahead_behind {
+        let ahead_behind = format_ahead_behind(status, config.compact_ahead_behind);
+        if !suffix.is_empty() && !ahead_behind.is_empty() {
+            suffix.push(' ');
+        }
+        suffix.push_str(&ahead_behind);
+    }
+    suffix
+}
+
+/// Returns the group label for a zero-based tab position, e.g. `"G1"`.
+///
+/// Tabs are grouped in runs of `size`, so with a size of 4 positions 0-3 are
+/// `G1`, 4-7 are `G2`, and so on. A size of `0` is treated as 1.
+pub fn group_label(pos: usize, size: usize) -> String {
+    format!("G{}", pos / size.max(1) + 1)
+}
+
+/// Prefixes a formatted name with its tab's group label when grouping is on.
+///
+/// # Examples
+///
+/// With `group_size = 4`, `with_group_label("proj:main", 5, &config)` →
+/// `"G2:proj:main"`.
+pub fn with_group_label(name: &str, position: usize, config: &FormatterConfig) -> String {
+    if config.group_size == 0 {
+        return name.to_string();
+    }
+    let label = format!(
+        "{}{}",
+        group_label(position, config.group_size),
+        config.separator
+    );
+    join_decoration(&label, name, config)
+}
+
+/// Prefixes a formatted name with its project family when `family_segment`
+/// found one.
+///
+/// # Examples
+///
+/// `with_family_label("api:main", Some("acme"), &config)` → `"acme:api:main"`.
+pub fn with_family_label(name: &str, family: Option<&str>, config: &FormatterConfig) -> String {
+    match family {
+        Some(family) => join_decoration(&format!("{}{}", family, config.separator), name, config),
+        None => name.to_string(),
+    }
+}
+
+/// Joins a decoration such as a group label or `tab_prefix` onto a name.
+///
+/// With `collapse_separators`, a separator ending the decoration and one
+/// starting the name are merged, so `"work:"` and `":main"` (an empty folder)
+/// give `"work:main"` rather than `"work::main"`.
+fn join_decoration(decoration: &str, name: &str, config: &FormatterConfig) -> String {
+    let sep = &config.separator;
+    let name = match name.strip_prefix(sep.as_str()) {
+        Some(rest)
+            if config.collapse_separators && !sep.is_empty() && decoration.ends_with(sep) =>
+        {
+            rest
+        }
+        _ => name,
+    };
+    format!("{}{}", decoration, name)
+}
+
+/// Shortens a kubernetes context to the cluster name it refers to.
+///
+/// EKS contexts are ARNs ending in `cluster/<name>` and GKE contexts are
+/// `gke_<project>_<zone>_<name>`; both are reduced to `<name>`. Any other
+/// context is returned unchanged.
+///
+/// # Examples
+///
+/// - `"arn:aws:eks:us-east-1:123:cluster/prod"` → `"prod"`
+/// - `"gke_acme_europe-west1_staging"` → `"staging"`
+/// - `"kind-dev"` → `"kind-dev"`
+pub fn format_kube_context(context: &str) -> String {
+    let short = if context.starts_with("arn:") {
+        context.rsplit_once('/').map(|(_, name)| name)
+    } else if context.starts_with("gke_") {
+        context.splitn(4, '_').nth(3)
+    } else {
+        None
+    };
+    short
+        .filter(|name| !name.is_empty())
+        .unwrap_or(context)
+        .to_string()
+}
+
+/// Derives a session name from a folder name.
+///
+/// Characters other than letters, digits, `-`, `_` and `.` become `-`, runs
+/// of `-` collapse to one and leading or trailing `-` are dropped. Returns
+/// `None` when nothing usable remains.
+///
+/// # Examples
+///
+/// - `"My API"` → `"My-API"`
+/// - `"~"` → `None`
+pub fn session_name(folder: &str) -> Option<String> {
+    let mut name = String::new();
+    for c in folder.chars() {
+        let c = if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            c
+        } else {
+            '-'
+        };
+        if !(c == '-' && name.ends_with('-')) {
+            name.push(c);
+        }
+    }
+    let name = name.trim_matches('-');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Prepends the kubernetes context badge when `show_kube_context` is on.
+///
+/// # Examples
+///
+/// With the default `kube_symbol`, `with_kube_context("infra", Some("kind-dev"), &config)`
+/// → `"⎈kind-dev infra"`. Without a context the name is unchanged.
+pub fn with_kube_context(name: &str, context: Option<&str>, config: &FormatterConfig) -> String {
+    match context {
+        Some(context) if config.show_kube_context => format!(
+            "{}{} {}",
+            config.kube_symbol,
+            format_kube_context(context),
+            name
+        ),
+        _ => name.to_string(),
+    }
+}
+
+/// Prepends the forge badge, e.g. `gh api:main`, when `show_forge` is on and
+/// the repo's `origin` host is known.
+pub fn with_forge_label(name: &str, forge: Option<&str>, config: &FormatterConfig) -> String {
+    match forge {
+        Some(forge) if config.show_forge => format!("{} {}", forge, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Prepends the project icon, e.g. `\u{e7a8} api:main`, when `show_icon` is
+/// on and the cwd holds one of the `project_icons` markers.
+pub fn with_project_icon(name: &str, icon: Option<&str>, config: &FormatterConfig) -> String {
+    match icon {
+        Some(icon) if config.show_icon && !icon.is_empty() => format!("{} {}", icon, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Returns the icon `shell_icons` gives the shell running `command`.
+///
+/// Matches the program name only, and a login shell's leading `-` is
+/// ignored, so `/usr/bin/fish` and `-zsh` both match. Returns `None` for
+/// other commands or when no command is known.
+pub fn shell_icon<'a>(command: Option<&str>, icons: &'a [(String, String)]) -> Option<&'a str> {
+    let name = command_name(command?)?;
+    let name = name.strip_prefix('-').unwrap_or(name);
+    icons
+        .iter()
+        .find(|(shell, _)| shell == name)
+        .map(|(_, icon)| icon.as_str())
+}
+
+/// Prepends the shell icon, e.g. `\u{e795} api:main`, when `show_shell_icon`
+/// is on.
+pub fn with_shell_icon(name: &str, icon: Option<&str>, config: &FormatterConfig) -> String {
+    match icon {
+        Some(icon) if config.show_shell_icon && !icon.is_empty() => format!("{} {}", icon, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Prepends the server of a UNC cwd, e.g. `fs01 api`, when `show_unc_server`
+/// is on.
+pub fn with_unc_server(name: &str, server: Option<&str>, config: &FormatterConfig) -> String {
+    match server {
+        Some(server) if config.show_unc_server => format!("{} {}", server, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Appends the commits ahead of `base_branch`, e.g. `api:feature ↑7`.
+///
+/// Nothing is added without a count or when the branch has no commits of its
+/// own, as on the base branch itself.
+pub fn with_base_ahead(name: &str, ahead: Option<usize>) -> String {
+    match ahead {
+        Some(ahead) if ahead > 0 => format!("{} ↑{}", name, ahead),
+        _ => name.to_string(),
+    }
+}
+
+/// Appends `non_vcs_symbol` when the cwd is known to be outside any
+/// repository, e.g. `scratch ∅`.
+pub fn with_non_vcs_symbol(name: &str, outside_vcs: bool, config: &FormatterConfig) -> String {
+    if outside_vcs {
+        format!("{}{}", name, config.non_vcs_symbol)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Appends `attention_symbol` to a tab that needs attention, e.g. `api:main !`.
+///
+/// This goes on last, after `total_max_len` is applied, so the marker is
+/// never cut off.
+pub fn with_attention(name: &str, attention: bool, config: &FormatterConfig) -> String {
+    if attention && !name.is_empty() {
+        format!("{}{}", name, config.attention_symbol)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Prepends `tab_prefix` and caps the whole name at `total_max_len`.
+///
+/// The cap cuts the computed name, ending it in `ellipsis`, so the prefix always
+/// survives unless it alone exceeds the cap. A `total_max_len` of 0 means
+/// no cap. The stale branch style takes no width, and a name that must be cut
+/// loses it rather than have the cut split its codes.
+///
+/// # Examples
+///
+/// With `tab_prefix = "work/"` and `total_max_len = 10`,
+/// `with_tab_prefix("proj:main", &config)` → `"work/proj…"`.
+pub fn with_tab_prefix(name: &str, config: &FormatterConfig) -> String {
+    let prefixed = join_decoration(&config.tab_prefix, name, config);
+    let max_len = config.total_max_len;
+    let plain = prefixed
+        .replace(STALE_BRANCH_STYLE, "")
+        .replace(STALE_BRANCH_RESET, "");
+    let units = split_units(&plain, config.grapheme_mode);
+    if max_len == 0 || units.len() <= max_len {
+        return prefixed;
+    }
+
+    let prefix_len = split_units(&config.tab_prefix, config.grapheme_mode).len();
+    let ellipsis_len = config.ellipsis.chars().count();
+    if prefix_len + ellipsis_len >= max_len {
+        return units[..max_len].concat();
+    }
+    format!(
+        "{}{}",
+        units[..max_len - ellipsis_len].concat(),
+        config.ellipsis
+    )
+}
+
+/// Formats the interim tab name shown while a git lookup is in flight.
+///
+/// This is the folder-only name with `busy_symbol` appended. Once the git
+/// result arrives, the tab is renamed to the final [`format_tab_name`] output,
+/// which clears the symbol.
+///
+/// # Examples
+///
+/// With `show_busy` enabled and the default symbol:
+/// - `format_busy_name("myproject", &config)` → `"myproject⋯"`
+pub fn format_busy_name(folder: &str, config: &FormatterConfig) -> String {
+    format!(
+        "{}{}",
+        format_tab_name(folder, None, config),
+        config.busy_symbol
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== FormatterConfig Tests ====================
+
+    #[test]
+    fn test_default_config() {
+        let config = FormatterConfig::default();
+        assert_eq!(config.folder_max_len, 10);
+        assert_eq!(config.folder_prefix_len, 5);
+        assert_eq!(config.folder_suffix_len, 4);
+        assert_eq!(config.branch_max_len, 5);
+        assert_eq!(config.branch_prefix_len, 1);
+        assert_eq!(config.branch_suffix_len, 4);
+        assert_eq!(config.separator, ":");
+        assert!(config.show_branch);
+        assert!(!config.show_busy);
+        assert_eq!(config.busy_symbol, "⋯");
+        assert!(!config.collapse_separators);
+        assert_eq!(
+            config.name_sources,
+            vec![NameSource::Folder, NameSource::Title]
+        );
+        assert_eq!(config.title_command_mode, TitleCommandMode::Whole);
+        assert!(!config.show_change_counts);
+        assert_eq!(config.staged_symbol, "+");
+        assert_eq!(config.unstaged_symbol, "~");
+        assert_eq!(config.reset_to, ResetMode::Keep);
+        assert!(config.branch_folder_override.is_empty());
+        assert_eq!(config.group_size, 0);
+        assert!(!config.smart_truncate);
+        assert_eq!(config.truncate_delimiters, "_-/.");
+        assert!(!config.show_clean);
+        assert_eq!(config.clean_symbol, "✓");
+        assert_eq!(
+            config.empty_folder_fallback,
+            EmptyFolderFallback::OriginalBasename
+        );
+        assert_eq!(config.truncated_branch_suffix, "");
+        assert_eq!(config.pr_format, "PR#{n}");
+        assert!(!config.collapse_branch_folder_overlap);
+        assert!(!config.strip_copy_suffixes);
+        assert!(!config.keep_numeric_suffix);
+        assert_eq!(config.narrow_threshold, 0);
+        assert_eq!(config.narrow_max_len, 6);
+        assert_eq!(config.folder_fixed_width, 0);
+        assert_eq!(config.branch_fixed_width, 0);
+        assert!(config.skip_commands.is_empty());
+        assert!(config.name_command.is_empty());
+        assert!(!config.grapheme_mode);
+        assert!(!config.show_nonprimary_remote);
+        assert_eq!(config.primary_remote, "origin");
+        assert_eq!(config.rename_on, RenameOn::AnyUpdate);
+        assert_eq!(config.title_trailing_separators, vec!["—", "–", "•"]);
+        assert_eq!(config.branch_word_max, 0);
+        assert!(config.mount_labels.is_empty());
+        assert!(!config.show_dir_freshness);
+        assert_eq!(config.fresh_minutes, 10);
+        assert_eq!(config.fresh_symbol, "~");
+        assert_eq!(config.branch_space_replacement, "-");
+        assert_eq!(config.sticky_cwd_ms, 0);
+        assert!(!config.compact_status);
+        assert_eq!(config.tab_prefix, "");
+        assert_eq!(config.total_max_len, 0);
+        assert_eq!(config.initial_name, "");
+        assert!(!config.show_kube_context);
+        assert_eq!(config.kube_symbol, "⎈");
+        assert!(config.folder_keep_keywords.is_empty());
+        assert!(!config.monorepo_mode);
+        assert_eq!(config.monorepo_min_tabs, 3);
+        assert_eq!(config.git_binary, "git");
+        assert!(config.git_extra_args.is_empty());
+        assert_eq!(config.vcs_order, vec![Vcs::Git]);
+        assert!(!config.show_ahead_behind);
+        assert!(config.compact_ahead_behind);
+        assert!(!config.rename_session);
+        assert_eq!(config.new_pane_name, "");
+        assert_eq!(config.folder_truncate_mode, TruncateMode::Middle);
+        assert_eq!(config.branch_truncate_mode, TruncateMode::Middle);
+        assert_eq!(config.ellipsis, "…");
+        assert!(!config.strip_folder_prefix_from_branch);
+        assert!(config.branch_abbreviations.is_empty());
+        assert_eq!(config.name_prefix, "");
+        assert_eq!(config.name_suffix, "");
+        assert!(!config.show_dirty);
+        assert_eq!(config.dirty_marker, "*");
+        assert_eq!(config.name_ttl_secs, 0);
+        assert_eq!(config.machine_format, "");
+        assert!(config.slow_repos.is_empty());
+        assert_eq!(config.git_cache_ttl_ms, 5000);
+        assert_eq!(config.settle_ms, 0);
+        assert!(!config.eager_folder);
+        assert_eq!(config.folder_case, Case::None);
+        assert!(config.respect_manual_rename);
+        assert!(!config.show_forge);
+        assert_eq!(config.family_segment, 0);
+        assert_eq!(config.family_base, "");
+        assert_eq!(config.name_template, "");
+        assert!(!config.strip_emoji);
+        assert_eq!(config.base_branch, "");
+        assert!(!config.show_unc_server);
+        assert!(!config.single_rename);
+        assert_eq!(config.single_rename_timeout_ms, 300);
+        assert_eq!(config.folder_depth, 1);
+        assert!(!config.show_icon);
+        assert_eq!(
+            config.project_icons[0],
+            ("Cargo.toml".to_string(), "\u{e7a8}".to_string())
+        );
+        assert_eq!(config.project_icons.len(), 4);
+        assert!(!config.show_shell_icon);
+        assert_eq!(config.shell_icons.len(), 4);
+        assert!(!config.show_layout);
+        assert_eq!(config.branch_case, Case::None);
+        assert!(config.folder_replace.is_empty());
+        assert_eq!(config.hide_default_branches, vec!["main", "master"]);
+        assert!(config.non_vcs_symbol.is_empty());
+        assert!(config.git_branch_command.is_empty());
+        assert!(config.separator_wide.is_empty());
+        assert!(config.separator_narrow.is_empty());
+        assert_eq!(config.separator_width_threshold, 0);
+        assert_eq!(config.max_badges, 0);
+        assert_eq!(config.badge_priority, Badge::ALL);
+        assert!(config.attention_symbol.is_empty());
+        assert_eq!(config.title_separators, vec![": ", ":"]);
+        assert_eq!(config.stale_days, 0);
+    }
+
+    #[test]
+    fn test_from_config_empty() {
+        let map = BTreeMap::new();
+        let config = FormatterConfig::from_config(&map);
+        assert_eq!(config.folder_max_len, 10); // defaults preserved
+    }
+
+    fn all_values_map() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("folder_max_len".to_string(), "20".to_string()),
+            ("folder_prefix_len".to_string(), "8".to_string()),
+            ("folder_suffix_len".to_string(), "6".to_string()),
+            ("branch_max_len".to_string(), "10".to_string()),
+            ("branch_prefix_len".to_string(), "3".to_string()),
+            ("branch_suffix_len".to_string(), "5".to_string()),
+            ("separator".to_string(), " | ".to_string()),
+            ("show_branch".to_string(), "false".to_string()),
+            ("show_busy".to_string(), "true".to_string()),
+            ("busy_symbol".to_string(), "…".to_string()),
+            ("collapse_separators".to_string(), "true".to_string()),
+            ("name_sources".to_string(), "repo_root,folder".to_string()),
+            ("title_command_mode".to_string(), "first_word".to_string()),
+            ("show_change_counts".to_string(), "true".to_string()),
+            ("staged_symbol".to_string(), "●".to_string()),
+            ("unstaged_symbol".to_string(), "✚".to_string()),
+            ("reset_to".to_string(), "index".to_string()),
+            (
+                "branch_folder_override".to_string(),
+                "gh-pages=docs".to_string(),
+            ),
+            ("group_size".to_string(), "4".to_string()),
+            ("smart_truncate".to_string(), "true".to_string()),
+            ("truncate_delimiters".to_string(), "_".to_string()),
+            ("show_clean".to_string(), "true".to_string()),
+            ("clean_symbol".to_string(), "ok".to_string()),
+            ("empty_folder_fallback".to_string(), "?".to_string()),
+            ("truncated_branch_suffix".to_string(), "»".to_string()),
+            ("pr_format".to_string(), "!{n}".to_string()),
+            (
+                "collapse_branch_folder_overlap".to_string(),
+                "true".to_string(),
+            ),
+            ("strip_copy_suffixes".to_string(), "true".to_string()),
+            ("keep_numeric_suffix".to_string(), "true".to_string()),
+            ("narrow_threshold".to_string(), "40".to_string()),
+            ("narrow_max_len".to_string(), "4".to_string()),
+            ("folder_fixed_width".to_string(), "8".to_string()),
+            ("branch_fixed_width".to_string(), "6".to_string()),
+            ("skip_commands".to_string(), "htop, watch,".to_string()),
+            ("name_command".to_string(), "basename {path}".to_string()),
+            ("grapheme_mode".to_string(), "true".to_string()),
+            ("show_nonprimary_remote".to_string(), "true".to_string()),
+            ("primary_remote".to_string(), "upstream".to_string()),
+            ("rename_on".to_string(), "focus_change".to_string()),
+            ("title_trailing_separators".to_string(), "|, ".to_string()),
+            ("branch_word_max".to_string(), "3".to_string()),
+            ("mount_labels".to_string(), "/mnt/data=data".to_string()),
+            ("show_dir_freshness".to_string(), "true".to_string()),
+            ("fresh_minutes".to_string(), "30".to_string()),
+            ("fresh_symbol".to_string(), "*".to_string()),
+            ("branch_space_replacement".to_string(), "_".to_string()),
+            ("sticky_cwd_ms".to_string(), "1500".to_string()),
+            ("compact_status".to_string(), "true".to_string()),
+            ("tab_prefix".to_string(), "work/".to_string()),
+            ("total_max_len".to_string(), "24".to_string()),
+            ("initial_name".to_string(), "namey…".to_string()),
+            ("show_kube_context".to_string(), "true".to_string()),
+            ("kube_symbol".to_string(), "k8s:".to_string()),
+            ("folder_keep_keywords".to_string(), "api, web".to_string()),
+            ("monorepo_mode".to_string(), "true".to_string()),
+            ("monorepo_min_tabs".to_string(), "2".to_string()),
+            ("git_binary".to_string(), "/opt/git/bin/git".to_string()),
+            ("vcs_order".to_string(), "git,hg,jj".to_string()),
+            ("show_ahead_behind".to_string(), "true".to_string()),
+            ("compact_ahead_behind".to_string(), "false".to_string()),
+            ("rename_session".to_string(), "true".to_string()),
+            ("new_pane_name".to_string(), "new".to_string()),
+            ("folder_truncate_mode".to_string(), "end".to_string()),
+            ("branch_truncate_mode".to_string(), "start".to_string()),
+            ("ellipsis".to_string(), "..".to_string()),
+            (
+                "strip_folder_prefix_from_branch".to_string(),
+                "true".to_string(),
+            ),
+            (
+                "branch_abbreviations".to_string(),
+                "authentication=auth".to_string(),
+            ),
+            ("name_prefix".to_string(), "[".to_string()),
+            ("name_suffix".to_string(), "]".to_string()),
+            ("show_dirty".to_string(), "true".to_string()),
+            ("dirty_marker".to_string(), "!".to_string()),
+            ("name_ttl_secs".to_string(), "60".to_string()),
+            (
+                "machine_format".to_string(),
+                "{folder}|{branch}|{dirty}".to_string(),
+            ),
+            ("slow_repos".to_string(), "~/mono, /src/big".to_string()),
+            ("git_cache_ttl_ms".to_string(), "0".to_string()),
+            ("settle_ms".to_string(), "150".to_string()),
+            ("eager_folder".to_string(), "true".to_string()),
+            ("folder_case".to_string(), "sentence".to_string()),
+            ("respect_manual_rename".to_string(), "false".to_string()),
+            ("show_forge".to_string(), "true".to_string()),
+            ("family_segment".to_string(), "1".to_string()),
+            ("family_base".to_string(), "~/work".to_string()),
+            (
+                "name_template".to_string(),
+                "{index}:{folder}@{branch}".to_string(),
+            ),
+            ("strip_emoji".to_string(), "true".to_string()),
+            ("base_branch".to_string(), "main".to_string()),
+            ("show_unc_server".to_string(), "true".to_string()),
+            ("single_rename".to_string(), "true".to_string()),
+            ("single_rename_timeout_ms".to_string(), "1000".to_string()),
+            ("folder_depth".to_string(), "2".to_string()),
+            ("show_icon".to_string(), "true".to_string()),
+            (
+                "project_icons".to_string(),
+                "deno.json=D,Cargo.toml=R".to_string(),
+            ),
+            ("show_shell_icon".to_string(), "true".to_string()),
+            ("shell_icons".to_string(), "fish=><>".to_string()),
+            ("show_layout".to_string(), "true".to_string()),
+            ("branch_case".to_string(), "upper".to_string()),
+            ("folder_replace".to_string(), "_= ".to_string()),
+            (
+                "hide_default_branches".to_string(),
+                "trunk, develop".to_string(),
+            ),
+            ("non_vcs_symbol".to_string(), " ∅".to_string()),
+            (
+                "git_branch_command".to_string(),
+                r#"git --git-dir="$1/.bare" --work-tree="$1" branch --show-current"#.to_string(),
+            ),
+            ("separator_wide".to_string(), " : ".to_string()),
+            ("separator_narrow".to_string(), ":".to_string()),
+            ("separator_width_threshold".to_string(), "100".to_string()),
+            ("max_badges".to_string(), "2".to_string()),
+            ("badge_priority".to_string(), "kube,dirty".to_string()),
+            ("attention_symbol".to_string(), " !".to_string()),
+            ("title_separators".to_string(), " — ,: ".to_string()),
+            ("stale_days".to_string(), "14".to_string()),
+            (
+                "git_extra_args".to_string(),
+                "-c safe.directory=*".to_string(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_from_config_all_values() {
+        let map = all_values_map();
+        let config = FormatterConfig::from_config(&map);
+        assert_eq!(config.folder_max_len, 20);
+        assert_eq!(config.folder_prefix_len, 8);
+        assert_eq!(config.folder_suffix_len, 6);
+        assert_eq!(config.branch_max_len, 10);
+        assert_eq!(config.branch_prefix_len, 3);
+        assert_eq!(config.branch_suffix_len, 5);
+        assert_eq!(config.separator, " | ");
+        assert!(!config.show_branch);
+        assert!(config.show_busy);
+        assert_eq!(config.busy_symbol, "…");
+        assert!(config.collapse_separators);
+        assert_eq!(
+            config.name_sources,
+            vec![NameSource::RepoRoot, NameSource::Folder]
+        );
+        assert_eq!(config.title_command_mode, TitleCommandMode::FirstWord);
+        assert!(config.show_change_counts);
+        assert_eq!(config.staged_symbol, "●");
+        assert_eq!(config.unstaged_symbol, "✚");
+        assert_eq!(config.reset_to, ResetMode::Index);
+        assert_eq!(
+            config.branch_folder_override,
+            vec![("gh-pages".to_string(), "docs".to_string())]
+        );
+        assert_eq!(config.group_size, 4);
+        assert!(config.smart_truncate);
+        assert_eq!(config.truncate_delimiters, "_");
+        assert!(config.show_clean);
+        assert_eq!(config.clean_symbol, "ok");
+        assert_eq!(
+            config.empty_folder_fallback,
+            EmptyFolderFallback::Literal("?".to_string())
+        );
+        assert_eq!(config.truncated_branch_suffix, "»");
+        assert_eq!(config.pr_format, "!{n}");
+        assert!(config.collapse_branch_folder_overlap);
+        assert!(config.strip_copy_suffixes);
+        assert!(config.keep_numeric_suffix);
+        assert_eq!(config.narrow_threshold, 40);
+        assert_eq!(config.narrow_max_len, 4);
+        assert_eq!(config.folder_fixed_width, 8);
+        assert_eq!(config.branch_fixed_width, 6);
+        assert_eq!(config.skip_commands, vec!["htop", "watch"]);
+        assert_eq!(config.name_command, "basename {path}");
+        assert!(config.grapheme_mode);
+        assert!(config.show_nonprimary_remote);
+        assert_eq!(config.primary_remote, "upstream");
+        assert_eq!(config.rename_on, RenameOn::FocusChange);
+        assert_eq!(config.title_trailing_separators, vec!["|"]);
+        assert_eq!(config.branch_word_max, 3);
+        assert_eq!(
+            config.mount_labels,
+            vec![("/mnt/data".to_string(), "data".to_string())]
+        );
+        assert!(config.show_dir_freshness);
+        assert_eq!(config.fresh_minutes, 30);
+        assert_eq!(config.fresh_symbol, "*");
+        assert_eq!(config.branch_space_replacement, "_");
+        assert_eq!(config.sticky_cwd_ms, 1500);
+        assert!(config.compact_status);
+        assert_eq!(config.tab_prefix, "work/");
+        assert_eq!(config.total_max_len, 24);
+        assert_eq!(config.initial_name, "namey…");
+        assert!(config.show_kube_context);
+        assert_eq!(config.kube_symbol, "k8s:");
+        assert_eq!(config.folder_keep_keywords, vec!["api", "web"]);
+        assert!(config.monorepo_mode);
+        assert_eq!(config.monorepo_min_tabs, 2);
+        assert_eq!(config.git_binary, "/opt/git/bin/git");
+        assert_eq!(config.git_extra_args, vec!["-c", "safe.directory=*"]);
+        assert_eq!(config.vcs_order, vec![Vcs::Git, Vcs::Hg, Vcs::Jj]);
+        assert!(config.show_ahead_behind);
+        assert!(!config.compact_ahead_behind);
+        assert!(config.rename_session);
+        assert_eq!(config.new_pane_name, "new");
+        assert_eq!(config.folder_truncate_mode, TruncateMode::End);
+        assert_eq!(config.branch_truncate_mode, TruncateMode::Start);
+        assert_eq!(config.ellipsis, "..");
+        assert!(config.strip_folder_prefix_from_branch);
+        assert_eq!(
+            config.branch_abbreviations,
+            vec![("authentication".to_string(), "auth".to_string())]
+        );
+        assert_eq!(config.name_prefix, "[");
+        assert_eq!(config.name_suffix, "]");
+        assert!(config.show_dirty);
+        assert_eq!(config.dirty_marker, "!");
+        assert_eq!(config.name_ttl_secs, 60);
+        assert_eq!(config.machine_format, "{folder}|{branch}|{dirty}");
+        assert_eq!(config.slow_repos, vec!["~/mono", "/src/big"]);
+        assert_eq!(config.git_cache_ttl_ms, 0);
+        assert_eq!(config.settle_ms, 150);
+        assert!(config.eager_folder);
+        assert_eq!(config.folder_case, Case::Sentence);
+        assert!(!config.respect_manual_rename);
+        assert!(config.show_forge);
+        assert_eq!(config.family_segment, 1);
+        assert_eq!(config.family_base, "~/work");
+        assert_eq!(config.name_template, "{index}:{folder}@{branch}");
+        assert!(config.strip_emoji);
+        assert_eq!(config.base_branch, "main");
+        assert!(config.show_unc_server);
+        assert!(config.single_rename);
+        assert_eq!(config.single_rename_timeout_ms, 1000);
+        assert_eq!(config.folder_depth, 2);
+        assert!(config.show_icon);
+        assert_eq!(
+            config.project_icons,
+            vec![
+                ("deno.json".to_string(), "D".to_string()),
+                ("Cargo.toml".to_string(), "R".to_string()),
+            ]
+        );
+        assert!(config.show_shell_icon);
+        assert_eq!(
+            config.shell_icons,
+            vec![("fish".to_string(), "><>".to_string())]
+        );
+        assert!(config.show_layout);
+        assert_eq!(config.branch_case, Case::Upper);
+        assert_eq!(
+            config.folder_replace,
+            vec![("_".to_string(), " ".to_string())]
+        );
+        assert_eq!(config.hide_default_branches, vec!["trunk", "develop"]);
+        assert_eq!(config.non_vcs_symbol, " ∅");
+        assert_eq!(
+            config.git_branch_command,
+            r#"git --git-dir="$1/.bare" --work-tree="$1" branch --show-current"#
+        );
+        assert_eq!(config.separator_wide, " : ");
+        assert_eq!(config.separator_narrow, ":");
+        assert_eq!(config.separator_width_threshold, 100);
+        assert_eq!(config.max_badges, 2);
+        assert_eq!(config.badge_priority, vec![Badge::Kube, Badge::Dirty]);
+        assert_eq!(config.attention_symbol, " !");
+        assert_eq!(config.title_separators, vec![" — ", ": "]);
+        assert_eq!(config.stale_days, 14);
+        // Every key above is a real option with a usable value
+        assert_eq!(FormatterConfig::try_from_config(&map).err(), None);
+    }
+
+    // ==================== builder() Tests ====================
+
+    #[test]
+    fn test_builder_defaults_match_default() {
+        assert_eq!(
+            format!("{:?}", FormatterConfig::builder().build()),
+            format!("{:?}", FormatterConfig::default())
+        );
+    }
+
+    #[test]
+    fn test_builder_all_values() {
+        let config = FormatterConfig::builder()
+            .folder_max_len(20)
+            .folder_prefix_len(8)
+            .folder_suffix_len(6)
+            .branch_max_len(10)
+            .branch_prefix_len(3)
+            .branch_suffix_len(5)
+            .separator(" | ")
+            .show_branch(false)
+            .show_busy(true)
+            .busy_symbol("…")
+            .collapse_separators(true)
+            .name_sources(vec![NameSource::RepoRoot, NameSource::Folder])
+            .title_command_mode(TitleCommandMode::FirstWord)
+            .show_change_counts(true)
+            .staged_symbol("●")
+            .unstaged_symbol("✚")
+            .reset_to(ResetMode::Index)
+            .branch_folder_override(vec![("gh-pages".to_string(), "docs".to_string())])
+            .group_size(4)
+            .smart_truncate(true)
+            .truncate_delimiters("_")
+            .show_clean(true)
+            .clean_symbol("ok")
+            .empty_folder_fallback(EmptyFolderFallback::Literal("?".to_string()))
+            .truncated_branch_suffix("»")
+            .pr_format("!{n}")
+            .collapse_branch_folder_overlap(true)
+            .strip_copy_suffixes(true)
+            .keep_numeric_suffix(true)
+            .narrow_threshold(40)
+            .narrow_max_len(4)
+            .folder_fixed_width(8)
+            .branch_fixed_width(6)
+            .skip_commands(vec!["htop", "watch"])
+            .name_command("basename {path}")
+            .grapheme_mode(true)
+            .show_nonprimary_remote(true)
+            .primary_remote("upstream")
+            .rename_on(RenameOn::FocusChange)
+            .title_trailing_separators(vec!["|"])
+            .branch_word_max(3)
+            .mount_labels(vec![("/mnt/data".to_string(), "data".to_string())])
+            .show_dir_freshness(true)
+            .fresh_minutes(30)
+            .fresh_symbol("*")
+            .branch_space_replacement("_")
+            .sticky_cwd_ms(1500)
+            .compact_status(true)
+            .tab_prefix("work/")
+            .total_max_len(24)
+            .initial_name("namey…")
+            .show_kube_context(true)
+            .kube_symbol("k8s:")
+            .folder_keep_keywords(vec!["api", "web"])
+            .monorepo_mode(true)
+            .monorepo_min_tabs(2)
+            .git_binary("/opt/git/bin/git")
+            .git_extra_args(vec!["-c", "safe.directory=*"])
+            .vcs_order(vec![Vcs::Git, Vcs::Hg, Vcs::Jj])
+            .show_ahead_behind(true)
+            .compact_ahead_behind(false)
+            .rename_session(true)
+            .new_pane_name("new")
+            .folder_truncate_mode(TruncateMode::End)
+            .branch_truncate_mode(TruncateMode::Start)
+            .ellipsis("..")
+            .strip_folder_prefix_from_branch(true)
+            .branch_abbreviations(vec![("authentication".to_string(), "auth".to_string())])
+            .name_prefix("[")
+            .name_suffix("]")
+            .show_dirty(true)
+            .dirty_marker("!")
+            .name_ttl_secs(60)
+            .machine_format("{folder}|{branch}|{dirty}")
+            .slow_repos(vec!["~/mono", "/src/big"])
+            .git_cache_ttl_ms(0)
+            .settle_ms(150)
+            .eager_folder(true)
+            .folder_case(Case::Sentence)
+            .respect_manual_rename(false)
+            .show_forge(true)
+            .family_segment(1)
+            .family_base("~/work")
+            .name_template("{index}:{folder}@{branch}")
+            .strip_emoji(true)
+            .base_branch("main")
+            .show_unc_server(true)
+            .single_rename(true)
+            .single_rename_timeout_ms(1000)
+            .folder_depth(2)
+            .show_icon(true)
+            .project_icons(vec![
+                ("deno.json".to_string(), "D".to_string()),
+                ("Cargo.toml".to_string(), "R".to_string()),
+            ])
+            .show_shell_icon(true)
+            .shell_icons(vec![("fish".to_string(), "><>".to_string())])
+            .show_layout(true)
+            .branch_case(Case::Upper)
+            .folder_replace(vec![("_".to_string(), " ".to_string())])
+            .hide_default_branches(vec!["trunk", "develop"])
+            .non_vcs_symbol(" ∅")
+            .git_branch_command(
+                r#"git --git-dir="$1/.bare" --work-tree="$1" branch --show-current"#,
+            )
+            .separator_wide(" : ")
+            .separator_narrow(":")
+            .separator_width_threshold(100)
+            .max_badges(2)
+            .badge_priority(vec![Badge::Kube, Badge::Dirty])
+            .attention_symbol(" !")
+            .title_separators(vec![" — ", ": "])
+            .stale_days(14)
+            .build();
+        // The same settings as test_from_config_all_values, field for field
+        assert_eq!(
+            format!("{:?}", config),
+            format!("{:?}", FormatterConfig::from_config(&all_values_map()))
+        );
+    }
+
+    #[test]
+    fn test_next_vcs() {
+        let config = FormatterConfig {
+            vcs_order: vec![Vcs::Git, Vcs::Hg],
+            ..Default::default()
+        };
+        assert_eq!(config.next_vcs(Vcs::Git), Some(Vcs::Hg));
+        assert_eq!(config.next_vcs(Vcs::Hg), None);
+        assert_eq!(config.next_vcs(Vcs::Jj), None);
+        assert_eq!(FormatterConfig::default().next_vcs(Vcs::Git), None);
+    }
+
+    #[test]
+    fn test_vcs_selects_single_system() {
+        let map = BTreeMap::from([("vcs".to_string(), "jj".to_string())]);
+        assert_eq!(FormatterConfig::from_config(&map).vcs_order, vec![Vcs::Jj]);
+
+        let map = BTreeMap::from([
+            ("vcs".to_string(), "jj".to_string()),
+            ("vcs_order".to_string(), "hg,git".to_string()),
+        ]);
+        assert_eq!(
+            FormatterConfig::from_config(&map).vcs_order,
+            vec![Vcs::Hg, Vcs::Git]
+        );
+
+        let map = BTreeMap::from([("vcs".to_string(), "svn".to_string())]);
+        assert_eq!(FormatterConfig::from_config(&map).vcs_order, vec![Vcs::Git]);
+    }
+
+    #[test]
+    fn test_vcs_auto_enables_detection() {
+        let config = FormatterConfig::from_config(&BTreeMap::new());
+        assert!(!config.detect_vcs);
+        let map = BTreeMap::from([("vcs".to_string(), " auto".to_string())]);
+        let config = FormatterConfig::from_config(&map);
+        assert!(config.detect_vcs);
+        assert_eq!(config.vcs_order, vec![Vcs::Git]);
+    }
+
+    #[test]
+    fn test_vcs_order_invalid_keeps_default() {
+        let config = FormatterConfig::from_config(&BTreeMap::from([(
+            "vcs_order".to_string(),
+            "svn".to_string(),
+        )]));
+        assert_eq!(config.vcs_order, vec![Vcs::Git]);
+    }
+
+    #[test]
+    fn test_from_config_invalid_numbers_ignored() {
+        let map = BTreeMap::from([
+            ("folder_max_len".to_string(), "not_a_number".to_string()),
+            ("branch_max_len".to_string(), "-5".to_string()), // negative, invalid for usize
+            ("title_command_mode".to_string(), "loud".to_string()),
+            ("reset_to".to_string(), "tab".to_string()),
+            ("rename_on".to_string(), "always".to_string()),
+            ("branch_truncate_mode".to_string(), "left".to_string()),
+        ]);
+        let config = FormatterConfig::from_config(&map);
+        assert_eq!(config.folder_max_len, 10); // default preserved
+        assert_eq!(config.branch_max_len, 5); // default preserved
+        assert_eq!(config.title_command_mode, TitleCommandMode::Whole);
+        assert_eq!(config.reset_to, ResetMode::Keep);
+        assert_eq!(config.rename_on, RenameOn::AnyUpdate);
+        assert_eq!(config.branch_truncate_mode, TruncateMode::Middle);
+    }
+
+    #[test]
+    fn test_apply_preset_minimal() {
+        let config = apply_preset("minimal");
+        assert!(!config.show_branch);
+        assert!(!config.needs_git());
+        assert_eq!(config.folder_max_len, 10);
+    }
+
+    #[test]
+    fn test_apply_preset_full() {
+        let config = apply_preset("full");
+        assert!(config.show_branch);
+        assert!(config.show_change_counts);
+    }
+
+    #[test]
+    fn test_apply_preset_compact() {
+        let config = apply_preset("compact");
+        assert_eq!(config.folder_max_len, 6);
+        assert_eq!(config.folder_prefix_len, 3);
+        assert_eq!(config.folder_suffix_len, 2);
+        assert_eq!(config.branch_max_len, 4);
+        assert_eq!(config.branch_prefix_len, 2);
+        assert_eq!(config.branch_suffix_len, 1);
+        assert_eq!(
+            format_tab_name("my_project", Some("feature"), &config),
+            "my_…ct:fe…e"
+        );
+    }
+
+    #[test]
+    fn test_apply_preset_unknown_is_default() {
+        let config = apply_preset("fancy");
+        assert!(config.show_branch);
+        assert_eq!(config.folder_max_len, 10);
+    }
+
+    #[test]
+    fn test_from_config_overrides_win_over_preset() {
+        let map = BTreeMap::from([
+            ("preset".to_string(), "compact".to_string()),
+            ("folder_max_len".to_string(), "8".to_string()),
+        ]);
+        let config = FormatterConfig::from_config(&map);
+        assert_eq!(config.folder_max_len, 8);
+        assert_eq!(config.branch_max_len, 4);
+
+        let map = BTreeMap::from([
+            ("preset".to_string(), "minimal".to_string()),
+            ("show_branch".to_string(), "true".to_string()),
+        ]);
+        assert!(FormatterConfig::from_config(&map).show_branch);
+    }
+
+    #[test]
+    fn test_from_config_empty_title_trailing_separators_disables() {
+        let map = BTreeMap::from([("title_trailing_separators".to_string(), String::new())]);
+        assert!(FormatterConfig::from_config(&map)
+            .title_trailing_separators
+            .is_empty());
+    }
+
+    #[test]
+    fn test_from_config_show_branch_truthy() {
+        // Any value except "false" should be true
+        let map = BTreeMap::from([("show_branch".to_string(), "true".to_string())]);
+        assert!(FormatterConfig::from_config(&map).show_branch);
+
+        let map = BTreeMap::from([("show_branch".to_string(), "yes".to_string())]);
         assert!(FormatterConfig::from_config(&map).show_branch);
 
-        let map = BTreeMap::from([("show_branch".to_string(), "1".to_string())]);
-        assert!(FormatterConfig::from_config(&map).show_branch);
+        let map = BTreeMap::from([("show_branch".to_string(), "1".to_string())]);
+        assert!(FormatterConfig::from_config(&map).show_branch);
+
+        let map = BTreeMap::from([("show_branch".to_string(), "false".to_string())]);
+        assert!(!FormatterConfig::from_config(&map).show_branch);
+    }
+
+    #[test]
+    fn test_needs_git_follows_show_branch() {
+        assert!(FormatterConfig::default().needs_git());
+
+        let map = BTreeMap::from([("show_branch".to_string(), "false".to_string())]);
+        assert!(!FormatterConfig::from_config(&map).needs_git());
+    }
+
+    #[test]
+    fn test_needs_git_for_repo_root_source() {
+        let map = BTreeMap::from([
+            ("show_branch".to_string(), "false".to_string()),
+            ("name_sources".to_string(), "repo_root,folder".to_string()),
+        ]);
+        assert!(FormatterConfig::from_config(&map).needs_git());
+    }
+
+    #[test]
+    fn test_needs_git_for_name_command() {
+        let map = BTreeMap::from([
+            ("show_branch".to_string(), "false".to_string()),
+            ("name_command".to_string(), "echo {branch}".to_string()),
+        ]);
+        assert!(FormatterConfig::from_config(&map).needs_git());
+    }
+
+    #[test]
+    fn test_needs_status() {
+        assert!(!FormatterConfig::default().needs_status());
+        assert!(counts_config().needs_status());
+        let config = FormatterConfig {
+            show_clean: true,
+            ..Default::default()
+        };
+        assert!(config.needs_status());
+    }
+
+    #[test]
+    fn test_skips_command() {
+        let config = FormatterConfig {
+            skip_commands: vec!["htop".to_string(), "watch".to_string()],
+            ..Default::default()
+        };
+        assert!(config.skips_command(Some("htop")));
+        assert!(config.skips_command(Some("/usr/bin/watch -n 1 ls")));
+        assert!(!config.skips_command(Some("vim htop")));
+        assert!(!config.skips_command(None));
+        assert!(!FormatterConfig::default().skips_command(Some("htop")));
+    }
+
+    #[test]
+    fn test_is_narrow() {
+        let config = FormatterConfig {
+            narrow_threshold: 30,
+            ..Default::default()
+        };
+        assert!(config.is_narrow(Some(20)));
+        assert!(!config.is_narrow(Some(30)));
+        assert!(!config.is_narrow(None));
+        assert!(!FormatterConfig::default().is_narrow(Some(1)));
+    }
+
+    #[test]
+    fn test_for_width_applies_narrow_profile() {
+        let config = FormatterConfig {
+            narrow_threshold: 30,
+            ..Default::default()
+        };
+        let wide = config.for_width(Some(120));
+        assert!(matches!(wide, Cow::Borrowed(_)));
+        assert_eq!(
+            format_tab_name("my_project_name", Some("feature"), &wide),
+            "my_pr…name:featu"
+        );
+
+        let narrow = config.for_width(Some(20));
+        assert_eq!(narrow.folder_max_len, 6);
+        assert_eq!(narrow.branch_max_len, 5);
+        assert_eq!(
+            format_tab_name("my_project_name", Some("feature"), &narrow),
+            "my_…me:fe…re"
+        );
+    }
+
+    #[test]
+    fn test_for_width_picks_separator() {
+        let config = FormatterConfig {
+            separator_wide: " : ".to_string(),
+            separator_narrow: ":".to_string(),
+            separator_width_threshold: 100,
+            ..Default::default()
+        };
+        let wide = config.for_width(Some(100));
+        assert_eq!(format_tab_name("proj", Some("dev"), &wide), "proj : dev");
+        let narrow = config.for_width(Some(99));
+        assert_eq!(format_tab_name("proj", Some("dev"), &narrow), "proj:dev");
+        // Before the width is known the bar is assumed wide
+        assert_eq!(config.for_width(None).separator, " : ");
+    }
+
+    #[test]
+    fn test_for_width_separator_fallbacks() {
+        let config = FormatterConfig {
+            separator: "/".to_string(),
+            separator_narrow: ":".to_string(),
+            ..Default::default()
+        };
+        // Without a threshold the plain separator always applies
+        assert!(matches!(config.for_width(Some(10)), Cow::Borrowed(_)));
+        let config = FormatterConfig {
+            separator_width_threshold: 50,
+            ..config
+        };
+        assert_eq!(config.for_width(Some(10)).separator, ":");
+        assert_eq!(config.for_width(Some(80)).separator, "/");
+    }
+
+    #[test]
+    fn test_from_config_show_upstream_alias() {
+        let map = BTreeMap::from([("show_upstream".to_string(), "true".to_string())]);
+        assert!(FormatterConfig::from_config(&map).show_ahead_behind);
+        let map = BTreeMap::from([
+            ("show_upstream".to_string(), "true".to_string()),
+            ("show_ahead_behind".to_string(), "false".to_string()),
+        ]);
+        assert!(!FormatterConfig::from_config(&map).show_ahead_behind);
+    }
+
+    #[test]
+    fn test_from_config_settle_ms_aliases() {
+        let map = BTreeMap::from([("debounce_ms".to_string(), "200".to_string())]);
+        assert_eq!(FormatterConfig::from_config(&map).settle_ms, 200);
+        let map = BTreeMap::from([("title_stability_ms".to_string(), "80".to_string())]);
+        assert_eq!(FormatterConfig::from_config(&map).settle_ms, 80);
+
+        // settle_ms wins over the deprecated keys
+        let map = BTreeMap::from([
+            ("settle_ms".to_string(), "100".to_string()),
+            ("debounce_ms".to_string(), "200".to_string()),
+        ]);
+        assert_eq!(FormatterConfig::from_config(&map).settle_ms, 100);
+    }
+
+    // ==================== try_from_config Tests ====================
+
+    #[test]
+    fn test_try_from_config_accepts_valid_settings() {
+        assert!(FormatterConfig::try_from_config(&BTreeMap::new()).is_ok());
+        let map = BTreeMap::from([
+            ("home_dir".to_string(), "/Users/me".to_string()),
+            ("show_busy".to_string(), "false".to_string()),
+            ("debounce_ms".to_string(), "150".to_string()),
+        ]);
+        assert_eq!(
+            FormatterConfig::try_from_config(&map).map(|c| c.settle_ms),
+            Ok(150)
+        );
+    }
+
+    #[test]
+    fn test_try_from_config_unknown_key() {
+        let map = BTreeMap::from([("folder_max_length".to_string(), "20".to_string())]);
+        assert_eq!(
+            FormatterConfig::try_from_config(&map).err(),
+            Some(vec![ConfigError::UnknownKey(
+                "folder_max_length".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_try_from_config_invalid_values() {
+        let map = BTreeMap::from([
+            ("folder_max_len".to_string(), "ten".to_string()),
+            ("show_busy".to_string(), "yes".to_string()),
+            ("folder_case".to_string(), "shout".to_string()),
+            ("folder_depth".to_string(), "0".to_string()),
+            ("vcs".to_string(), "svn".to_string()),
+        ]);
+        let invalid = |key: &str, value: &str| ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            FormatterConfig::try_from_config(&map).err(),
+            Some(vec![
+                invalid("folder_max_len", "ten"),
+                invalid("show_busy", "yes"),
+                invalid("folder_case", "shout"),
+                invalid("folder_depth", "0"),
+                invalid("vcs", "svn"),
+            ])
+        );
+
+        // The lenient path keeps the defaults for all of them
+        let config = FormatterConfig::from_config(&map);
+        assert_eq!(config.folder_max_len, 10);
+        assert!(!config.show_busy);
+        assert_eq!(config.folder_depth, 1);
+    }
+
+    #[test]
+    fn test_try_from_config_contradiction() {
+        let map = BTreeMap::from([
+            ("folder_prefix_len".to_string(), "8".to_string()),
+            ("folder_suffix_len".to_string(), "8".to_string()),
+        ]);
+        let errors = FormatterConfig::try_from_config(&map).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![ConfigError::Contradiction(
+                "folder_prefix_len + folder_suffix_len (8 + 8) exceeds folder_max_len (10)"
+                    .to_string()
+            )]
+        );
+        assert!(FormatterConfig::try_from_config(&BTreeMap::from([(
+            "branch_max_len".to_string(),
+            "2".to_string()
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_config_error_display() {
+        assert_eq!(
+            ConfigError::UnknownKey("colour".to_string()).to_string(),
+            "unknown key `colour`"
+        );
+        assert_eq!(
+            ConfigError::InvalidValue {
+                key: "show_busy".to_string(),
+                value: "yes".to_string()
+            }
+            .to_string(),
+            "invalid value `yes` for `show_busy`"
+        );
+    }
+
+    #[test]
+    fn test_from_config_invalid_name_sources_ignored() {
+        let map = BTreeMap::from([("name_sources".to_string(), "nope, ".to_string())]);
+        assert_eq!(
+            FormatterConfig::from_config(&map).name_sources,
+            vec![NameSource::Folder, NameSource::Title]
+        );
+    }
+
+    // ==================== parse_pairs() / glob_match() Tests ====================
+
+    #[test]
+    fn test_parse_pairs() {
+        assert_eq!(
+            parse_pairs("gh-pages=docs, release/*=rel"),
+            vec![
+                ("gh-pages".to_string(), "docs".to_string()),
+                ("release/*".to_string(), "rel".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pairs_skips_malformed() {
+        assert_eq!(
+            parse_pairs("nope,=empty,a=b,"),
+            vec![("a".to_string(), "b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gh-pages", "gh-pages"));
+        assert!(!glob_match("gh-pages", "gh-pages-old"));
+        assert!(glob_match("release/*", "release/1.2"));
+        assert!(glob_match("*-docs", "api-docs"));
+        assert!(glob_match("feat*x*z", "feature-xyz"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("release/*", "hotfix/1.2"));
+        assert!(!glob_match("a*c", "ab"));
+    }
+
+    // ==================== reset_name() Tests ====================
+
+    #[test]
+    fn test_reset_name_index() {
+        assert_eq!(reset_name(ResetMode::Index, 0), Some("Tab 1".to_string()));
+        assert_eq!(reset_name(ResetMode::Index, 4), Some("Tab 5".to_string()));
+    }
+
+    #[test]
+    fn test_reset_name_blank() {
+        assert_eq!(reset_name(ResetMode::Blank, 2), Some(String::new()));
+    }
+
+    #[test]
+    fn test_reset_name_keep() {
+        assert_eq!(reset_name(ResetMode::Keep, 2), None);
+    }
+
+    #[test]
+    fn test_reset_mode_parse() {
+        assert_eq!(ResetMode::parse("blank"), Some(ResetMode::Blank));
+        assert_eq!(ResetMode::parse("keep"), Some(ResetMode::Keep));
+        assert_eq!(ResetMode::parse(""), None);
+        assert_eq!(RenameOn::parse("any_update"), Some(RenameOn::AnyUpdate));
+        assert_eq!(RenameOn::parse("focus_change"), Some(RenameOn::FocusChange));
+        assert_eq!(RenameOn::parse("focus"), None);
+    }
+
+    // ==================== truncate() Tests ====================
+
+    #[test]
+    fn test_truncate_empty_string() {
+        assert_eq!(truncate("", 10, 5, 4, false, "…"), "");
+    }
+
+    #[test]
+    fn test_truncate_short() {
+        assert_eq!(truncate("hello", 10, 5, 4, false, "…"), "hello");
+    }
+
+    #[test]
+    fn test_truncate_exact_length() {
+        assert_eq!(truncate("helloworld", 10, 5, 4, false, "…"), "helloworld");
+    }
+
+    #[test]
+    fn test_truncate_one_over() {
+        assert_eq!(truncate("helloworld!", 10, 5, 4, false, "…"), "hello…rld!");
+    }
+
+    #[test]
+    fn test_truncate_long_folder() {
+        assert_eq!(
+            truncate("my_project_name", 10, 5, 4, false, "…"),
+            "my_pr…name"
+        );
+    }
+
+    #[test]
+    fn test_truncate_prefix_suffix_exceeds_length() {
+        // prefix(5) + suffix(4) = 9 >= char_count(8), so just take first max_len chars
+        assert_eq!(truncate("abcdefgh", 10, 5, 4, false, "…"), "abcdefgh");
+    }
+
+    #[test]
+    fn test_truncate_needed_exceeds_max() {
+        // needed = 1 + 1 + 4 = 6 > max_len(5), so just take first 5 chars
+        assert_eq!(truncate("feature-branch", 5, 1, 4, false, "…"), "featu");
+    }
+
+    #[test]
+    fn test_truncate_unicode() {
+        // Unicode characters should be handled correctly
+        assert_eq!(truncate("héllo", 10, 5, 4, false, "…"), "héllo");
+        assert_eq!(
+            truncate("日本語テスト文字列", 6, 2, 2, false, "…"),
+            "日本…字列"
+        );
+    }
+
+    #[test]
+    fn test_truncate_zero_max_len() {
+        assert_eq!(truncate("hello", 0, 0, 0, false, "…"), "");
+    }
+
+    #[test]
+    fn test_truncate_single_char_result() {
+        assert_eq!(truncate("hello", 1, 0, 0, false, "…"), "h");
+    }
+
+    #[test]
+    fn test_truncate_grapheme_mode_keeps_flag_intact() {
+        let flags = "🇯🇵🇫🇷🇩🇪🇮🇹";
+        // By char, the cut lands inside the second flag
+        assert_eq!(truncate(flags, 3, 0, 0, false, "…"), "🇯🇵🇫");
+        assert_eq!(truncate(flags, 3, 0, 0, true, "…"), "🇯🇵🇫🇷🇩🇪");
+        assert_eq!(truncate(flags, 3, 1, 1, true, "…"), "🇯🇵…🇮🇹");
+    }
+
+    #[test]
+    fn test_truncate_grapheme_mode_keeps_combining_accent() {
+        // "cafe" + combining acute accent, then more text
+        let name = "cafe\u{301}_project";
+        assert_eq!(truncate(name, 4, 0, 0, true, "…"), "cafe\u{301}");
+        assert_eq!(truncate(name, 4, 0, 0, false, "…"), "cafe");
+        assert_eq!(
+            truncate_at_delimiter("cafe\u{301}s_x", 5, "_", true, "…"),
+            "cafe\u{301}…"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_grapheme_mode() {
+        let config = FormatterConfig {
+            grapheme_mode: true,
+            folder_max_len: 3,
+            folder_prefix_len: 1,
+            folder_suffix_len: 1,
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("🇯🇵🇫🇷🇩🇪🇮🇹", None, &config), "🇯🇵…🇮🇹");
+        let fixed = FormatterConfig {
+            grapheme_mode: true,
+            folder_fixed_width: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}", None, &fixed),
+            "e\u{301}e\u{301}e\u{301}…"
+        );
+    }
+
+    // ==================== strip_copy_suffix() Tests ====================
+
+    #[test]
+    fn test_strip_copy_suffix_patterns() {
+        assert_eq!(strip_copy_suffix("project (1)", false), "project");
+        assert_eq!(strip_copy_suffix("project-2", false), "project");
+        assert_eq!(strip_copy_suffix("project-copy", false), "project");
+        assert_eq!(strip_copy_suffix("project-copy (2)", false), "project");
+    }
+
+    #[test]
+    fn test_strip_copy_suffix_leaves_other_names() {
+        assert_eq!(strip_copy_suffix("project", false), "project");
+        assert_eq!(strip_copy_suffix("my-project", false), "my-project");
+        assert_eq!(strip_copy_suffix("notes (draft)", false), "notes (draft)");
+        assert_eq!(strip_copy_suffix("notes ()", false), "notes ()");
+        assert_eq!(strip_copy_suffix("project-", false), "project-");
+        // Never strips down to nothing
+        assert_eq!(strip_copy_suffix("-copy", false), "-copy");
+        assert_eq!(strip_copy_suffix("(1)", false), "(1)");
+    }
+
+    #[test]
+    fn test_strip_copy_suffix_keep_numeric() {
+        assert_eq!(strip_copy_suffix("python-3", true), "python-3");
+        assert_eq!(strip_copy_suffix("python-3 (1)", true), "python-3");
+        assert_eq!(strip_copy_suffix("python-3-copy", true), "python-3");
+        assert_eq!(strip_copy_suffix("python-3", false), "python");
+    }
+
+    #[test]
+    fn test_format_tab_name_strip_copy_suffixes() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            strip_copy_suffixes: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("api (1)", Some("main"), &config),
+            "api:main"
+        );
+        assert_eq!(
+            format_tab_name(
+                "api (1)",
+                Some("main"),
+                &FormatterConfig {
+                    hide_default_branches: vec![],
+                    ..Default::default()
+                }
+            ),
+            "api (1):main"
+        );
+    }
+
+    // ==================== abbreviate_words() Tests ====================
+
+    #[test]
+    fn test_abbreviate_words_multi_word_branch() {
+        assert_eq!(
+            abbreviate_words("feature/implement-authentication-flow", 4, "-/"),
+            "feat/impl-auth-flow"
+        );
+        assert_eq!(abbreviate_words("fix/a-bc", 1, "-/"), "f/a-b");
+    }
+
+    #[test]
+    fn test_abbreviate_words_preserves_delimiters() {
+        assert_eq!(abbreviate_words("a--b//c", 2, "-/"), "a--b//c");
+        assert_eq!(abbreviate_words("-leading/trailing-", 3, "-/"), "-lea/tra-");
+        // Characters outside the delimiter set are part of a word
+        assert_eq!(abbreviate_words("hot_fix_now", 3, "-/"), "hot");
+        assert_eq!(abbreviate_words("main", 10, "-/"), "main");
+    }
+
+    // ==================== folder_replace Tests ====================
+
+    #[test]
+    fn test_parse_replacements_keeps_spaces() {
+        assert_eq!(
+            parse_replacements("_= ,-= "),
+            vec![
+                ("_".to_string(), " ".to_string()),
+                ("-".to_string(), " ".to_string()),
+            ]
+        );
+        assert!(parse_replacements("nope,= x").is_empty());
+    }
+
+    #[test]
+    fn test_folder_replace_before_truncation() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_replace: parse_replacements("_= ,-= "),
+            folder_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_long-project", Some("main"), &config),
+            "my long project:main"
+        );
+        let short = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_replace: parse_replacements("_= "),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", None, &short),
+            "my pr…name"
+        );
+    }
+
+    #[test]
+    fn test_folder_replace_absent_is_noop() {
+        let config = FormatterConfig::from_config(&BTreeMap::new());
+        assert_eq!(format_tab_name("my_proj", None, &config), "my_proj");
+    }
+
+    // ==================== apply_case() Tests ====================
+
+    #[test]
+    fn test_apply_case_sentence_normal_word() {
+        assert_eq!(apply_case("my_project", Case::Sentence), "My_project");
+        assert_eq!(apply_case("Api", Case::Sentence), "Api");
+        assert_eq!(apply_case("my_project", Case::None), "my_project");
+    }
+
+    #[test]
+    fn test_apply_case_sentence_skips_leading_punctuation_and_digits() {
+        assert_eq!(apply_case(".dotfiles", Case::Sentence), ".Dotfiles");
+        assert_eq!(apply_case("2fa-service", Case::Sentence), "2Fa-service");
+        assert_eq!(apply_case("_élan", Case::Sentence), "_Élan");
+        assert_eq!(apply_case("2024", Case::Sentence), "2024");
+        assert_eq!(apply_case("", Case::Sentence), "");
+    }
+
+    #[test]
+    fn test_apply_case_lower_upper_title() {
+        assert_eq!(apply_case("MyProject", Case::Lower), "myproject");
+        assert_eq!(apply_case("feat/login", Case::Upper), "FEAT/LOGIN");
+        assert_eq!(
+            apply_case("my_PROJECT-name", Case::Title),
+            "My_Project-Name"
+        );
+        assert_eq!(apply_case("2fa-service", Case::Title), "2fa-Service");
+    }
+
+    #[test]
+    fn test_apply_case_multibyte() {
+        assert_eq!(apply_case("ÉCOLE", Case::Lower), "école");
+        assert_eq!(apply_case("über-straße", Case::Upper), "ÜBER-STRASSE");
+        assert_eq!(apply_case("ÉLAN_ÖL", Case::Title), "Élan_Öl");
+        // Multibyte characters count once each when truncating afterwards
+        let config = FormatterConfig {
+            folder_case: Case::Upper,
+            branch_case: Case::Lower,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("café_über_ölfeld", Some("MAÑANA"), &config),
+            "CAFÉ_…FELD:mañan"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_folder_case() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_case: Case::Sentence,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", Some("main"), &config),
+            "My_pr…name:main"
+        );
+    }
+
+    // ==================== machine_format Tests ====================
+
+    fn machine_config() -> FormatterConfig {
+        FormatterConfig {
+            machine_format: "{folder}|{branch}|{dirty}".to_string(),
+            name_prefix: "[".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_machine_format_uses_raw_values() {
+        let config = machine_config();
+        let status = GitStatus {
+            unstaged: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "my_project_name",
+                Some("feature/implement-login"),
+                Some(&status),
+                None,
+                false,
+                true,
+                &config
+            ),
+            "my_project_name|feature/implement-login|1"
+        );
+        let clean = GitStatus::default();
+        assert_eq!(
+            format_tab_name_with_status(
+                "api",
+                Some("main"),
+                Some(&clean),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "api|main|0"
+        );
+    }
+
+    #[test]
+    fn test_machine_format_empty_fields() {
+        let config = machine_config();
+        assert_eq!(format_tab_name("scratch", None, &config), "scratch||");
+        assert_eq!(format_tab_name("api", Some("main"), &config), "api|main|");
+        assert_eq!(format_tab_name("", None, &config), "||");
+    }
+
+    #[test]
+    fn test_machine_format_requests_what_it_shows() {
+        let config = FormatterConfig {
+            show_branch: false,
+            ..machine_config()
+        };
+        assert!(config.needs_git());
+        assert!(config.needs_status());
+        assert!(!FormatterConfig::default().needs_status());
+    }
+
+    // ==================== render_template() Tests ====================
+
+    #[test]
+    fn test_render_template_all_placeholders() {
+        assert_eq!(
+            render_template("{folder}{sep}{branch}", "api", Some("main"), ":"),
+            "api:main"
+        );
+        assert_eq!(
+            render_template("[{folder}] {branch} {other}", "api", Some("main"), ":"),
+            "[api] main {other}"
+        );
+    }
+
+    #[test]
+    fn test_render_template_missing_branch_collapses() {
+        assert_eq!(
+            render_template("{folder}@{branch}", "api", None, ":"),
+            "api"
+        );
+        assert_eq!(
+            render_template("<{folder}{sep}{branch}>", "api", None, ":"),
+            "<api>"
+        );
+        // Leading placeholders take the text after them instead
+        assert_eq!(
+            render_template("{branch}/{folder}", "api", None, ":"),
+            "api"
+        );
+        assert_eq!(
+            render_template("{index}:{folder}@{branch}", "api", None, ":"),
+            "api"
+        );
+        // An ssh host that was not filled in renders empty
+        assert_eq!(render_template("{host}:{folder}", "app", None, ":"), "app");
+        assert_eq!(
+            render_template("{folder}@{branch}", "api", Some(""), ":"),
+            "api"
+        );
+    }
+
+    #[test]
+    fn test_template_for_tab() {
+        assert_eq!(
+            template_for_tab("{index}:{folder}@{branch}", 1),
+            "2:{folder}@{branch}"
+        );
+    }
+
+    #[test]
+    fn test_name_template_overrides_layout() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            name_template: "{folder}@{branch}".to_string(),
+            show_branch: false,
+            ..Default::default()
+        };
+        assert!(config.needs_git());
+        assert_eq!(
+            format_tab_name("my_project_name", Some("main"), &config),
+            "my_pr…name@main"
+        );
+        assert_eq!(format_tab_name("api", None, &config), "api");
+    }
+
+    // ==================== show_dirty Tests ====================
+
+    fn dirty_config() -> FormatterConfig {
+        FormatterConfig {
+            hide_default_branches: vec![],
+            show_dirty: true,
+            folder_max_len: 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dirty_marker_after_branch() {
+        let config = dirty_config();
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                None,
+                None,
+                false,
+                true,
+                &config
+            ),
+            "myproject:main*"
+        );
+        let custom = FormatterConfig {
+            dirty_marker: " ●".to_string(),
+            ..dirty_config()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                None,
+                None,
+                false,
+                true,
+                &custom
+            ),
+            "myproject:main ●"
+        );
+    }
+
+    #[test]
+    fn test_dirty_marker_absent_when_clean() {
+        let config = dirty_config();
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                None,
+                None,
+                false,
+                false,
+                &config
+            ),
+            "myproject:main"
+        );
+    }
+
+    #[test]
+    fn test_dirty_marker_suppressed_without_branch() {
+        let config = FormatterConfig {
+            show_branch: false,
+            ..dirty_config()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                None,
+                None,
+                false,
+                true,
+                &config
+            ),
+            "myproject"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                None,
+                None,
+                None,
+                false,
+                true,
+                &dirty_config()
+            ),
+            "myproject"
+        );
+        // Disabled by default
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                None,
+                None,
+                false,
+                true,
+                &FormatterConfig {
+                    hide_default_branches: vec![],
+                    ..Default::default()
+                }
+            ),
+            "myproject:main"
+        );
+    }
+
+    // ==================== name_prefix / name_suffix Tests ====================
+
+    fn wrapped_config() -> FormatterConfig {
+        FormatterConfig {
+            hide_default_branches: vec![],
+            name_prefix: "[".to_string(),
+            name_suffix: "]".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_name_prefix_suffix_wraps_name() {
+        let config = wrapped_config();
+        assert_eq!(
+            format_tab_name("my_project_name", Some("main"), &config),
+            "[my_pr…name:main]"
+        );
+        assert_eq!(format_tab_name("api", None, &config), "[api]");
+        let no_branch = FormatterConfig {
+            show_branch: false,
+            ..wrapped_config()
+        };
+        assert_eq!(format_tab_name("api", Some("main"), &no_branch), "[api]");
+    }
+
+    #[test]
+    fn test_name_prefix_suffix_empty_folder() {
+        let config = wrapped_config();
+        assert_eq!(format_tab_name("", Some("main"), &config), "[:main]");
+        // Nothing to wrap: the name stays empty rather than becoming `[]`
+        assert_eq!(format_tab_name("", None, &config), "");
+    }
+
+    // ==================== abbreviate_dict() Tests ====================
+
+    fn abbreviations() -> Vec<(String, String)> {
+        parse_pairs("authentication=auth,integration=intg")
+    }
+
+    #[test]
+    fn test_abbreviate_dict_replaces_known_words() {
+        assert_eq!(
+            abbreviate_dict(
+                "feature/authentication-integration",
+                &abbreviations(),
+                "-/_"
+            ),
+            "feature/auth-intg"
+        );
+        // Only whole words match
+        assert_eq!(
+            abbreviate_dict("fix/reauthentication", &abbreviations(), "-/_"),
+            "fix/reauthentication"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_branch_abbreviations() {
+        let config = FormatterConfig {
+            branch_abbreviations: abbreviations(),
+            branch_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("api", Some("feature/authentication-flow"), &config),
+            "api:feature/auth-flow"
+        );
+        // Branches without dictionary words, or short enough, are untouched
+        assert_eq!(
+            format_tab_name("api", Some("feature/login"), &config),
+            "api:feature/login"
+        );
+        assert_eq!(
+            format_tab_name("api", Some("integration"), &config),
+            "api:integration"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_branch_word_max() {
+        let config = FormatterConfig {
+            branch_word_max: 4,
+            branch_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name(
+                "api",
+                Some("feature/implement-authentication-flow"),
+                &config
+            ),
+            "api:feat/impl-auth-flow"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_branch_space_replacement() {
+        let config = FormatterConfig {
+            branch_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("api", Some("my bookmark"), &config),
+            "api:my-bookmark"
+        );
+
+        let config = FormatterConfig {
+            branch_space_replacement: " ".to_string(),
+            ..config
+        };
+        assert_eq!(
+            format_tab_name("api", Some("my bookmark"), &config),
+            "api:my bookmark"
+        );
+    }
+
+    #[test]
+    fn test_truncate_multi_char_ellipsis() {
+        assert_eq!(truncate("helloworld!", 10, 4, 4, false, ".."), "hell..rld!");
+        // No room for prefix + ellipsis + suffix: hard cut
+        assert_eq!(truncate("helloworld!", 10, 5, 4, false, ".."), "helloworld");
+        assert_eq!(
+            truncate_at_delimiter("my_long_project_name", 10, "_", false, ".."),
+            "my_long.."
+        );
+        assert_eq!(
+            truncate_edge("feature/JIRA-1234", 6, TruncateMode::Start, false, ".."),
+            "..1234"
+        );
+        assert_eq!(
+            truncate_keeping("legacy-api-service", 10, Some("api"), false, ".."),
+            "..-api-s.."
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_custom_ellipsis() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ellipsis: "~".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", Some("main"), &config),
+            "my_pr~name:main"
+        );
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ellipsis: "..".to_string(),
+            folder_prefix_len: 4,
+            ..Default::default()
+        };
+        let name = format_tab_name("my_project_name", None, &config);
+        assert_eq!(name, "my_p..name");
+        assert_eq!(name.chars().count(), config.folder_max_len);
+    }
+
+    // ==================== truncate_edge() Tests ====================
+
+    #[test]
+    fn test_truncate_edge_start_and_end() {
+        let branch = "feature/JIRA-1234";
+        assert_eq!(
+            truncate_edge(branch, 4, TruncateMode::Start, false, "…"),
+            "…234"
+        );
+        assert_eq!(
+            truncate_edge(branch, 5, TruncateMode::End, false, "…"),
+            "feat…"
+        );
+        assert_eq!(
+            truncate_edge("main", 4, TruncateMode::Start, false, "…"),
+            "main"
+        );
+        assert_eq!(truncate_edge("main", 0, TruncateMode::End, false, "…"), "");
+    }
+
+    #[test]
+    fn test_truncate_edge_hard_cut_vs_ellipsis() {
+        let folder = "my_long_project";
+        assert_eq!(
+            truncate_edge(folder, 10, TruncateMode::HardCut, false, "…"),
+            "my_long_pr"
+        );
+        assert_eq!(
+            truncate_edge(folder, 10, TruncateMode::End, false, "…"),
+            "my_long_p…"
+        );
+        // Cuts fall between characters, and between graphemes in grapheme mode
+        assert_eq!(
+            truncate_edge("café_über", 4, TruncateMode::HardCut, false, "…"),
+            "café"
+        );
+        assert_eq!(
+            truncate_edge("cafe\u{301}s", 4, TruncateMode::HardCut, true, "…"),
+            "cafe\u{301}"
+        );
+        assert_eq!(
+            truncate_edge("next", 4, TruncateMode::HardCut, false, "…"),
+            "next"
+        );
+
+        let config = FormatterConfig {
+            folder_truncate_mode: TruncateMode::HardCut,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", Some("next"), &config),
+            "my_project:next"
+        );
+        assert_eq!(TruncateMode::parse("hard_cut"), Some(TruncateMode::HardCut));
+    }
+
+    #[test]
+    fn test_format_tab_name_truncate_modes() {
+        let config = FormatterConfig {
+            folder_truncate_mode: TruncateMode::End,
+            branch_truncate_mode: TruncateMode::Start,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", Some("feature/JIRA-1234"), &config),
+            "my_projec…:…1234"
+        );
+
+        // Middle mode keeps the existing behavior
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_project_name", Some("main"), &config),
+            "my_pr…name:main"
+        );
+    }
+
+    // ==================== truncate_keeping() Tests ====================
+
+    #[test]
+    fn test_truncate_keeping_centers_on_keyword() {
+        assert_eq!(
+            truncate_keeping("legacy-api-service", 10, Some("api"), false, "…"),
+            "…y-api-se…"
+        );
+    }
+
+    #[test]
+    fn test_truncate_keeping_keyword_near_an_end() {
+        assert_eq!(
+            truncate_keeping("api-gateway-service", 10, Some("api"), false, "…"),
+            "api-gatew…"
+        );
+        assert_eq!(
+            truncate_keeping("legacy-service-api", 10, Some("api"), false, "…"),
+            "…rvice-api"
+        );
+    }
+
+    #[test]
+    fn test_truncate_keeping_falls_back_to_middle() {
+        assert_eq!(
+            truncate_keeping("legacy-web-service", 10, Some("api"), false, "…"),
+            "legac…vice"
+        );
+        assert_eq!(
+            truncate_keeping("legacy-api-service", 10, None, false, "…"),
+            "legac…vice"
+        );
+        assert_eq!(truncate_keeping("api", 10, Some("api"), false, "…"), "api");
+    }
+
+    #[test]
+    fn test_format_tab_name_folder_keep_keywords() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_keep_keywords: vec!["web".to_string(), "api".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("legacy-api-service", Some("main"), &config),
+            "…y-api-se…:main"
+        );
+        // No keyword present: the usual prefix/suffix truncation applies
+        assert_eq!(
+            format_tab_name("legacy-billing-service", None, &config),
+            "legac…vice"
+        );
+    }
+
+    // ==================== truncate_at_delimiter("…") Tests ====================
+
+    #[test]
+    fn test_truncate_at_delimiter_fits() {
+        assert_eq!(
+            truncate_at_delimiter("my_project", 10, "_-/.", false, "…"),
+            "my_project"
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_delimiter_cuts_at_nearest_boundary() {
+        let name = "my_long_project_name";
+        assert_eq!(
+            truncate_at_delimiter(name, 10, "_-/.", false, "…"),
+            "my_long…"
+        );
+        assert_eq!(
+            truncate_at_delimiter(name, 16, "_-/.", false, "…"),
+            "my_long_project…"
+        );
+        assert_eq!(
+            truncate_at_delimiter(name, 15, "_-/.", false, "…"),
+            "my_long…"
+        );
+        assert_eq!(truncate_at_delimiter(name, 5, "_-/.", false, "…"), "my…");
+    }
+
+    #[test]
+    fn test_truncate_at_delimiter_custom_set() {
+        assert_eq!(
+            truncate_at_delimiter("feature/add-login", 12, "/", false, "…"),
+            "feature…"
+        );
+        assert_eq!(
+            truncate_at_delimiter("feature/add-login", 12, "-", false, "…"),
+            "feature/add…"
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_delimiter_no_boundary_hard_cut() {
+        assert_eq!(
+            truncate_at_delimiter("myproject", 5, "_-/.", false, "…"),
+            "mypr…"
+        );
+        // A leading delimiter is never a cut point, so nothing is lost entirely
+        assert_eq!(
+            truncate_at_delimiter("_private", 4, "_", false, "…"),
+            "_pr…"
+        );
+        assert_eq!(
+            truncate_at_delimiter("my_long", 2, "_-/.", false, "…"),
+            "m…"
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_delimiter_zero_and_unicode() {
+        assert_eq!(truncate_at_delimiter("hello", 0, "_", false, "…"), "");
+        assert_eq!(
+            truncate_at_delimiter("日本_語テスト", 5, "_", false, "…"),
+            "日本…"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_smart_truncate() {
+        let config = FormatterConfig {
+            smart_truncate: true,
+            branch_max_len: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_long_project_name", Some("feature/login"), &config),
+            "my_long…:feature…"
+        );
+    }
+
+    // ==================== fit_width() Tests ====================
+
+    #[test]
+    fn test_fit_width_pads_short_segment() {
+        assert_eq!(fit_width("api", 6, "…", false), "api   ");
+        assert_eq!(fit_width("api", 3, "…", false), "api");
+        assert_eq!(fit_width("", 2, "…", false), "  ");
+    }
+
+    #[test]
+    fn test_fit_width_truncates_long_segment_exactly() {
+        let fitted = fit_width("my_project_name", 8, "…", false);
+        assert_eq!(fitted, "my_proj…");
+        assert_eq!(fitted.width(), 8);
+    }
+
+    #[test]
+    fn test_fit_width_counts_display_columns() {
+        // Each CJK character is two columns wide
+        assert_eq!(fit_width("日本", 6, "…", false), "日本  ");
+        let fitted = fit_width("日本語テスト", 6, "…", false);
+        assert_eq!(fitted, "日本… ");
+        assert_eq!(fitted.width(), 6);
+    }
+
+    #[test]
+    fn test_fit_width_ellipsis_wider_than_width() {
+        assert_eq!(fit_width("project", 2, "...", false), "pr");
+        assert_eq!(fit_width("project", 0, "…", false), "");
+    }
+
+    #[test]
+    fn test_format_tab_name_fixed_widths() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_fixed_width: 6,
+            branch_fixed_width: 4,
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("api", Some("main"), &config), "api   :main");
+        assert_eq!(
+            format_tab_name("my_project", Some("dev"), &config),
+            "my_pr…:dev "
+        );
+        assert_eq!(format_tab_name("api", None, &config), "api   ");
+    }
+
+    // ==================== collapse_separators() Tests ====================
+
+    #[test]
+    fn test_collapse_separators_doubled() {
+        assert_eq!(collapse_separators("proj::main", ":"), "proj:main");
+        assert_eq!(collapse_separators("proj:::main", ":"), "proj:main");
+    }
+
+    #[test]
+    fn test_collapse_separators_doubled_spaces() {
+        assert_eq!(collapse_separators("proj  main", ":"), "proj main");
+        assert_eq!(collapse_separators("a   b  c", ":"), "a b c");
+    }
+
+    #[test]
+    fn test_collapse_separators_multi_char_separator() {
+        assert_eq!(collapse_separators("proj |  | main", " | "), "proj | main");
+    }
+
+    #[test]
+    fn test_collapse_separators_empty_separator() {
+        assert_eq!(collapse_separators("proj  main", ""), "proj main");
+    }
+
+    #[test]
+    fn test_collapse_separators_unchanged() {
+        assert_eq!(collapse_separators("proj:main", ":"), "proj:main");
+    }
+
+    // ==================== format_tab_name() Tests ====================
+
+    #[test]
+    fn test_format_tab_name_with_branch() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("myproject", Some("main"), &config),
+            "myproject:main"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_no_branch() {
+        let config = FormatterConfig::default();
+        assert_eq!(format_tab_name("myproject", None, &config), "myproject");
+    }
+
+    #[test]
+    fn test_format_tab_name_truncated_folder() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("my_long_project_name", Some("main"), &config),
+            "my_lo…name:main"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_truncated_branch() {
+        let config = FormatterConfig::default();
+        // branch "feature" is 7 chars, max is 5, prefix 1 + ellipsis + suffix 4 = 6 > 5
+        // so it just takes first 5 chars
+        assert_eq!(
+            format_tab_name("src", Some("feature"), &config),
+            "src:featu"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_both_truncated() {
+        let config = FormatterConfig::default();
+        let result = format_tab_name("my_long_project_name", Some("feature-branch-name"), &config);
+        assert_eq!(result, "my_lo…name:featu");
+    }
+
+    #[test]
+    #[allow(clippy::field_reassign_with_default)]
+    fn test_format_tab_name_branch_disabled() {
+        let mut config = FormatterConfig::default();
+        config.show_branch = false;
+        assert_eq!(
+            format_tab_name("myproject", Some("main"), &config),
+            "myproject"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_custom_separator() {
+        let mut config = FormatterConfig::default();
+        config.hide_default_branches.clear();
+        config.separator = " @ ".to_string();
+        assert_eq!(
+            format_tab_name("myproject", Some("main"), &config),
+            "myproject @ main"
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_empty_folder() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("", Some("main"), &config), ":main");
+    }
+
+    #[test]
+    fn test_format_tab_name_empty_branch() {
+        let config = FormatterConfig::default();
+        // Empty string branch is still Some, so it shows separator
+        assert_eq!(format_tab_name("src", Some(""), &config), "src:");
+    }
+
+    #[test]
+    fn test_empty_folder_fallback_parse() {
+        assert_eq!(
+            EmptyFolderFallback::parse("original_basename"),
+            EmptyFolderFallback::OriginalBasename
+        );
+        assert_eq!(
+            EmptyFolderFallback::parse("root"),
+            EmptyFolderFallback::Literal("root".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_tab_name_empty_folder_uses_original_basename() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            folder_max_len: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("myproject", Some("main"), &config),
+            "myproject:main"
+        );
+        assert_eq!(format_busy_name("myproject", &config), "myproject⋯");
+    }
+
+    #[test]
+    fn test_format_tab_name_empty_override_label_uses_literal() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            branch_folder_override: vec![("main".to_string(), String::new())],
+            empty_folder_fallback: EmptyFolderFallback::Literal("·".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("proj", Some("main"), &config), "·:main");
+        // Folders that are not emptied are left alone
+        assert_eq!(format_tab_name("proj", Some("dev"), &config), "proj:dev");
+    }
+
+    #[test]
+    fn test_format_tab_name_truncated_branch_suffix() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            truncated_branch_suffix: "»".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("proj", Some("feature-branch"), &config),
+            "proj:featu»"
+        );
+        assert_eq!(format_tab_name("proj", Some("main"), &config), "proj:main");
+    }
+
+    #[test]
+    fn test_truncated_branch_suffix_with_branch_case() {
+        let config = FormatterConfig {
+            branch_case: Case::Upper,
+            truncated_branch_suffix: "+".to_string(),
+            ..Default::default()
+        };
+        // Changing the case is not a truncation
+        assert_eq!(format_tab_name("api", Some("dev"), &config), "api:DEV");
+        assert_eq!(
+            format_tab_name("api", Some("feature-branch"), &config),
+            "api:FEATU+"
+        );
+    }
+
+    // ==================== collapse_branch_folder_overlap Tests ====================
+
+    fn overlap_config() -> FormatterConfig {
+        FormatterConfig {
+            hide_default_branches: vec![],
+            collapse_branch_folder_overlap: true,
+            branch_max_len: 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_overlap_trailing_segment_collapsed() {
+        let config = overlap_config();
+        assert_eq!(
+            format_tab_name("auth", Some("pkg/auth"), &config),
+            "auth:pkg"
+        );
+        assert_eq!(
+            format_tab_name("auth", Some("team/pkg/auth"), &config),
+            "auth:team/pkg"
+        );
+    }
+
+    #[test]
+    fn test_overlap_whole_branch_shows_folder_only() {
+        let config = overlap_config();
+        assert_eq!(format_tab_name("auth", Some("auth"), &config), "auth");
+        let status = GitStatus {
+            staged: 1,
+            unstaged: 0,
+            ..Default::default()
+        };
+        let counts = FormatterConfig {
+            show_change_counts: true,
+            ..config
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "auth",
+                Some("auth"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &counts
+            ),
+            "auth +1"
+        );
+    }
+
+    #[test]
+    fn test_overlap_non_overlapping_unchanged() {
+        let config = overlap_config();
+        assert_eq!(
+            format_tab_name("auth", Some("pkg/oauth"), &config),
+            "auth:pkg/oauth"
+        );
+        assert_eq!(
+            format_tab_name("auth", Some("auth/login"), &config),
+            "auth:auth/login"
+        );
+        assert_eq!(
+            format_tab_name("auth", Some("/auth"), &config),
+            "auth:/auth"
+        );
+        assert_eq!(format_tab_name("", Some("main"), &config), ":main");
+    }
+
+    #[test]
+    fn test_overlap_disabled_by_default() {
+        let config = FormatterConfig {
+            branch_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("auth", Some("pkg/auth"), &config),
+            "auth:pkg/auth"
+        );
+    }
+
+    // ==================== hide_default_branches Tests ====================
+
+    #[test]
+    fn test_hide_default_branches_shows_folder_only() {
+        let config = FormatterConfig {
+            branch_max_len: 20,
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("api", Some("main"), &config), "api");
+        assert_eq!(format_tab_name("api", Some("master"), &config), "api");
+        // Exact matches only, so feature branches still show
+        assert_eq!(
+            format_tab_name("api", Some("main-fix"), &config),
+            "api:main-fix"
+        );
+        assert_eq!(
+            format_tab_name("api", Some("feature/main"), &config),
+            "api:feature/main"
+        );
+    }
+
+    #[test]
+    fn test_hide_default_branches_keeps_counts() {
+        let config = FormatterConfig {
+            show_change_counts: true,
+            ..Default::default()
+        };
+        let status = GitStatus {
+            staged: 1,
+            unstaged: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "api",
+                Some("main"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "api +1"
+        );
+    }
+
+    #[test]
+    fn test_hide_default_branches_keeps_dirty_marker() {
+        let config = FormatterConfig {
+            show_dirty: true,
+            ..Default::default()
+        };
+        let dirty = |branch| {
+            format_tab_name_with_status("api", Some(branch), None, None, false, true, &config)
+        };
+        assert_eq!(dirty("main"), "api*");
+        assert_eq!(dirty("dev"), "api:dev*");
+    }
+
+    #[test]
+    fn test_hide_default_branches_empty_shows_all() {
+        let config = FormatterConfig {
+            hide_default_branches: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("api", Some("main"), &config), "api:main");
+
+        let map = BTreeMap::from([("hide_default_branches".to_string(), String::new())]);
+        assert!(FormatterConfig::from_config(&map)
+            .hide_default_branches
+            .is_empty());
+    }
+
+    // ==================== strip_folder_prefix_from_branch Tests ====================
+
+    fn prefix_config() -> FormatterConfig {
+        FormatterConfig {
+            strip_folder_prefix_from_branch: true,
+            branch_max_len: 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_strip_folder_prefix_matching() {
+        let config = prefix_config();
+        assert_eq!(
+            format_tab_name("proj", Some("proj-feature"), &config),
+            "proj:feature"
+        );
+        assert_eq!(
+            format_tab_name("proj", Some("proj/fix/login"), &config),
+            "proj:fix/login"
+        );
+    }
+
+    #[test]
+    fn test_strip_folder_prefix_non_matching() {
+        let config = prefix_config();
+        assert_eq!(
+            format_tab_name("proj", Some("project-x"), &config),
+            "proj:project-x"
+        );
+        assert_eq!(
+            format_tab_name("proj", Some("feature/proj-x"), &config),
+            "proj:feature/proj-x"
+        );
+        assert_eq!(
+            format_tab_name("proj", Some("proj-"), &config),
+            "proj:proj-"
+        );
+        let disabled = FormatterConfig {
+            strip_folder_prefix_from_branch: false,
+            ..config
+        };
+        assert_eq!(
+            format_tab_name("proj", Some("proj-feature"), &disabled),
+            "proj:proj-feature"
+        );
+    }
+
+    // ==================== format_pr_ref() Tests ====================
+
+    #[test]
+    fn test_format_pr_ref_pull_merge() {
+        assert_eq!(
+            format_pr_ref("pull/42/merge", "PR#{n}"),
+            Some("PR#42".to_string())
+        );
+        assert_eq!(
+            format_pr_ref("refs/pull/42/head", "PR#{n}"),
+            Some("PR#42".to_string())
+        );
+        assert_eq!(
+            format_pr_ref("merge-requests/7/head", "!{n}"),
+            Some("!7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_pr_ref_normal_branches_unaffected() {
+        assert_eq!(format_pr_ref("main", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("feature/login", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("pull/abc/merge", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("pull//merge", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("pull/42/merge/x", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("pull/42/other", "PR#{n}"), None);
+        assert_eq!(format_pr_ref("pull", "PR#{n}"), None);
+    }
 
-        let map = BTreeMap::from([("show_branch".to_string(), "false".to_string())]);
-        assert!(!FormatterConfig::from_config(&map).show_branch);
+    #[test]
+    fn test_format_pr_ref_disabled() {
+        assert_eq!(format_pr_ref("pull/42/merge", ""), None);
     }
 
-    // ==================== truncate() Tests ====================
+    #[test]
+    fn test_format_tab_name_pr_ref() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("api", Some("pull/42/merge"), &config),
+            "api:PR#42"
+        );
+        assert_eq!(format_tab_name("api", Some("main"), &config), "api:main");
+    }
+
+    // ==================== format_tab_name_with_status() Tests ====================
+
+    fn counts_config() -> FormatterConfig {
+        FormatterConfig {
+            hide_default_branches: vec![],
+            show_change_counts: true,
+            ..Default::default()
+        }
+    }
 
     #[test]
-    fn test_truncate_empty_string() {
-        assert_eq!(truncate("", 10, 5, 4), "");
+    fn test_format_tab_name_with_status_staged_and_unstaged() {
+        let status = GitStatus {
+            staged: 2,
+            unstaged: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "myproject",
+                Some("main"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &counts_config()
+            ),
+            "myproject:main +2~3"
+        );
     }
 
     #[test]
-    fn test_truncate_short() {
-        assert_eq!(truncate("hello", 10, 5, 4), "hello");
+    fn test_format_tab_name_with_status_omits_zero_counts() {
+        let config = counts_config();
+        let staged = GitStatus {
+            staged: 1,
+            unstaged: 0,
+            ..Default::default()
+        };
+        let unstaged = GitStatus {
+            staged: 0,
+            unstaged: 4,
+            ..Default::default()
+        };
+        let clean = GitStatus::default();
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&staged),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main +1"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&unstaged),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ~4"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&clean),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main"
+        );
     }
 
     #[test]
-    fn test_truncate_exact_length() {
-        assert_eq!(truncate("helloworld", 10, 5, 4), "helloworld");
+    fn test_format_tab_name_with_status_custom_symbols() {
+        let config = FormatterConfig {
+            staged_symbol: "S".to_string(),
+            unstaged_symbol: "U".to_string(),
+            ..counts_config()
+        };
+        let status = GitStatus {
+            staged: 2,
+            unstaged: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main S2U3"
+        );
     }
 
     #[test]
-    fn test_truncate_one_over() {
-        assert_eq!(truncate("helloworld!", 10, 5, 4), "hello…rld!");
+    fn test_format_tab_name_with_status_disabled_or_no_branch() {
+        let status = GitStatus {
+            staged: 2,
+            unstaged: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &FormatterConfig {
+                    hide_default_branches: vec![],
+                    ..Default::default()
+                }
+            ),
+            "proj:main"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                None,
+                Some(&status),
+                None,
+                false,
+                false,
+                &counts_config()
+            ),
+            "proj"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                None,
+                None,
+                false,
+                false,
+                &counts_config()
+            ),
+            "proj:main"
+        );
     }
 
     #[test]
-    fn test_truncate_long_folder() {
-        assert_eq!(truncate("my_project_name", 10, 5, 4), "my_pr…name");
+    fn test_format_tab_name_with_status_clean_dirty_and_non_repo() {
+        let config = FormatterConfig {
+            show_clean: true,
+            ..counts_config()
+        };
+        let clean = GitStatus::default();
+        let dirty = GitStatus {
+            staged: 0,
+            unstaged: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&clean),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ✓"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&dirty),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ~2"
+        );
+        // Outside a repo there is no branch and no status
+        assert_eq!(
+            format_tab_name_with_status("proj", None, None, None, false, false, &config),
+            "proj"
+        );
     }
 
     #[test]
-    fn test_truncate_prefix_suffix_exceeds_length() {
-        // prefix(5) + suffix(4) = 9 >= char_count(8), so just take first max_len chars
-        assert_eq!(truncate("abcdefgh", 10, 5, 4), "abcdefgh");
+    fn test_format_tab_name_with_status_clean_without_counts() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            show_clean: true,
+            clean_symbol: "ok".to_string(),
+            ..Default::default()
+        };
+        let dirty = GitStatus {
+            staged: 1,
+            unstaged: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&GitStatus::default()),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ok"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&dirty),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main"
+        );
     }
 
+    // ==================== compact_status_badge() Tests ====================
+
     #[test]
-    fn test_truncate_needed_exceeds_max() {
-        // needed = 1 + 1 + 4 = 6 > max_len(5), so just take first 5 chars
-        assert_eq!(truncate("feature-branch", 5, 1, 4), "featu");
+    fn test_compact_status_badge_combinations() {
+        let status = |ahead, behind, unstaged| GitStatus {
+            ahead,
+            behind,
+            unstaged,
+            ..Default::default()
+        };
+        assert_eq!(compact_status_badge(&status(0, 0, 0)), "");
+        assert_eq!(compact_status_badge(&status(2, 0, 1)), "⇡*");
+        assert_eq!(compact_status_badge(&status(0, 3, 0)), "⇣");
+        assert_eq!(compact_status_badge(&status(1, 1, 0)), "⇡⇣");
+        assert_eq!(compact_status_badge(&status(1, 1, 4)), "⇡⇣*");
+        assert_eq!(compact_status_badge(&status(0, 0, 2)), "*");
     }
 
     #[test]
-    fn test_truncate_unicode() {
-        // Unicode characters should be handled correctly
-        assert_eq!(truncate("héllo", 10, 5, 4), "héllo");
-        assert_eq!(truncate("日本語テスト文字列", 6, 2, 2), "日本…字列");
+    fn test_compact_status_badge_counts_staged_as_dirty() {
+        let status = GitStatus {
+            staged: 1,
+            ..Default::default()
+        };
+        assert_eq!(compact_status_badge(&status), "*");
     }
 
     #[test]
-    fn test_truncate_zero_max_len() {
-        assert_eq!(truncate("hello", 0, 0, 0), "");
+    fn test_format_tab_name_with_status_compact() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            compact_status: true,
+            show_change_counts: true,
+            show_clean: true,
+            ..Default::default()
+        };
+        let ahead_dirty = GitStatus {
+            unstaged: 2,
+            ahead: 1,
+            ..Default::default()
+        };
+        let name = |status: &GitStatus| {
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(status),
+                None,
+                false,
+                false,
+                &config,
+            )
+        };
+        assert_eq!(name(&ahead_dirty), "proj:main ⇡*");
+        assert_eq!(name(&GitStatus::default()), "proj:main");
     }
 
     #[test]
-    fn test_truncate_single_char_result() {
-        assert_eq!(truncate("hello", 1, 0, 0), "h");
+    fn test_format_tab_name_with_status_clean_ignores_ahead() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            show_clean: true,
+            ..Default::default()
+        };
+        let ahead = GitStatus {
+            ahead: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&ahead),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ✓"
+        );
     }
 
-    // ==================== format_tab_name() Tests ====================
+    // ==================== format_ahead_behind() Tests ====================
+
+    fn ahead_behind(ahead: usize, behind: usize) -> GitStatus {
+        GitStatus {
+            ahead,
+            behind,
+            ..Default::default()
+        }
+    }
 
     #[test]
-    fn test_format_tab_name_with_branch() {
-        let config = FormatterConfig::default();
+    fn test_format_ahead_behind_compact() {
+        assert_eq!(format_ahead_behind(&ahead_behind(2, 0), true), "↑2");
+        assert_eq!(format_ahead_behind(&ahead_behind(0, 1), true), "↓1");
+        assert_eq!(format_ahead_behind(&ahead_behind(2, 1), true), "↑2↓1");
+        assert_eq!(format_ahead_behind(&ahead_behind(0, 0), true), "");
+    }
+
+    #[test]
+    fn test_format_ahead_behind_always_show() {
+        assert_eq!(format_ahead_behind(&ahead_behind(2, 0), false), "↑2↓0");
+        assert_eq!(format_ahead_behind(&ahead_behind(0, 1), false), "↑0↓1");
+        assert_eq!(format_ahead_behind(&ahead_behind(12, 3), false), "↑12↓3");
+        assert_eq!(format_ahead_behind(&ahead_behind(0, 0), false), "↑0↓0");
+    }
+
+    #[test]
+    fn test_format_tab_name_with_status_ahead_behind() {
+        let config = FormatterConfig {
+            show_ahead_behind: true,
+            ..counts_config()
+        };
+        let status = GitStatus {
+            unstaged: 3,
+            ahead: 2,
+            ..Default::default()
+        };
         assert_eq!(
-            format_tab_name("myproject", Some("main"), &config),
-            "myproject:main"
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&status),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main ~3 ↑2"
+        );
+        let clean = GitStatus {
+            ahead: 2,
+            behind: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&clean),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main↑2↓1"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                Some(&GitStatus::default()),
+                None,
+                false,
+                false,
+                &config
+            ),
+            "proj:main"
         );
     }
 
     #[test]
-    fn test_format_tab_name_no_branch() {
-        let config = FormatterConfig::default();
-        assert_eq!(format_tab_name("myproject", None, &config), "myproject");
+    fn test_format_tab_name_with_status_stale_branch() {
+        let config = FormatterConfig {
+            stale_days: 14,
+            show_dirty: true,
+            ..Default::default()
+        };
+        let old = GitStatus {
+            commit_age_days: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status("api", Some("dev"), Some(&old), None, false, true, &config),
+            "api:\x1b[2;31mdev\x1b[0m*"
+        );
+        let recent = GitStatus {
+            commit_age_days: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name_with_status(
+                "api",
+                Some("dev"),
+                Some(&recent),
+                None,
+                false,
+                true,
+                &config
+            ),
+            "api:dev*"
+        );
     }
 
     #[test]
-    fn test_format_tab_name_truncated_folder() {
-        let config = FormatterConfig::default();
+    fn test_is_stale() {
+        let config = FormatterConfig {
+            stale_days: 14,
+            ..Default::default()
+        };
+        let aged = |days| GitStatus {
+            commit_age_days: Some(days),
+            ..Default::default()
+        };
+        assert!(is_stale(Some(&aged(14)), &config));
+        assert!(!is_stale(Some(&aged(13)), &config));
+        // An unknown age or a disabled threshold never marks a branch stale
+        assert!(!is_stale(Some(&GitStatus::default()), &config));
+        assert!(!is_stale(None, &config));
+        assert!(!is_stale(Some(&aged(400)), &FormatterConfig::default()));
+    }
+
+    #[test]
+    fn test_with_tab_prefix_ignores_stale_style_width() {
+        let config = FormatterConfig {
+            total_max_len: 7,
+            ..Default::default()
+        };
+        let stale = "api:\x1b[2;31mdev\x1b[0m";
+        assert_eq!(with_tab_prefix(stale, &config), stale);
+        // A cut drops the style instead of splitting it
         assert_eq!(
-            format_tab_name("my_long_project_name", Some("main"), &config),
-            "my_lo…name:main"
+            with_tab_prefix("api:\x1b[2;31mfeature\x1b[0m", &config),
+            "api:fe…"
         );
     }
 
     #[test]
-    fn test_format_tab_name_truncated_branch() {
-        let config = FormatterConfig::default();
-        // branch "feature" is 7 chars, max is 5, prefix 1 + ellipsis + suffix 4 = 6 > 5
-        // so it just takes first 5 chars
+    fn test_format_tab_name_with_status_nonprimary_remote() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            show_nonprimary_remote: true,
+            ..Default::default()
+        };
         assert_eq!(
-            format_tab_name("src", Some("feature"), &config),
-            "src:featu"
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                None,
+                Some("upstream"),
+                false,
+                false,
+                &config
+            ),
+            "proj:main@upstream"
+        );
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                None,
+                Some("origin"),
+                false,
+                false,
+                &config
+            ),
+            "proj:main"
+        );
+        assert_eq!(
+            format_tab_name_with_status("proj", Some("main"), None, None, false, false, &config),
+            "proj:main"
+        );
+        // Off by default even when the remote is known
+        assert_eq!(
+            format_tab_name_with_status(
+                "proj",
+                Some("main"),
+                None,
+                Some("upstream"),
+                false,
+                false,
+                &FormatterConfig {
+                    hide_default_branches: vec![],
+                    ..Default::default()
+                }
+            ),
+            "proj:main"
         );
     }
 
     #[test]
-    fn test_format_tab_name_both_truncated() {
+    fn test_format_tab_name_with_status_freshness_marker() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            show_dir_freshness: true,
+            ..Default::default()
+        };
+        let fresh = |branch, fresh| {
+            format_tab_name_with_status("scratch", branch, None, None, fresh, false, &config)
+        };
+        assert_eq!(fresh(None, true), "scratch~");
+        assert_eq!(fresh(Some("main"), true), "scratch~:main");
+        assert_eq!(fresh(None, false), "scratch");
+        assert_eq!(
+            format_tab_name_with_status(
+                "scratch",
+                None,
+                None,
+                None,
+                true,
+                false,
+                &FormatterConfig {
+                    hide_default_branches: vec![],
+                    ..Default::default()
+                }
+            ),
+            "scratch"
+        );
+    }
+
+    // ==================== format_busy_name() Tests ====================
+
+    #[test]
+    fn test_format_busy_name() {
+        let config = FormatterConfig {
+            show_busy: true,
+            ..Default::default()
+        };
+        assert_eq!(format_busy_name("myproject", &config), "myproject⋯");
+    }
+
+    #[test]
+    fn test_format_busy_name_truncates_folder() {
         let config = FormatterConfig::default();
-        let result = format_tab_name("my_long_project_name", Some("feature-branch-name"), &config);
-        assert_eq!(result, "my_lo…name:featu");
+        assert_eq!(
+            format_busy_name("my_long_project_name", &config),
+            "my_lo…name⋯"
+        );
     }
 
     #[test]
-    fn test_format_tab_name_branch_disabled() {
-        let mut config = FormatterConfig::default();
-        config.show_branch = false;
+    fn test_busy_then_final_name() {
+        // Interim name carries the symbol; the final name replaces it entirely
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            show_busy: true,
+            busy_symbol: "?".to_string(),
+            ..Default::default()
+        };
+        let interim = format_busy_name("myproject", &config);
+        let last = format_tab_name("myproject", Some("main"), &config);
+        assert_eq!(interim, "myproject?");
+        assert_eq!(last, "myproject:main");
+        assert!(!last.contains(&config.busy_symbol));
+    }
+
+    #[test]
+    fn test_from_config_show_busy_requires_true() {
+        let map = BTreeMap::from([("show_busy".to_string(), "yes".to_string())]);
+        assert!(!FormatterConfig::from_config(&map).show_busy);
+    }
+
+    #[test]
+    fn test_format_tab_name_collapse_separators() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            collapse_separators: true,
+            ..Default::default()
+        };
+        assert_eq!(format_tab_name("proj:", Some("main"), &config), "proj:main");
+        assert_eq!(format_tab_name("my  app", None, &config), "my app");
+    }
+
+    #[test]
+    fn test_format_tab_name_collapse_separators_disabled() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            ..Default::default()
+        };
         assert_eq!(
-            format_tab_name("myproject", Some("main"), &config),
-            "myproject"
+            format_tab_name("proj:", Some("main"), &config),
+            "proj::main"
         );
     }
 
     #[test]
-    fn test_format_tab_name_custom_separator() {
-        let mut config = FormatterConfig::default();
-        config.separator = " @ ".to_string();
+    fn test_format_tab_name_branch_folder_override_matching() {
+        let config = FormatterConfig {
+            branch_folder_override: parse_pairs("gh-pages=docs"),
+            ..Default::default()
+        };
         assert_eq!(
-            format_tab_name("myproject", Some("main"), &config),
-            "myproject @ main"
+            format_tab_name("my_website", Some("gh-pages"), &config),
+            "docs:gh-pa"
         );
     }
 
     #[test]
-    fn test_format_tab_name_empty_folder() {
+    fn test_format_tab_name_branch_folder_override_non_matching() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            branch_folder_override: parse_pairs("gh-pages=docs"),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tab_name("website", Some("main"), &config),
+            "website:main"
+        );
+        assert_eq!(format_tab_name("website", None, &config), "website");
+    }
+
+    // ==================== group_label() Tests ====================
+
+    #[test]
+    fn test_group_label_size_four() {
+        assert_eq!(group_label(0, 4), "G1");
+        assert_eq!(group_label(3, 4), "G1");
+        assert_eq!(group_label(4, 4), "G2");
+        assert_eq!(group_label(7, 4), "G2");
+        assert_eq!(group_label(8, 4), "G3");
+    }
+
+    #[test]
+    fn test_group_label_other_sizes() {
+        assert_eq!(group_label(0, 1), "G1");
+        assert_eq!(group_label(5, 1), "G6");
+        assert_eq!(group_label(5, 3), "G2");
+        assert_eq!(group_label(2, 0), "G3"); // zero treated as one
+    }
+
+    #[test]
+    fn test_with_group_label() {
+        let config = FormatterConfig {
+            group_size: 4,
+            ..Default::default()
+        };
+        assert_eq!(with_group_label("proj:main", 5, &config), "G2:proj:main");
+    }
+
+    #[test]
+    fn test_with_group_label_disabled() {
         let config = FormatterConfig::default();
-        assert_eq!(format_tab_name("", Some("main"), &config), ":main");
+        assert_eq!(with_group_label("proj:main", 5, &config), "proj:main");
     }
 
     #[test]
-    fn test_format_tab_name_empty_branch() {
+    fn test_shell_icon_known_shells() {
+        let icons = parse_pairs("fish=F,zsh=Z,nu=N");
+        assert_eq!(shell_icon(Some("fish"), &icons), Some("F"));
+        assert_eq!(shell_icon(Some("/usr/bin/zsh -l"), &icons), Some("Z"));
+        assert_eq!(shell_icon(Some("-zsh"), &icons), Some("Z"));
+        assert_eq!(shell_icon(Some("nu"), &icons), Some("N"));
+    }
+
+    #[test]
+    fn test_shell_icon_unknown_command() {
+        let icons = parse_pairs("fish=F");
+        assert_eq!(shell_icon(Some("htop"), &icons), None);
+        assert_eq!(shell_icon(Some("  "), &icons), None);
+        assert_eq!(shell_icon(None, &icons), None);
+    }
+
+    #[test]
+    fn test_with_shell_icon() {
+        let config = FormatterConfig {
+            show_shell_icon: true,
+            ..Default::default()
+        };
+        assert_eq!(with_shell_icon("api", Some("F"), &config), "F api");
+        assert_eq!(with_shell_icon("api", None, &config), "api");
+    }
+
+    #[test]
+    fn test_with_layout() {
+        let config = FormatterConfig {
+            show_layout: true,
+            ..Default::default()
+        };
+        assert_eq!(with_layout("api", PaneLayout::Split, &config), "api ⊞");
+        assert_eq!(with_layout("api", PaneLayout::Floating, &config), "api ⧉");
+        assert_eq!(with_layout("api", PaneLayout::Single, &config), "api");
+        let off = FormatterConfig::default();
+        assert_eq!(with_layout("api", PaneLayout::Stacked, &off), "api");
+    }
+
+    #[test]
+    fn test_with_project_icon() {
+        let config = FormatterConfig {
+            show_icon: true,
+            ..Default::default()
+        };
+        assert_eq!(with_project_icon("api", Some("R"), &config), "R api");
+        assert_eq!(with_project_icon("api", None, &config), "api");
+        let off = FormatterConfig::default();
+        assert_eq!(with_project_icon("api", Some("R"), &off), "api");
+    }
+
+    #[test]
+    fn test_with_base_ahead() {
+        assert_eq!(with_base_ahead("api:feature", Some(7)), "api:feature ↑7");
+        assert_eq!(with_base_ahead("api:main", Some(0)), "api:main");
+        assert_eq!(with_base_ahead("api:main", None), "api:main");
+    }
+
+    #[test]
+    fn test_with_family_label() {
         let config = FormatterConfig::default();
-        // Empty string branch is still Some, so it shows separator
-        assert_eq!(format_tab_name("src", Some(""), &config), "src:");
+        assert_eq!(
+            with_family_label("api:main", Some("acme"), &config),
+            "acme:api:main"
+        );
+        assert_eq!(with_family_label("api:main", None, &config), "api:main");
+    }
+
+    // ==================== session_name() Tests ====================
+
+    #[test]
+    fn test_session_name_sanitizes_folder() {
+        assert_eq!(session_name("api"), Some("api".to_string()));
+        assert_eq!(session_name("My API"), Some("My-API".to_string()));
+        assert_eq!(session_name("a / b"), Some("a-b".to_string()));
+        assert_eq!(session_name("web_app.v2"), Some("web_app.v2".to_string()));
+        assert_eq!(session_name("--x--"), Some("x".to_string()));
+    }
+
+    #[test]
+    fn test_session_name_empty() {
+        assert_eq!(session_name("~"), None);
+        assert_eq!(session_name(""), None);
+        assert_eq!(session_name("/"), None);
+    }
+
+    // ==================== Kube context Tests ====================
+
+    #[test]
+    fn test_format_kube_context() {
+        assert_eq!(
+            format_kube_context("arn:aws:eks:us-east-1:123456789012:cluster/prod"),
+            "prod"
+        );
+        assert_eq!(
+            format_kube_context("gke_acme_europe-west1_staging"),
+            "staging"
+        );
+        assert_eq!(format_kube_context("kind-dev"), "kind-dev");
+        assert_eq!(format_kube_context("gke_partial"), "gke_partial");
+    }
+
+    #[test]
+    fn test_with_kube_context() {
+        let config = FormatterConfig {
+            show_kube_context: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            with_kube_context("infra:main", Some("kind-dev"), &config),
+            "⎈kind-dev infra:main"
+        );
+        assert_eq!(with_kube_context("infra:main", None, &config), "infra:main");
+        assert_eq!(
+            with_kube_context("infra", Some("kind-dev"), &FormatterConfig::default()),
+            "infra"
+        );
+    }
+
+    // ==================== select_badges() Tests ====================
+
+    #[test]
+    fn test_select_badges_without_cap_keeps_all() {
+        let present = [Badge::Layout, Badge::Kube, Badge::Dirty];
+        assert_eq!(
+            select_badges(&present, &FormatterConfig::default()),
+            present
+        );
+    }
+
+    #[test]
+    fn test_select_badges_drops_lowest_priority_at_cap() {
+        let config = FormatterConfig {
+            max_badges: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            select_badges(&[Badge::Layout, Badge::Kube, Badge::Dirty], &config),
+            vec![Badge::Dirty, Badge::Kube]
+        );
+        // Within the cap nothing is dropped or reordered
+        assert_eq!(
+            select_badges(&[Badge::Layout, Badge::Kube], &config),
+            vec![Badge::Layout, Badge::Kube]
+        );
+    }
+
+    #[test]
+    fn test_select_badges_custom_priority() {
+        let config = FormatterConfig {
+            max_badges: 1,
+            badge_priority: vec![Badge::Layout, Badge::Kube],
+            ..Default::default()
+        };
+        assert_eq!(
+            select_badges(&[Badge::Dirty, Badge::Kube, Badge::Layout], &config),
+            vec![Badge::Layout]
+        );
+        // Unlisted badges rank below the listed ones
+        let config = FormatterConfig {
+            max_badges: 2,
+            ..config
+        };
+        assert_eq!(
+            select_badges(&[Badge::Dirty, Badge::Forge, Badge::Kube], &config),
+            vec![Badge::Kube, Badge::Dirty]
+        );
+    }
+
+    #[test]
+    fn test_badge_parse_list() {
+        assert_eq!(
+            Badge::parse_list("kube, base_ahead,bogus,status"),
+            vec![Badge::Kube, Badge::BaseAhead, Badge::Status]
+        );
+        let map = BTreeMap::from([("badge_priority".to_string(), "bogus".to_string())]);
+        assert_eq!(
+            FormatterConfig::from_config(&map).badge_priority,
+            Badge::ALL
+        );
+    }
+
+    // ==================== with_attention() Tests ====================
+
+    #[test]
+    fn test_with_attention() {
+        let config = FormatterConfig {
+            attention_symbol: " !".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(with_attention("api:next", true, &config), "api:next !");
+        assert_eq!(with_attention("api:next", false, &config), "api:next");
+        assert_eq!(with_attention("", true, &config), "");
+    }
+
+    // ==================== with_tab_prefix() Tests ====================
+
+    #[test]
+    fn test_with_tab_prefix() {
+        let config = FormatterConfig {
+            tab_prefix: "work/".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(with_tab_prefix("proj:main", &config), "work/proj:main");
+        assert_eq!(
+            with_tab_prefix("proj:main", &FormatterConfig::default()),
+            "proj:main"
+        );
+    }
+
+    #[test]
+    fn test_with_tab_prefix_respects_total_max_len() {
+        let config = FormatterConfig {
+            tab_prefix: "work/".to_string(),
+            total_max_len: 10,
+            ..Default::default()
+        };
+        assert_eq!(with_tab_prefix("proj:main", &config), "work/proj…");
+        assert_eq!(with_tab_prefix("api", &config), "work/api");
+
+        // A prefix that fills the cap on its own is cut too
+        let config = FormatterConfig {
+            tab_prefix: "workspace/".to_string(),
+            total_max_len: 6,
+            ..Default::default()
+        };
+        assert_eq!(with_tab_prefix("proj", &config), "worksp");
+
+        let config = FormatterConfig {
+            tab_prefix: "work/".to_string(),
+            total_max_len: 10,
+            ellipsis: "..".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(with_tab_prefix("proj:main", &config), "work/pro..");
+    }
+
+    #[test]
+    fn test_with_tab_prefix_merges_separator_with_empty_folder() {
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            tab_prefix: "work:".to_string(),
+            collapse_separators: true,
+            ..Default::default()
+        };
+        let name = format_tab_name("", Some("main"), &config);
+        assert_eq!(name, ":main");
+        assert_eq!(with_tab_prefix(&name, &config), "work:main");
+
+        // Without the normalization pass the decoration is joined verbatim
+        let config = FormatterConfig {
+            hide_default_branches: vec![],
+            collapse_separators: false,
+            ..config
+        };
+        assert_eq!(with_tab_prefix(":main", &config), "work::main");
+    }
+
+    #[test]
+    fn test_with_group_label_merges_separator_with_empty_folder() {
+        let config = FormatterConfig {
+            group_size: 4,
+            collapse_separators: true,
+            ..Default::default()
+        };
+        assert_eq!(with_group_label(":main", 0, &config), "G1:main");
+        assert_eq!(
+            with_tab_prefix(
+                &with_group_label(":main", 0, &config),
+                &FormatterConfig {
+                    tab_prefix: "work:".to_string(),
+                    ..config.clone()
+                }
+            ),
+            "work:G1:main"
+        );
+    }
+
+    #[test]
+    fn test_with_tab_prefix_caps_without_prefix() {
+        let config = FormatterConfig {
+            total_max_len: 8,
+            ..Default::default()
+        };
+        assert_eq!(with_tab_prefix("project:main", &config), "project…");
     }
 }
@@ -0,0 +1,612 @@
+//! Parsing of git command output.
+//!
+//! This module turns the raw stdout of the git commands the plugin runs into
+//! structured data, such as the branch and repository root, the
+//! staged/unstaged change counts reported by `git status --porcelain=v2
+//! --branch`, the remote the branch tracks, the commits ahead of a base branch
+//! and the forge hosting `origin`. It also names the version control systems a
+//! branch can be looked up with and recognises their not-a-repository errors.
+
+/// A version control system the branch lookup can use.
+///
+/// Systems are tried in the order configured via `vcs_order` until one
+/// reports a branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vcs {
+    /// Git, the default.
+    Git,
+    /// Mercurial.
+    Hg,
+    /// Jujutsu.
+    Jj,
+}
+
+impl Vcs {
+    /// Parses a single system name (`git`, `hg`, `jj`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "git" => Some(Self::Git),
+            "hg" => Some(Self::Hg),
+            "jj" => Some(Self::Jj),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list of systems, skipping unknown entries.
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',').filter_map(|p| Self::parse(p.trim())).collect()
+    }
+
+    /// The name used in config and in the `kind` command context tag.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Git => "git",
+            Self::Hg => "hg",
+            Self::Jj => "jj",
+        }
+    }
+}
+
+/// Working tree status of a git repository.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GitStatus {
+    /// Number of paths with changes staged in the index.
+    pub staged: usize,
+    /// Number of paths with changes in the working tree not yet staged.
+    pub unstaged: usize,
+    /// Number of commits the branch is ahead of its upstream.
+    pub ahead: usize,
+    /// Number of commits the branch is behind its upstream.
+    pub behind: usize,
+    /// Whole days since the branch's last commit, when it was read.
+    pub commit_age_days: Option<u64>,
+}
+
+impl GitStatus {
+    /// Returns whether the working tree or index has any changes.
+    pub fn is_dirty(&self) -> bool {
+        self.staged > 0 || self.unstaged > 0
+    }
+}
+
+/// Parses the output of `git status --porcelain=v2` into change counts.
+///
+/// Each changed entry (`1`, `2` or `u` lines) carries an `XY` field where `X`
+/// is the index status and `Y` the working tree status, with `.` meaning
+/// unmodified. A path counts as staged when `X` is not `.` and as unstaged
+/// when `Y` is not `.`, so a path can count towards both. Unmerged entries
+/// always count as unstaged. The `# branch.ab +A -B` header gives the commits
+/// ahead of and behind the upstream. Untracked, ignored and other header
+/// lines are skipped.
+///
+/// The status comes after the lookup's other lines, so only the lines from
+/// the first `# branch.` header on are read; a branch named `u` or `1` is
+/// never counted as a change. Returns `None` without that header, as when
+/// `git status` failed, so a failed check never reads as a clean tree.
+///
+/// # Examples
+///
+/// The line `1 M. N... 100644 100644 100644 abc def src/lib.rs` is one staged
+/// change; `1 .M ...` would be one unstaged change.
+pub fn parse_porcelain_v2(output: &str) -> Option<GitStatus> {
+    let start = output
+        .lines()
+        .position(|line| line.starts_with("# branch."))?;
+    let mut status = GitStatus::default();
+
+    for line in output.lines().skip(start) {
+        let mut fields = line.split(' ');
+        let kind = fields.next().unwrap_or_default();
+        let xy = fields.next().unwrap_or_default();
+
+        match kind {
+            "1" | "2" => {
+                let mut codes = xy.chars();
+                if codes.next().is_some_and(|x| x != '.') {
+                    status.staged += 1;
+                }
+                if codes.next().is_some_and(|y| y != '.') {
+                    status.unstaged += 1;
+                }
+            }
+            "u" => status.unstaged += 1,
+            "#" if xy == "branch.ab" => {
+                let mut count = |sign| {
+                    fields
+                        .next()
+                        .and_then(|f| f.strip_prefix(sign))
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or(0)
+                };
+                status.ahead = count('+');
+                status.behind = count('-');
+            }
+            _ => {}
+        }
+    }
+
+    Some(status)
+}
+
+/// Returns the branch, the first line of the branch lookup's output.
+///
+/// Every lookup script prints the branch and then the repository root, so
+/// this works for all of them, though jj output goes through
+/// [`parse_jj_branch`] for its bookmark markers. Returns `None` when the line
+/// is missing or blank.
+pub fn parse_git_branch(stdout: &[u8]) -> Option<String> {
+    parse_git_line(stdout, 0)
+}
+
+/// Returns whether a failed lookup's stderr says the path is not in a
+/// repository of `vcs`, rather than reporting some other error in one.
+///
+/// # Examples
+///
+/// git's `fatal: not a git repository (or any of the parent directories): .git`
+/// is a non-repo; `fatal: ambiguous argument 'HEAD'` in a repo without
+/// commits is not.
+pub fn is_not_a_repo(vcs: Vcs, stderr: &str) -> bool {
+    let marker = match vcs {
+        Vcs::Git => "not a git repository",
+        Vcs::Hg => "no repository found",
+        Vcs::Jj => "no jj repo",
+    };
+    stderr.to_lowercase().contains(marker)
+}
+
+/// Returns the branch from the jj lookup's output.
+///
+/// The first line lists the bookmarks on `@`, where jj marks one that moved
+/// or conflicts with a trailing `*` or `?`. The first bookmark is used; when
+/// there is none, as on a fresh change, the change id on the third line
+/// stands in for it.
+///
+/// # Examples
+///
+/// `"feature* main\n/repo\nkxqpz\n"` yields `"feature"`; `"\n/repo\nkxqpz\n"`
+/// yields `"kxqpz"`.
+pub fn parse_jj_branch(stdout: &[u8]) -> Option<String> {
+    let output = String::from_utf8_lossy(stdout);
+    let bookmark = output
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .map(|b| b.trim_end_matches(['*', '?']))
+        .filter(|b| !b.is_empty());
+    match bookmark {
+        Some(bookmark) => Some(bookmark.to_string()),
+        None => parse_git_line(stdout, 2),
+    }
+}
+
+/// Returns the repository root, the second line of the branch lookup's output.
+pub fn parse_git_toplevel(stdout: &[u8]) -> Option<String> {
+    parse_git_line(stdout, 1)
+}
+
+fn parse_git_line(stdout: &[u8], index: usize) -> Option<String> {
+    let output = String::from_utf8_lossy(stdout);
+    let line = output.lines().nth(index)?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+/// Extracts the remote the current branch tracks from the `upstream` line.
+///
+/// The plugin's git script prints `upstream <remote>/<branch>` using
+/// `git rev-parse --abbrev-ref --symbolic-full-name @{u}`, with nothing after
+/// the tag when the branch has no upstream. Returns the part before the first
+/// `/`, or `None` when there is no upstream.
+///
+/// # Examples
+///
+/// The line `upstream upstream/main` yields `"upstream"`; `upstream ` alone
+/// yields `None`.
+pub fn parse_upstream_remote(output: &str) -> Option<String> {
+    let upstream = output
+        .lines()
+        .find_map(|line| line.strip_prefix("upstream "))?
+        .trim();
+    let (remote, _) = upstream.split_once('/')?;
+    if remote.is_empty() {
+        None
+    } else {
+        Some(remote.to_string())
+    }
+}
+
+/// Extracts the `origin` remote URL from the `origin` line.
+///
+/// The plugin's git script prints `origin <url>` using
+/// `git remote get-url origin`, with nothing after the tag when there is no
+/// such remote.
+pub fn parse_origin_url(output: &str) -> Option<String> {
+    let url = output
+        .lines()
+        .find_map(|line| line.strip_prefix("origin "))?
+        .trim();
+    (!url.is_empty()).then(|| url.to_string())
+}
+
+/// Extracts the commits ahead of the base branch from the `base` line.
+///
+/// The plugin's git script prints `base <count>` using
+/// `git rev-list --count <base>..HEAD`, with nothing after the tag when the
+/// base branch does not exist.
+///
+/// # Examples
+///
+/// The line `base 7` yields `Some(7)`; `base ` alone yields `None`.
+pub fn parse_base_ahead(output: &str) -> Option<usize> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("base "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Parses the counts printed by
+/// `git rev-list --left-right --count @{upstream}...HEAD` into
+/// `(ahead, behind)`.
+///
+/// git prints the commits only the upstream has, a tab, then the commits
+/// only `HEAD` has, so the left count is how far the branch is behind.
+/// Returns `None` for anything else, such as the empty output of a branch
+/// without an upstream.
+///
+/// # Examples
+///
+/// `2\t1` yields `Some((1, 2))`: one commit ahead and two behind.
+pub fn parse_ahead_behind(stdout: &[u8]) -> Option<(usize, usize)> {
+    let output = std::str::from_utf8(stdout).ok()?;
+    let (behind, ahead) = output.trim().split_once('\t')?;
+    Some((ahead.trim().parse().ok()?, behind.trim().parse().ok()?))
+}
+
+/// Extracts the days since the last commit from the `age` line.
+///
+/// The plugin's git script prints `age <now> <commit>` as Unix timestamps,
+/// the second from `git log -1 --format=%ct`, so the plugin never reads a
+/// clock itself. The commit time is missing on a branch without commits.
+///
+/// # Examples
+///
+/// `age 1700086400 1700000000` yields `Some(1)`; `age 1700086400 ` yields
+/// `None`.
+pub fn parse_commit_age_days(output: &str) -> Option<u64> {
+    let line = output.lines().find_map(|line| line.strip_prefix("age "))?;
+    let mut times = line.split_whitespace().map(str::parse::<u64>);
+    let now = times.next()?.ok()?;
+    let commit = times.next()?.ok()?;
+    Some(now.saturating_sub(commit) / 86_400)
+}
+
+/// Returns a short label for the forge hosting `remote_url`.
+///
+/// Accepts URLs such as `https://github.com/o/r.git` and
+/// `ssh://git@gitlab.com/o/r`, and the scp-like `git@github.com:o/r.git`.
+/// Well-known forges get a fixed label (`github.com` → `gh`); any other host
+/// is labeled with its first name, so `git.example.com` gives `git`.
+pub fn forge_label(remote_url: &str) -> Option<String> {
+    let host = match remote_url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        // scp-like syntax: [user@]host:path
+        None => remote_url.split_once(':')?.0,
+    };
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_lowercase();
+    let label = match host.as_str() {
+        "" => return None,
+        "github.com" => "gh",
+        "gitlab.com" => "gl",
+        "bitbucket.org" => "bb",
+        "codeberg.org" => "cb",
+        "sr.ht" | "git.sr.ht" => "srht",
+        _ => host.split('.').next()?,
+    };
+    Some(label.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain_v2_empty() {
+        assert_eq!(parse_porcelain_v2(""), None);
+        // A clean tree still has its branch headers
+        assert_eq!(
+            parse_porcelain_v2("# branch.oid abc123\n"),
+            Some(GitStatus::default())
+        );
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_without_header_is_none() {
+        // The branch and toplevel lines alone, as when `git status` failed
+        assert_eq!(parse_porcelain_v2("u\n/work/api\n"), None);
+        assert_eq!(parse_porcelain_v2("1\n/work/api\n"), None);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_ignores_lines_before_status() {
+        let output = "u\n/work/api\n# branch.oid abc123\n# branch.head u\n";
+        assert_eq!(parse_porcelain_v2(output), Some(GitStatus::default()));
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_staged_only() {
+        let output = "# branch.oid abc123\n\
+                      1 M. N... 100644 100644 100644 abc123 def456 src/lib.rs\n\
+                      1 A. N... 000000 100644 100644 000000 def456 src/new.rs\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.staged, 2);
+        assert_eq!(status.unstaged, 0);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_unstaged_only() {
+        let output = "# branch.oid abc123\n\
+                      1 .M N... 100644 100644 100644 abc123 abc123 README.md\n\
+                      1 .D N... 100644 100644 000000 abc123 abc123 old.txt\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.staged, 0);
+        assert_eq!(status.unstaged, 2);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_index_and_worktree() {
+        // Staged, then modified again in the worktree: counts as both
+        let output =
+            "# branch.oid abc123\n1 MM N... 100644 100644 100644 abc123 def456 src/main.rs\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.unstaged, 1);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_rename_and_unmerged() {
+        let output = "# branch.oid abc123\n2 R. N... 100644 100644 100644 abc123 abc123 R100 new.rs\told.rs\n\
+                      u UU N... 100644 100644 100644 100644 a b c conflict.rs\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.unstaged, 1);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_skips_other_lines() {
+        let output = "main\n/home/user/project\n\
+                      # branch.oid abc123\n\
+                      ? untracked.txt\n\
+                      ! ignored.log\n\
+                      1 .M N... 100644 100644 100644 abc123 abc123 README.md\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.staged, 0);
+        assert_eq!(status.unstaged, 1);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_ahead_behind() {
+        let output = "# branch.oid abc123\n\
+                      # branch.head main\n\
+                      # branch.upstream origin/main\n\
+                      # branch.ab +2 -1\n\
+                      1 .M N... 100644 100644 100644 abc123 abc123 README.md\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status.ahead, 2);
+        assert_eq!(status.behind, 1);
+        assert_eq!(status.unstaged, 1);
+        assert!(status.is_dirty());
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_no_upstream_header() {
+        let output = "# branch.oid abc123\n# branch.head main\n";
+        let status = parse_porcelain_v2(output).unwrap();
+        assert_eq!(status, GitStatus::default());
+        assert!(!status.is_dirty());
+    }
+
+    #[test]
+    fn test_parse_upstream_remote() {
+        let output = "main\n/home/user/project\nupstream upstream/main\n";
+        assert_eq!(parse_upstream_remote(output), Some("upstream".to_string()));
+        assert_eq!(
+            parse_upstream_remote("upstream origin/feature/login\n"),
+            Some("origin".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_not_a_repo() {
+        assert!(is_not_a_repo(
+            Vcs::Git,
+            "fatal: not a git repository (or any of the parent directories): .git\n"
+        ));
+        assert!(is_not_a_repo(
+            Vcs::Hg,
+            "abort: no repository found in '/tmp/x' (.hg not found)!\n"
+        ));
+        assert!(is_not_a_repo(
+            Vcs::Jj,
+            "Error: There is no jj repo in \".\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_is_not_a_repo_other_errors() {
+        assert!(!is_not_a_repo(
+            Vcs::Git,
+            "fatal: ambiguous argument 'HEAD': unknown revision\n"
+        ));
+        assert!(!is_not_a_repo(
+            Vcs::Git,
+            "fatal: detected dubious ownership in repository at '/work/api'\n"
+        ));
+        assert!(!is_not_a_repo(Vcs::Git, ""));
+        // Each system only recognises its own message
+        assert!(!is_not_a_repo(Vcs::Hg, "fatal: not a git repository\n"));
+    }
+
+    #[test]
+    fn test_parse_hg_branch() {
+        // `hg branch` prints just the name; the lookup adds `hg root` after it
+        assert_eq!(parse_git_branch(b"default\n"), Some("default".to_string()));
+        assert_eq!(parse_git_toplevel(b"default\n"), None);
+        assert_eq!(
+            parse_git_branch(b"stable\n/work/legacy\n"),
+            Some("stable".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_jj_branch() {
+        assert_eq!(
+            parse_jj_branch(b"feature* main\n/work/api\nkxqpzvts\n"),
+            Some("feature".to_string())
+        );
+        assert_eq!(
+            parse_jj_branch(b"trunk??\n/work/api\nkxqpzvts\n"),
+            Some("trunk".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_jj_branch_falls_back_to_change_id() {
+        assert_eq!(
+            parse_jj_branch(b"\n/work/api\nkxqpzvts\n"),
+            Some("kxqpzvts".to_string())
+        );
+        assert_eq!(parse_jj_branch(b"\n/work/api\n"), None);
+        assert_eq!(parse_jj_branch(b""), None);
+    }
+
+    #[test]
+    fn test_vcs_parse_list() {
+        assert_eq!(
+            Vcs::parse_list("git, hg,svn,jj"),
+            vec![Vcs::Git, Vcs::Hg, Vcs::Jj]
+        );
+        assert!(Vcs::parse_list("").is_empty());
+        assert_eq!(Vcs::parse(Vcs::Jj.name()), Some(Vcs::Jj));
+    }
+
+    #[test]
+    fn test_parse_upstream_remote_none() {
+        assert_eq!(
+            parse_upstream_remote("main\n/home/user/project\nupstream \n"),
+            None
+        );
+        assert_eq!(parse_upstream_remote("main\n"), None);
+        assert_eq!(parse_upstream_remote("upstream main\n"), None);
+        assert_eq!(parse_upstream_remote("upstream /main\n"), None);
+    }
+
+    #[test]
+    fn test_forge_label_https() {
+        assert_eq!(
+            forge_label("https://github.com/wusher/zellij-namey.git"),
+            Some("gh".to_string())
+        );
+        assert_eq!(
+            forge_label("https://user@GitLab.com:443/group/repo"),
+            Some("gl".to_string())
+        );
+        assert_eq!(
+            forge_label("https://git.example.com/team/repo.git"),
+            Some("git".to_string())
+        );
+    }
+
+    #[test]
+    fn test_forge_label_ssh() {
+        assert_eq!(
+            forge_label("git@github.com:wusher/zellij-namey.git"),
+            Some("gh".to_string())
+        );
+        assert_eq!(
+            forge_label("ssh://git@bitbucket.org:7999/team/repo.git"),
+            Some("bb".to_string())
+        );
+        assert_eq!(forge_label("/srv/git/repo.git"), None);
+        assert_eq!(forge_label(""), None);
+    }
+
+    #[test]
+    fn test_parse_origin_url() {
+        let output = "main\n/work/api\norigin git@github.com:o/r.git\n";
+        assert_eq!(
+            parse_origin_url(output),
+            Some("git@github.com:o/r.git".to_string())
+        );
+        assert_eq!(parse_origin_url("main\norigin \n"), None);
+    }
+
+    #[test]
+    fn test_parse_git_branch_and_toplevel() {
+        let stdout = b"main\n/work/api\nupstream origin/main\n";
+        assert_eq!(parse_git_branch(stdout), Some("main".to_string()));
+        assert_eq!(parse_git_toplevel(stdout), Some("/work/api".to_string()));
+        assert_eq!(parse_git_branch(b""), None);
+        assert_eq!(parse_git_toplevel(b"main\n  \n"), None);
+    }
+
+    #[test]
+    fn test_parse_base_ahead() {
+        let output = "feature\n/work/api\nbase 7\n";
+        assert_eq!(parse_base_ahead(output), Some(7));
+        assert_eq!(parse_base_ahead("main\nbase 0\n"), Some(0));
+    }
+
+    #[test]
+    fn test_parse_base_ahead_without_base_branch() {
+        assert_eq!(parse_base_ahead("feature\n/work/api\nbase \n"), None);
+        assert_eq!(parse_base_ahead("feature\n/work/api\n"), None);
+    }
+
+    #[test]
+    fn test_parse_commit_age_days() {
+        let output = "dev\n/work/api\nage 1700086400 1700000000\n";
+        assert_eq!(parse_commit_age_days(output), Some(1));
+        assert_eq!(
+            parse_commit_age_days("age 1700086399 1700000000\n"),
+            Some(0)
+        );
+        // A commit dated ahead of the clock counts as brand new
+        assert_eq!(
+            parse_commit_age_days("age 1700000000 1700086400\n"),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_parse_commit_age_days_without_commits() {
+        assert_eq!(
+            parse_commit_age_days("main\n/work/api\nage 1700086400 \n"),
+            None
+        );
+        assert_eq!(parse_commit_age_days("main\n/work/api\n"), None);
+    }
+
+    #[test]
+    fn test_parse_ahead_behind() {
+        assert_eq!(parse_ahead_behind(b"2\t1\n"), Some((1, 2)));
+        assert_eq!(parse_ahead_behind(b"0\t3"), Some((3, 0)));
+        assert_eq!(parse_ahead_behind(b"0\t0\n"), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_parse_ahead_behind_without_upstream() {
+        assert_eq!(parse_ahead_behind(b""), None);
+        assert_eq!(parse_ahead_behind(b"2 1\n"), None);
+        assert_eq!(parse_ahead_behind(b"x\t1\n"), None);
+    }
+
+    #[test]
+    fn test_parse_porcelain_v2_skips_upstream_line() {
+        let output = "upstream origin/main\n# branch.oid abc123\n\
+                      1 .M N... 100644 100644 100644 a b README.md\n";
+        assert_eq!(parse_porcelain_v2(output).unwrap().unstaged, 1);
+    }
+}
@@ -0,0 +1,13 @@
+//! Tab naming logic for the zellij-namey plugin.
+//!
+//! The plugin itself lives in the binary target; this library exposes the
+//! parts that do not depend on Zellij, so they can be tested and reused on
+//! their own: pane title parsing, pane context, git output parsing and name
+//! formatting.
+
+pub mod context;
+pub mod formatter;
+pub mod git;
+pub mod title;
+
+pub use title::{extract_cwd_from_title, parse_title, ParsedTitle};
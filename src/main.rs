@@ -1,83 +1,127 @@
-mod context;
-mod formatter;
+mod state;
 
 use std::collections::BTreeMap;
+use zellij_namey::{context, formatter, git, title};
 use zellij_tile::prelude::*;
 
-use context::PaneContext;
-use formatter::{format_tab_name, FormatterConfig};
+use context::resolve_home_dir;
+use formatter::FormatterConfig;
+use state::{CommandRunner, Renamer, Scheduler, State};
 
-const GIT_BRANCH_SCRIPT: &str = r#"git -C "$1" rev-parse --abbrev-ref HEAD 2>/dev/null"#;
+// The git scripts take the path as "$1" and the git command itself, binary
+// plus any extra arguments, as the rest, so neither needs shell quoting.
+// Branch lookups keep the first command's stderr so a failure outside any
+// repository can be told apart from other errors.
+const GIT_BRANCH_SCRIPT: &str = r#""${@:2}" -C "$1" rev-parse --abbrev-ref HEAD --show-toplevel"#;
 
-fn is_our_command(context: &BTreeMap<String, String>) -> bool {
-    context.get("source").map(|s| s.as_str()) == Some("namey")
-}
+// Prints the directory itself when it was modified within "$2" minutes.
+const FRESHNESS_SCRIPT: &str = r#"find "$1" -maxdepth 0 -mmin "-$2" 2>/dev/null"#;
 
-fn extract_cwd_from_title(title: &str) -> Option<String> {
-    let title = title.trim();
-    if title.is_empty() {
-        return None;
-    }
+const GIT_STATUS_LINE: &str = r#""${@:2}" -C "$1" status --porcelain=v2 --branch 2>/dev/null"#;
 
-    // Try ": /path" format
-    if let Some(idx) = title.rfind(": ") {
-        let after_colon = title[idx + 2..].trim();
-        if after_colon.starts_with('/') || after_colon.starts_with('~') {
-            return Some(after_colon.to_string());
-        }
-    }
+// Always prints a tagged line, empty when there is no upstream, so it can
+// never be mistaken for status output.
+const GIT_UPSTREAM_LINE: &str = r#"printf 'upstream %s\n' "$("${@:2}" -C "$1" rev-parse --abbrev-ref --symbolic-full-name '@{u}' 2>/dev/null)""#;
 
-    // Check if whole title is a path
-    if title.starts_with('/') || title.starts_with('~') {
-        return Some(title.to_string());
-    }
+// Tagged like the upstream line, empty when there is no `origin` remote.
+const GIT_ORIGIN_LINE: &str =
+    r#"printf 'origin %s\n' "$("${@:2}" -C "$1" remote get-url origin 2>/dev/null)""#;
 
-    // Try ":/" format (no space)
-    if let Some(idx) = title.rfind(':') {
-        let after_colon = title[idx + 1..].trim();
-        if after_colon.starts_with('/') || after_colon.starts_with('~') {
-            return Some(after_colon.to_string());
-        }
-    }
+// Tagged like the upstream line, empty when the base branch does not exist.
+// Reads the base branch from the `base` variable the script sets up.
+const GIT_BASE_LINE: &str =
+    r#"printf 'base %s\n' "$("${@:2}" -C "$1" rev-list --count "$base..HEAD" 2>/dev/null)""#;
+
+// Prints the first of the marker files "${@:2}" present in "$1", failing
+// when there is none.
+const PROJECT_SCRIPT: &str =
+    r#"for f in "${@:2}"; do [ -e "$1/$f" ] && { printf '%s\n' "$f"; exit 0; }; done; exit 1"#;
+
+// Prints the top of the README.md at the root of the repo holding "$1", or
+// in "$1" itself outside a repo. "${@:2}" is the git command.
+const README_SCRIPT: &str = r#"root=$("${@:2}" -C "$1" rev-parse --show-toplevel 2>/dev/null) || root="$1"; head -n 200 "$root/README.md" 2>/dev/null"#;
 
-    None
+// Prints the cwd's package.json, capped so a huge file stays cheap to scan.
+const PACKAGE_SCRIPT: &str = r#"head -c 65536 "$1/package.json" 2>/dev/null"#;
+
+// Prints the system owning "$1", walking up to the root; jj wins over the
+// .git it may be colocated with.
+const VCS_DETECT_SCRIPT: &str = r#"d="$1"; while :; do for v in jj hg git; do [ -e "$d/.$v" ] && { printf '%s\n' "$v"; exit 0; }; done; [ "$d" = / ] || [ "$d" = . ] && exit 1; d=$(dirname "$d"); done"#;
+
+// Tagged like the upstream line with the current time, then the time of the
+// last commit, which is empty on a branch without commits.
+const GIT_AGE_LINE: &str =
+    r#"printf 'age %s %s\n' "$(date +%s)" "$("${@:2}" -C "$1" log -1 --format=%ct 2>/dev/null)""#;
+
+// Print the branch, then the repository root, like the git lookup.
+const HG_BRANCH_SCRIPT: &str = r#"hg --cwd "$1" branch && hg --cwd "$1" root 2>/dev/null"#;
+
+// A third line carries the change id for when @ has no bookmark.
+const JJ_BRANCH_SCRIPT: &str = r#"cd "$1" 2>/dev/null && jj log -r @ --no-graph --ignore-working-copy -T 'bookmarks ++ "\n"' && jj root 2>/dev/null && jj log -r @ --no-graph --ignore-working-copy -T 'change_id.short() ++ "\n"' 2>/dev/null"#;
+
+fn is_our_command(context: &BTreeMap<String, String>) -> bool {
+    context.get("source").map(|s| s.as_str()) == Some("namey")
 }
 
-fn parse_git_branch(stdout: &[u8]) -> Option<String> {
-    let output = String::from_utf8_lossy(stdout);
-    let branch = output.trim();
-    if branch.is_empty() {
-        None
-    } else {
-        Some(branch.to_string())
-    }
+/// Context for a follow-up command such as `name_command`, told apart from
+/// the git lookup by its `stage`.
+fn build_stage_context(path: &str, tab: usize, stage: &str) -> BTreeMap<String, String> {
+    let mut context = build_command_context(path, tab);
+    context.insert("stage".to_string(), stage.to_string());
+    context
 }
 
-fn build_command_context(path: &str) -> BTreeMap<String, String> {
+fn build_command_context(path: &str, tab: usize) -> BTreeMap<String, String> {
     BTreeMap::from([
         ("source".to_string(), "namey".to_string()),
         ("path".to_string(), path.to_string()),
+        ("tab".to_string(), tab.to_string()),
     ])
 }
 
-#[derive(Default)]
-struct State {
-    config: FormatterConfig,
-    current_cwd: Option<String>,
-    current_tab_index: usize,
-    current_tab_name: String,
+/// Host backed by the real Zellij plugin API.
+struct ZellijHost;
+
+impl Renamer for ZellijHost {
+    fn rename_tab(&mut self, position: u32, name: &str) {
+        rename_tab(position, name);
+    }
+
+    fn rename_session(&mut self, name: &str) {
+        rename_session(name);
+    }
+}
+
+impl CommandRunner for ZellijHost {
+    fn run_command(&mut self, args: &[&str], context: BTreeMap<String, String>) {
+        run_command(args, context);
+    }
+}
+
+impl Scheduler for ZellijHost {
+    fn set_timeout(&mut self, secs: f64) {
+        set_timeout(secs);
+    }
 }
 
 register_plugin!(State);
 
 impl ZellijPlugin for State {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
-        self.config = FormatterConfig::from_config(&configuration);
+        // Plugin stderr ends up in the Zellij log
+        self.config = FormatterConfig::try_from_config(&configuration).unwrap_or_else(|errors| {
+            for error in &errors {
+                eprintln!("zellij-namey: config: {}", error);
+            }
+            FormatterConfig::from_config(&configuration)
+        });
+        self.home_dir = resolve_home_dir(std::env::var("HOME").ok(), &configuration);
 
         request_permission(&[
             PermissionType::ReadApplicationState,
             PermissionType::ChangeApplicationState,
             PermissionType::RunCommands,
+            PermissionType::ReadCliPipes,
         ]);
 
         subscribe(&[
@@ -85,90 +129,44 @@ impl ZellijPlugin for State {
             EventType::PaneUpdate,
             EventType::RunCommandResult,
             EventType::PermissionRequestResult,
+            EventType::Timer,
         ]);
     }
 
     fn update(&mut self, event: Event) -> bool {
         match event {
-            Event::PermissionRequestResult(_status) => {}
+            Event::PermissionRequestResult(PermissionStatus::Granted) => {
+                self.handle_permissions_granted(&mut ZellijHost);
+            }
             Event::TabUpdate(tab_info) => {
-                if let Some(active_tab) = tab_info.iter().find(|t| t.active) {
-                    self.current_tab_index = active_tab.position;
-                    self.current_tab_name = active_tab.name.clone();
-                }
+                self.handle_tab_update(&tab_info);
             }
             Event::PaneUpdate(pane_manifest) => {
-                self.handle_pane_update(pane_manifest);
+                self.handle_pane_update(pane_manifest, &mut ZellijHost);
             }
-            Event::RunCommandResult(exit_code, stdout, _stderr, context) => {
-                self.handle_command_result(exit_code, stdout, context);
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                self.handle_command_result(exit_code, stdout, stderr, context, &mut ZellijHost);
+            }
+            Event::Timer(elapsed) => {
+                self.handle_timer(elapsed, &mut ZellijHost);
             }
             _ => {}
         }
         false
     }
 
-    fn render(&mut self, _rows: usize, _cols: usize) {}
-}
-
-impl State {
-    fn handle_pane_update(&mut self, pane_manifest: PaneManifest) {
-        let focused_pane = pane_manifest
-            .panes
-            .values()
-            .flatten()
-            .find(|p| p.is_focused && !p.is_plugin);
-
-        if let Some(pane) = focused_pane {
-            if let Some(cwd) = extract_cwd_from_title(&pane.title) {
-                if self.current_cwd.as_ref() != Some(&cwd) {
-                    self.current_cwd = Some(cwd.clone());
-                    self.request_git_branch(&cwd);
-                }
-            } else {
-                // Use title directly as folder name
-                let folder = pane.title.trim();
-                if !folder.is_empty() {
-                    let new_name = format_tab_name(folder, None, &self.config);
-                    if new_name != self.current_tab_name {
-                        rename_tab(self.current_tab_index as u32, &new_name);
-                    }
-                }
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        let reply = self.handle_pipe_message(&pipe_message.name, &mut ZellijHost);
+        if let PipeSource::Cli(pipe_id) = &pipe_message.source {
+            if let Some(reply) = reply {
+                cli_pipe_output(pipe_id, &reply);
             }
+            unblock_cli_pipe_input(pipe_id);
         }
+        false
     }
 
-    fn request_git_branch(&mut self, path: &str) {
-        let context = build_command_context(path);
-        run_command(&["bash", "-c", GIT_BRANCH_SCRIPT, "_", path], context);
-    }
-
-    fn handle_command_result(
-        &mut self,
-        exit_code: Option<i32>,
-        stdout: Vec<u8>,
-        context: BTreeMap<String, String>,
-    ) {
-        if !is_our_command(&context) {
-            return;
-        }
-
-        let path = match context.get("path") {
-            Some(p) => p.clone(),
-            None => return,
-        };
-
-        let branch = if exit_code == Some(0) {
-            parse_git_branch(&stdout)
-        } else {
-            None
-        };
-
-        let ctx = PaneContext { cwd: path, branch };
-        let new_name = format_tab_name(ctx.folder_name(), ctx.branch.as_deref(), &self.config);
-
-        if new_name != self.current_tab_name {
-            rename_tab(self.current_tab_index as u32, &new_name);
-        }
+    fn render(&mut self, _rows: usize, cols: usize) {
+        self.handle_resize(cols, &mut ZellijHost);
     }
 }
@@ -0,0 +1,4406 @@
+//! Plugin state and event handling.
+//!
+//! This module holds the per-tab bookkeeping and the logic that reacts to
+//! Zellij events. Side effects go through the [`Renamer`], [`CommandRunner`]
+//! and [`Scheduler`] traits so the event flow can be exercised in tests
+//! without a running Zellij host.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use zellij_tile::prelude::{PaneInfo, PaneManifest, TabInfo};
+
+use crate::context::{
+    expand_tilde, family_name, is_within, parse_package_name, parse_readme_title, reduce_title,
+    repo_subpath, resolve_name, NameInputs, NameSource, PaneContext,
+};
+use crate::formatter::{
+    format_busy_name, format_status, format_tab_name, format_tab_name_with_status, is_stale,
+    reset_name, select_badges, session_name, shell_icon, template_for_tab, with_attention,
+    with_base_ahead, with_family_label, with_forge_label, with_group_label, with_kube_context,
+    with_layout, with_non_vcs_symbol, with_project_icon, with_shell_icon, with_tab_prefix,
+    with_unc_server, Badge, FormatterConfig, PaneLayout, RenameOn,
+};
+use crate::git::{
+    forge_label, is_not_a_repo, parse_base_ahead, parse_commit_age_days, parse_git_branch,
+    parse_git_toplevel, parse_jj_branch, parse_origin_url, parse_porcelain_v2,
+    parse_upstream_remote, GitStatus, Vcs,
+};
+use crate::title::{extract_cwd_from_title, parse_title, strip_emoji};
+use crate::{
+    build_command_context, build_stage_context, is_our_command, FRESHNESS_SCRIPT, GIT_AGE_LINE,
+    GIT_BASE_LINE, GIT_BRANCH_SCRIPT, GIT_ORIGIN_LINE, GIT_STATUS_LINE, GIT_UPSTREAM_LINE,
+    HG_BRANCH_SCRIPT, JJ_BRANCH_SCRIPT, PACKAGE_SCRIPT, PROJECT_SCRIPT, README_SCRIPT,
+    VCS_DETECT_SCRIPT,
+};
+
+/// Renames tabs and the session in the host.
+pub trait Renamer {
+    /// Renames the tab at `position` to `name`.
+    fn rename_tab(&mut self, position: u32, name: &str);
+    /// Renames the current session to `name`.
+    fn rename_session(&mut self, name: &str);
+}
+
+/// Runs background commands in the host.
+pub trait CommandRunner {
+    /// Runs `args`, tagging the eventual result with `context`.
+    fn run_command(&mut self, args: &[&str], context: BTreeMap<String, String>);
+}
+
+/// Schedules timer events in the host.
+pub trait Scheduler {
+    /// Requests a timer event after `secs` seconds.
+    fn set_timeout(&mut self, secs: f64);
+}
+
+/// Every side effect the plugin needs from its host.
+pub trait Host: Renamer + CommandRunner + Scheduler {}
+
+impl<T: Renamer + CommandRunner + Scheduler> Host for T {}
+
+/// How many recent names are kept per tab to tell manual renames apart
+/// from TabUpdates that lag behind the plugin's own renames.
+const RECENT_NAMES: usize = 4;
+
+/// What the plugin knows about a single tab.
+#[derive(Debug, Clone, Default)]
+pub struct TabState {
+    /// The tab's name, as last reported by Zellij or set by the plugin.
+    pub name: String,
+    /// The last cwd seen in the tab's focused pane.
+    pub cwd: Option<String>,
+    /// The remote host when the focused pane's title shows an ssh session.
+    pub ssh_host: Option<String>,
+    /// The title of the tab's focused pane.
+    pub title: String,
+    /// The command running in the tab's focused pane, if any.
+    pub command: Option<String>,
+    /// The id of the tab's focused pane at the last evaluation.
+    pub focused_pane: Option<u32>,
+    /// Whether the cwd was recently modified, once the freshness check returns.
+    pub fresh: Option<bool>,
+    /// The active kubernetes context, once its lookup returns with one.
+    pub kube_context: Option<String>,
+    /// The project icon, once the marker file lookup finds one.
+    pub icon: Option<String>,
+    /// The README heading, once the `readme_title` lookup finds one.
+    pub readme_title: Option<String>,
+    /// The `package.json` name, once the `package_name` lookup finds one.
+    pub package_name: Option<String>,
+    /// How the tab's panes were arranged at the last pane update.
+    pub layout: PaneLayout,
+    /// The name the tab would have been given while renames were frozen.
+    pub pending_name: Option<String>,
+    /// The pending sticky-cwd timer while a non-path title is being held.
+    pub hold: Option<u64>,
+    /// The pending `single_rename` timer while the branch lookup runs.
+    pub deferred: Option<u64>,
+    /// Recent names the plugin wrote and the names they replaced, so a
+    /// TabUpdate reporting any of them, even a stale one, is not mistaken
+    /// for a manual rename.
+    pub recent_names: VecDeque<String>,
+    /// Whether the user renamed the tab since its cwd last changed.
+    pub manual: bool,
+    /// The name last given to the tab, before `attention_symbol` is added.
+    pub plain_name: String,
+    /// The ids of the tab's panes whose command had exited at the last pane
+    /// update.
+    pub exited_panes: Vec<u32>,
+    /// Whether a command finished in the tab since it was last focused.
+    pub attention: bool,
+    /// The context the tab was last named from, kept so it can be re-rendered
+    /// when the width changes. `None` while a git lookup is in flight.
+    pub pane: Option<PaneContext>,
+}
+
+/// Plugin state shared across events.
+///
+/// Bookkeeping is kept per tab so that a git lookup in flight for one tab
+/// can never clobber the name or cwd of another.
+#[derive(Default)]
+pub struct State {
+    /// Formatting configuration parsed at load time.
+    pub config: FormatterConfig,
+    /// Home directory used to expand `~` before running git.
+    pub home_dir: Option<String>,
+    active_tab: usize,
+    tabs: BTreeMap<usize, TabState>,
+    cols: Option<usize>,
+    /// The session name last set by `rename_session`.
+    session_name: Option<String>,
+    /// Whether renames are paused by the `namey:freeze` pipe command.
+    frozen: bool,
+    /// The session name held back while frozen.
+    pending_session_name: Option<String>,
+    /// Pending timers with the duration each was set for, oldest first.
+    timers: VecDeque<(f64, Timer)>,
+    next_timer_id: u64,
+    /// Finished branch lookups by cwd, reused until `git_cache_ttl_ms` passes.
+    git_cache: BTreeMap<String, CachedLookup>,
+    /// The latest pane update held back by `settle_ms`, with its timer id.
+    pending_panes: Option<(u64, PaneManifest)>,
+}
+
+/// The parts of a finished branch lookup that depend only on the cwd.
+#[derive(Debug, Clone)]
+struct CachedLookup {
+    /// The expiry timer for this entry.
+    timer_id: u64,
+    branch: Option<String>,
+    repo_root: Option<String>,
+    status: Option<GitStatus>,
+    remote: Option<String>,
+    forge: Option<String>,
+    base_ahead: Option<usize>,
+    outside_vcs: bool,
+}
+
+/// A timer set through [`Scheduler`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Timer {
+    /// Ends the sticky-cwd hold with this id on a tab.
+    Hold(usize, u64),
+    /// Re-renders every tab for `name_ttl_secs`.
+    Refresh,
+    /// Drops the git cache entry with this timer id.
+    ExpireLookup(u64),
+    /// Applies the held pane update if no later one replaced it.
+    Settle(u64),
+    /// Names a tab from its folder if its `single_rename` lookup is still
+    /// running.
+    Defer(usize, u64),
+}
+
+impl State {
+    /// Records tab names and the active tab position, dropping closed tabs.
+    pub fn handle_tab_update(&mut self, tab_info: &[TabInfo]) {
+        self.realign_tabs(tab_info);
+
+        for tab in tab_info {
+            let state = self.tabs.entry(tab.position).or_default();
+            if self.config.respect_manual_rename
+                && !state.recent_names.is_empty()
+                && !state.recent_names.contains(&tab.name)
+            {
+                state.manual = true;
+            }
+            state.name = tab.name.clone();
+            if tab.active {
+                self.active_tab = tab.position;
+            }
+        }
+    }
+
+    /// Drops the state of closed tabs, moving each later tab's state down
+    /// with it.
+    ///
+    /// Positions stay contiguous, so closing a tab shifts every tab after it
+    /// down by one. The closed tabs are found by matching the remaining
+    /// names, in order, against the names last seen; tabs that cannot be told
+    /// apart that way are taken to have closed from the end.
+    fn realign_tabs(&mut self, tab_info: &[TabInfo]) {
+        let mut remaining: Vec<&TabInfo> = tab_info.iter().collect();
+        remaining.sort_by_key(|t| t.position);
+        let mut closed = self.tabs.len().saturating_sub(remaining.len());
+        let mut kept = Vec::new();
+        for (_, tab) in std::mem::take(&mut self.tabs) {
+            let Some(next) = remaining.get(kept.len()) else {
+                break;
+            };
+            if closed > 0 && tab.name != next.name {
+                closed -= 1;
+                continue;
+            }
+            kept.push(tab);
+        }
+        self.tabs = remaining.iter().map(|t| t.position).zip(kept).collect();
+    }
+
+    /// Shows `initial_name` on the active tab as soon as the plugin may rename,
+    /// so it is visibly running before the first pane update.
+    ///
+    /// A tab that was already named from a pane is left alone.
+    pub fn handle_permissions_granted(&mut self, host: &mut impl Host) {
+        self.arm_refresh(host);
+        if self.config.initial_name.is_empty() {
+            return;
+        }
+        let position = self.active_tab;
+        let tab = self.tabs.entry(position).or_default();
+        if tab.cwd.is_some() || tab.pane.is_some() {
+            return;
+        }
+        let name = self.config.initial_name.clone();
+        self.rename(position, name, host);
+    }
+
+    /// Records the plugin's width, re-rendering every tab when the narrow
+    /// profile or the width-dependent separator switches.
+    pub fn handle_resize(&mut self, cols: usize, host: &mut impl Host) {
+        // Names from name_command are used verbatim and never depend on width
+        if !self.config.name_command.is_empty() {
+            self.cols = Some(cols);
+            return;
+        }
+
+        let bands = |state: &Self| {
+            (
+                state.config.is_narrow(state.cols),
+                state.config.is_separator_narrow(state.cols),
+            )
+        };
+        let before = bands(self);
+        self.cols = Some(cols);
+        if bands(self) == before {
+            return;
+        }
+
+        let positions: Vec<usize> = self.tabs.keys().copied().collect();
+        for position in positions {
+            let tab = &self.tabs[&position];
+            match (&tab.pane, &tab.cwd) {
+                (Some(pane), _) => {
+                    let pane = pane.clone();
+                    self.rename_from_sources(position, Some(pane), host);
+                }
+                // Title-only tab; a tab with a cwd but no context is mid-lookup
+                (None, None) => {
+                    self.rename_from_sources(position, None, host);
+                }
+                (None, Some(_)) => {}
+            }
+        }
+    }
+
+    /// Reacts to a pane change in any tab.
+    ///
+    /// Every naming-relevant change, whether a title, cwd or focus change,
+    /// arrives here, so this is the one place `settle_ms` applies: the update
+    /// is held until no other arrives for that long, and only the latest
+    /// manifest of a burst is applied.
+    pub fn handle_pane_update(&mut self, pane_manifest: PaneManifest, host: &mut impl Host) {
+        if self.config.settle_ms == 0 {
+            self.apply_pane_update(pane_manifest, host);
+            return;
+        }
+        let id = self.next_timer_id;
+        self.next_timer_id += 1;
+        self.pending_panes = Some((id, pane_manifest));
+        let secs = self.config.settle_ms as f64 / 1000.0;
+        self.set_timer(secs, Timer::Settle(id), host);
+    }
+
+    /// Applies a pane update to every tab it covers.
+    ///
+    /// A new cwd triggers a git lookup tagged with the tab position; panes
+    /// without a cwd are named through the `name_sources` chain right away.
+    fn apply_pane_update(&mut self, pane_manifest: PaneManifest, host: &mut impl Host) {
+        let mut positions: Vec<usize> = pane_manifest.panes.keys().copied().collect();
+        positions.sort_unstable();
+        for position in positions {
+            self.update_tab_panes(position, &pane_manifest.panes[&position], host);
+        }
+    }
+
+    /// Names one tab from its own focused pane.
+    fn update_tab_panes(&mut self, position: usize, panes: &[PaneInfo], host: &mut impl Host) {
+        let eligible: Vec<_> = panes.iter().filter(|p| !p.is_plugin).collect();
+
+        // A tab still being created can briefly have no terminal panes; leave
+        // its state uninitialized so nothing is renamed from partial data
+        let Some(pane) = eligible.iter().find(|p| p.is_focused) else {
+            return;
+        };
+        let untitled = eligible.len() == 1 && pane.title.trim().is_empty();
+        self.track_attention(position, &eligible, host);
+
+        let tab = self.tabs.entry(position).or_default();
+        // In focus_change mode, updates from the same focused pane are ignored
+        if self.config.rename_on == RenameOn::FocusChange && tab.focused_pane == Some(pane.id) {
+            return;
+        }
+        tab.focused_pane = Some(pane.id);
+        tab.title = pane.title.clone();
+        tab.command = pane.terminal_command.clone();
+        let layout = classify_layout(&eligible);
+        let relayout = self.config.show_layout && tab.layout != layout;
+        tab.layout = layout;
+
+        let parsed = parse_title(
+            &pane.title,
+            &self.config.title_trailing_separators,
+            &self.config.title_separators,
+        );
+        // The same path on another machine is another cwd
+        let moved_host = tab.ssh_host != parsed.host;
+        tab.ssh_host = parsed.host;
+
+        if let Some(cwd) = parsed.cwd {
+            tab.hold = None;
+            if tab.cwd.as_ref() != Some(&cwd) || moved_host {
+                let left_repo = tab.pane.take().and_then(|p| p.repo_root);
+                tab.cwd = Some(cwd.clone());
+                tab.manual = false;
+                tab.fresh = None;
+                tab.kube_context = None;
+                tab.icon = None;
+                tab.readme_title = None;
+                tab.package_name = None;
+                tab.deferred = None;
+                if self.needs_lookup(position, &cwd) {
+                    if self.config.single_rename {
+                        self.defer_rename(position, host);
+                    } else if self.config.show_busy || self.config.eager_folder {
+                        self.rename_interim(position, &cwd, host);
+                    }
+                }
+                self.look_up_cwd(position, &cwd, host);
+                if let Some(root) = left_repo {
+                    self.rerender_repo(&root, position, host);
+                }
+            } else if relayout {
+                if let Some(pane) = tab.pane.clone() {
+                    self.rename_from_sources(position, Some(pane), host);
+                }
+            }
+            // The session follows the active tab only
+            if position == self.active_tab {
+                self.sync_session_name(&cwd, host);
+            }
+        } else if untitled
+            && !self.config.new_pane_name.is_empty()
+            && tab_cwd_unknown(self, position)
+        {
+            // A brand-new tab whose only pane has not reported anything yet
+            let name = self.config.new_pane_name.clone();
+            self.rename(position, name, host);
+        } else if !self.hold_sticky_cwd(position, host) {
+            self.rename_from_title(position, host);
+        }
+    }
+
+    /// Flags a background tab once a command pane in it exits and clears the
+    /// flag when the tab is focused, re-applying its name either way.
+    ///
+    /// Switching tabs sends a pane update for the newly active tab, so the
+    /// flag clears as soon as the user looks at the tab.
+    fn track_attention(&mut self, position: usize, panes: &[&PaneInfo], host: &mut impl Host) {
+        if self.config.attention_symbol.is_empty() {
+            return;
+        }
+        let tab = self.tabs.entry(position).or_default();
+        let exited: Vec<u32> = panes.iter().filter(|p| p.exited).map(|p| p.id).collect();
+        let finished = exited.iter().any(|id| !tab.exited_panes.contains(id));
+        tab.exited_panes = exited;
+        let attention = position != self.active_tab && (tab.attention || finished);
+        if attention == tab.attention {
+            return;
+        }
+        tab.attention = attention;
+        if !tab.plain_name.is_empty() {
+            let name = tab.plain_name.clone();
+            self.rename(position, name, host);
+        }
+    }
+
+    /// Renames the session after the active tab's folder when
+    /// `rename_session` is on. Switching tabs sends a pane update for the
+    /// newly active tab, so this follows the active tab as it changes.
+    fn sync_session_name(&mut self, cwd: &str, host: &mut impl Host) {
+        if !self.config.rename_session {
+            return;
+        }
+        let pane = PaneContext {
+            cwd: cwd.to_string(),
+            ..Default::default()
+        };
+        let Some(name) = session_name(pane.folder_name()) else {
+            return;
+        };
+        if self.frozen {
+            self.pending_session_name = Some(name);
+        } else if self.session_name.as_ref() != Some(&name) {
+            host.rename_session(&name);
+            self.session_name = Some(name);
+        }
+    }
+
+    /// Applies a command piped to the plugin, e.g. with
+    /// `zellij pipe --name namey:freeze`. Unknown names are ignored.
+    ///
+    /// `namey:freeze` stops all renames while state keeps updating;
+    /// `namey:unfreeze` applies whatever names the tabs and session would have
+    /// been given in the meantime. `namey:inspect` returns a reply for the
+    /// pipe describing the active tab, see [`State::inspect`].
+    pub fn handle_pipe_message(&mut self, name: &str, host: &mut impl Host) -> Option<String> {
+        match name {
+            "namey:inspect" => return Some(self.inspect()),
+            "namey:freeze" => self.frozen = true,
+            "namey:unfreeze" if self.frozen => {
+                self.frozen = false;
+                let pending: Vec<(usize, String)> = self
+                    .tabs
+                    .iter_mut()
+                    .filter_map(|(position, tab)| Some((*position, tab.pending_name.take()?)))
+                    .collect();
+                for (position, name) in pending {
+                    self.rename(position, name, host);
+                }
+                if let Some(name) = self.pending_session_name.take() {
+                    if self.session_name.as_ref() != Some(&name) {
+                        host.rename_session(&name);
+                        self.session_name = Some(name);
+                    }
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Describes what the plugin made of the active tab, one `key=value` line
+    /// each: the raw pane title, the cwd extracted from it right now, the
+    /// name the `name_sources` chain resolves to and the branch last looked
+    /// up. Unknown values are empty.
+    pub fn inspect(&self) -> String {
+        let position = self.active_tab;
+        let tab = self.tabs.get(&position).cloned().unwrap_or_default();
+        let cwd = extract_cwd_from_title(
+            &tab.title,
+            &self.config.title_trailing_separators,
+            &self.config.title_separators,
+        );
+        let pane = tab
+            .pane
+            .clone()
+            .or_else(|| tab.cwd.clone().map(|cwd| self.base_pane(position, cwd)));
+        let branch = pane.as_ref().and_then(|p| p.branch.clone());
+        let folder = resolve_name(&self.config.name_sources, &self.name_inputs(position, pane));
+        format!(
+            "tab={}\ntitle={}\ncwd={}\nfolder={}\nbranch={}\n",
+            position,
+            tab.title,
+            cwd.unwrap_or_default(),
+            folder.unwrap_or_default(),
+            branch.unwrap_or_default(),
+        )
+    }
+
+    /// Runs the lookups that name a tab from `cwd`.
+    fn look_up_cwd(&mut self, position: usize, cwd: &str, host: &mut impl Host) {
+        // A remote cwd means nothing on this machine, so only its folder is used
+        if self.is_remote(position) {
+            let pane = self.base_pane(position, cwd.to_string());
+            self.rename_from_sources(position, Some(pane), host);
+            return;
+        }
+        if self.config.show_dir_freshness {
+            self.request_freshness(position, cwd, host);
+        }
+        if self.config.show_kube_context {
+            self.request_kube_context(position, cwd, host);
+        }
+        if self.config.show_icon && !self.config.project_icons.is_empty() {
+            self.request_project_icon(position, cwd, host);
+        }
+        if self.config.name_sources.contains(&NameSource::ReadmeTitle) {
+            self.request_readme_title(position, cwd, host);
+        }
+        if self.config.name_sources.contains(&NameSource::PackageName) {
+            self.request_package_name(position, cwd, host);
+        }
+        if self.config.needs_git() {
+            self.request_git_branch(position, cwd, host);
+        } else {
+            // Nothing needs git, so name from the folder and never spawn it
+            let pane = self.base_pane(position, cwd.to_string());
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// Handles a fired timer that ran for `elapsed` seconds.
+    ///
+    /// Zellij only reports how long a timer ran, so it is matched to the
+    /// pending timer whose duration is closest. Timers of equal duration fire
+    /// in the order they were set, which is the order they are queued in.
+    pub fn handle_timer(&mut self, elapsed: f64, host: &mut impl Host) {
+        let closest = (0..self.timers.len()).min_by(|a, b| {
+            let distance = |i: &usize| (self.timers[*i].0 - elapsed).abs();
+            distance(a).total_cmp(&distance(b))
+        });
+        let Some((_, timer)) = closest.and_then(|i| self.timers.remove(i)) else {
+            return;
+        };
+        match timer {
+            Timer::Hold(position, hold) => self.release_hold(position, hold, host),
+            Timer::Refresh => self.refresh_tabs(host),
+            Timer::ExpireLookup(id) => self.git_cache.retain(|_, entry| entry.timer_id != id),
+            Timer::Defer(position, id) => self.release_deferred(position, id, host),
+            Timer::Settle(id) => {
+                if let Some((_, panes)) = self.pending_panes.take_if(|(pending, _)| *pending == id)
+                {
+                    self.apply_pane_update(panes, host);
+                }
+            }
+        }
+    }
+
+    fn set_timer(&mut self, secs: f64, timer: Timer, host: &mut impl Host) {
+        self.timers.push_back((secs, timer));
+        host.set_timeout(secs);
+    }
+
+    /// Arms the next `name_ttl_secs` refresh, if enabled.
+    fn arm_refresh(&mut self, host: &mut impl Host) {
+        if self.config.name_ttl_secs > 0 {
+            self.set_timer(self.config.name_ttl_secs as f64, Timer::Refresh, host);
+        }
+    }
+
+    /// Looks up every tab with a known cwd again, so time-based parts of
+    /// the name never go stale, then arms the next refresh.
+    fn refresh_tabs(&mut self, host: &mut impl Host) {
+        self.git_cache.clear();
+        let cwds: Vec<(usize, String)> = self
+            .tabs
+            .iter()
+            .filter_map(|(position, tab)| Some((*position, tab.cwd.clone()?)))
+            .collect();
+        for (position, cwd) in cwds {
+            self.look_up_cwd(position, &cwd, host);
+        }
+        self.arm_refresh(host);
+    }
+
+    /// Releases a tab whose sticky-cwd timer fired, if it is still held.
+    ///
+    /// A hold that ended early, when a path title came back, is simply
+    /// dropped.
+    fn release_hold(&mut self, position: usize, hold: u64, host: &mut impl Host) {
+        let Some(tab) = self.tabs.get_mut(&position) else {
+            return;
+        };
+        if tab.hold != Some(hold) {
+            return;
+        }
+        tab.hold = None;
+        // Forget the cwd so the next path title names the tab afresh
+        tab.cwd = None;
+        self.rename_from_title(position, host);
+    }
+
+    /// Holds off naming a tab with a new cwd until its branch lookup returns,
+    /// for up to `single_rename_timeout_ms`.
+    fn defer_rename(&mut self, position: usize, host: &mut impl Host) {
+        let id = self.next_timer_id;
+        self.next_timer_id += 1;
+        self.tabs.entry(position).or_default().deferred = Some(id);
+        let secs = self.config.single_rename_timeout_ms as f64 / 1000.0;
+        self.set_timer(secs, Timer::Defer(position, id), host);
+    }
+
+    /// Names a tab whose branch lookup outlasted `single_rename_timeout_ms`
+    /// from its folder, as `eager_folder` would have straight away.
+    ///
+    /// A deferral whose lookup already returned is simply dropped.
+    fn release_deferred(&mut self, position: usize, id: u64, host: &mut impl Host) {
+        let Some(tab) = self.tabs.get_mut(&position) else {
+            return;
+        };
+        if tab.deferred != Some(id) {
+            return;
+        }
+        tab.deferred = None;
+        if let Some(cwd) = tab.cwd.clone() {
+            self.rename_interim(position, &cwd, host);
+        }
+    }
+
+    /// Keeps a tab's last cwd while its pane shows a non-path title, for up
+    /// to `sticky_cwd_ms`. Returns whether the tab is being held.
+    fn hold_sticky_cwd(&mut self, position: usize, host: &mut impl Host) -> bool {
+        if self.config.sticky_cwd_ms == 0 {
+            return false;
+        }
+        let tab = self.tabs.entry(position).or_default();
+        if tab.cwd.is_none() {
+            return false;
+        }
+        if tab.hold.is_none() {
+            let hold = self.next_timer_id;
+            tab.hold = Some(hold);
+            self.next_timer_id += 1;
+            let secs = self.config.sticky_cwd_ms as f64 / 1000.0;
+            self.set_timer(secs, Timer::Hold(position, hold), host);
+        }
+        true
+    }
+
+    /// Names the tab without a cwd, resetting it when nothing resolves.
+    fn rename_from_title(&mut self, position: usize, host: &mut impl Host) {
+        if !self.rename_from_sources(position, None, host) && tab_cwd_unknown(self, position) {
+            // Nothing resolved and there is no earlier cwd to fall back on
+            if let Some(name) = reset_name(self.config.reset_to, position) {
+                self.rename(position, name, host);
+            }
+        }
+    }
+
+    /// Applies a git lookup result to the tab that requested it.
+    ///
+    /// Results for other plugins' commands, and stale results for a cwd the
+    /// tab has since left or that is now on another machine, are ignored. A
+    /// failed branch lookup's `stderr`
+    /// tells a cwd outside any repository from other errors.
+    pub fn handle_command_result(
+        &mut self,
+        exit_code: Option<i32>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        context: BTreeMap<String, String>,
+        host: &mut impl Host,
+    ) {
+        if !is_our_command(&context) {
+            return;
+        }
+
+        let Some(path) = context.get("path").cloned() else {
+            return;
+        };
+        let position = context
+            .get("tab")
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(self.active_tab);
+
+        if self.tabs.get(&position).and_then(|t| t.cwd.as_ref()) != Some(&path)
+            || self.is_remote(position)
+        {
+            return;
+        }
+
+        match context.get("stage").map(String::as_str) {
+            Some("name") => {
+                self.apply_name_command_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("fresh") => {
+                self.apply_freshness_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("kube") => {
+                self.apply_kube_context_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("icon") => {
+                self.apply_project_icon_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("readme") => {
+                self.apply_readme_title_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("package") => {
+                self.apply_package_name_result(position, exit_code, &stdout, host);
+                return;
+            }
+            Some("detect") => {
+                self.apply_vcs_detect_result(position, path, exit_code, &stdout, host);
+                return;
+            }
+            _ => {}
+        }
+
+        let kind = context
+            .get("kind")
+            .and_then(|k| Vcs::parse(k))
+            .unwrap_or(Vcs::Git);
+        let (branch, repo_root) = if exit_code == Some(0) {
+            let branch = match kind {
+                Vcs::Jj => parse_jj_branch(&stdout),
+                Vcs::Git | Vcs::Hg => parse_git_branch(&stdout),
+            };
+            (branch, parse_git_toplevel(&stdout))
+        } else {
+            (None, None)
+        };
+        // Outside any VCS only if every system tried so far said so
+        let outside_vcs = branch.is_none()
+            && is_not_a_repo(kind, &String::from_utf8_lossy(&stderr))
+            && !context.contains_key("vcs_error");
+        if branch.is_none() {
+            if let Some(next) = self.config.next_vcs(kind) {
+                self.request_vcs_lookup(position, &path, next, !outside_vcs, host);
+                return;
+            }
+        }
+
+        // Status, upstream and origin are only looked up by the git script
+        let is_git = branch.is_some() && kind == Vcs::Git;
+        let output = String::from_utf8_lossy(&stdout);
+        let status = if is_git && self.config.needs_status() {
+            parse_porcelain_v2(&output).map(|status| GitStatus {
+                commit_age_days: parse_commit_age_days(&output),
+                ..status
+            })
+        } else {
+            None
+        };
+        let remote = if is_git && self.config.show_nonprimary_remote {
+            parse_upstream_remote(&output)
+        } else {
+            None
+        };
+        let forge = if is_git && self.config.show_forge {
+            parse_origin_url(&output).and_then(|url| forge_label(&url))
+        } else {
+            None
+        };
+        let base_ahead = if is_git && !self.config.base_branch.is_empty() {
+            parse_base_ahead(&output)
+        } else {
+            None
+        };
+
+        let lookup = CachedLookup {
+            timer_id: self.next_timer_id,
+            branch,
+            repo_root,
+            status,
+            remote,
+            forge,
+            base_ahead,
+            outside_vcs,
+        };
+        self.cache_lookup(&path, &lookup, host);
+        self.apply_lookup(position, path, lookup, host);
+    }
+
+    /// Remembers a finished lookup for `git_cache_ttl_ms`, if enabled.
+    fn cache_lookup(&mut self, path: &str, lookup: &CachedLookup, host: &mut impl Host) {
+        if self.config.git_cache_ttl_ms > 0 {
+            self.next_timer_id += 1;
+            self.git_cache.insert(path.to_string(), lookup.clone());
+            let secs = self.config.git_cache_ttl_ms as f64 / 1000.0;
+            self.set_timer(secs, Timer::ExpireLookup(lookup.timer_id), host);
+        }
+    }
+
+    /// Names a tab from a finished branch lookup, fresh or cached.
+    fn apply_lookup(
+        &mut self,
+        position: usize,
+        path: String,
+        lookup: CachedLookup,
+        host: &mut impl Host,
+    ) {
+        if let Some(tab) = self.tabs.get_mut(&position) {
+            tab.deferred = None;
+        }
+        let pane = PaneContext {
+            branch: lookup.branch,
+            repo_root: lookup.repo_root,
+            dirty: lookup.status.is_some_and(|s| s.is_dirty()),
+            status: lookup.status,
+            remote: lookup.remote,
+            forge: lookup.forge,
+            base_ahead: lookup.base_ahead,
+            outside_vcs: lookup.outside_vcs,
+            ..self.base_pane(position, path)
+        };
+        if self.config.name_command.is_empty() {
+            let root = pane.repo_root.clone();
+            self.rename_from_sources(position, Some(pane), host);
+            if let Some(root) = root {
+                self.rerender_repo(&root, position, host);
+            }
+        } else {
+            self.request_name_command(position, pane, host);
+        }
+    }
+
+    /// Runs `name_command` for a tab whose git lookup just finished.
+    ///
+    /// The context is kept on the tab so a failed or empty run can still fall
+    /// back to the regular name.
+    fn request_name_command(&mut self, position: usize, pane: PaneContext, host: &mut impl Host) {
+        let context = build_stage_context(&pane.cwd, position, "name");
+        let script = name_command_script(&self.config.name_command);
+        let git_path = expand_tilde(&pane.cwd, self.home_dir.as_deref());
+        let branch = pane.branch.clone().unwrap_or_default();
+        self.tabs.entry(position).or_default().pane = Some(pane);
+        host.run_command(&["bash", "-c", &script, "_", &git_path, &branch], context);
+    }
+
+    fn apply_name_command_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let name = (exit_code == Some(0))
+            .then(|| first_output_line(stdout))
+            .flatten();
+        match name {
+            Some(name) => self.rename(position, name, host),
+            None => {
+                let pane = self.tabs.get(&position).and_then(|t| t.pane.clone());
+                self.rename_from_sources(position, pane, host);
+            }
+        }
+    }
+
+    /// Runs the freshness check for a tab's new cwd, alongside any git lookup.
+    fn request_freshness(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        let context = build_stage_context(path, position, "fresh");
+        let dir = expand_tilde(path, self.home_dir.as_deref());
+        let minutes = self.config.fresh_minutes.to_string();
+        host.run_command(
+            &["bash", "-c", FRESHNESS_SCRIPT, "_", &dir, &minutes],
+            context,
+        );
+    }
+
+    /// Records the freshness check and re-renders the tab if it is already named.
+    fn apply_freshness_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let fresh = exit_code == Some(0) && first_output_line(stdout).is_some();
+        let tab = self.tabs.entry(position).or_default();
+        tab.fresh = Some(fresh);
+        if let Some(mut pane) = tab.pane.clone() {
+            pane.fresh = fresh;
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// Looks up the active kubernetes context, which names no repo and so
+    /// runs whether or not the cwd is one.
+    fn request_kube_context(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        let context = build_stage_context(path, position, "kube");
+        host.run_command(&["kubectl", "config", "current-context"], context);
+    }
+
+    /// Records the kubernetes context and re-renders the tab if it is already named.
+    fn apply_kube_context_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let kube_context = (exit_code == Some(0))
+            .then(|| first_output_line(stdout))
+            .flatten();
+        let tab = self.tabs.entry(position).or_default();
+        tab.kube_context = kube_context.clone();
+        if let Some(mut pane) = tab.pane.clone() {
+            pane.kube_context = kube_context;
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// Looks for the `project_icons` marker files in the cwd, which needs no
+    /// repo and so runs whether or not the cwd is one.
+    fn request_project_icon(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        let context = build_stage_context(path, position, "icon");
+        let dir = expand_tilde(path, self.home_dir.as_deref());
+        let mut args = vec!["bash", "-c", PROJECT_SCRIPT, "_", &dir];
+        args.extend(self.config.project_icons.iter().map(|(m, _)| m.as_str()));
+        host.run_command(&args, context);
+    }
+
+    /// Records the project icon and re-renders the tab if it is already named.
+    fn apply_project_icon_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let marker = (exit_code == Some(0))
+            .then(|| first_output_line(stdout))
+            .flatten();
+        let icon = marker.and_then(|marker| {
+            self.config
+                .project_icons
+                .iter()
+                .find(|(m, _)| *m == marker)
+                .map(|(_, icon)| icon.clone())
+        });
+        let tab = self.tabs.entry(position).or_default();
+        tab.icon = icon.clone();
+        if let Some(mut pane) = tab.pane.clone() {
+            pane.icon = icon;
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// Reads the README at the root of the repo holding the cwd, or in the
+    /// cwd itself, for the `readme_title` name source.
+    fn request_readme_title(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        let context = build_stage_context(path, position, "readme");
+        let dir = expand_tilde(path, self.home_dir.as_deref());
+        let mut args = vec![
+            "bash",
+            "-c",
+            README_SCRIPT,
+            "_",
+            &dir,
+            &self.config.git_binary,
+        ];
+        args.extend(self.config.git_extra_args.iter().map(String::as_str));
+        host.run_command(&args, context);
+    }
+
+    /// Records the README heading and re-renders the tab if it is already
+    /// named. A missing README or one without a heading leaves the name to
+    /// the rest of the `name_sources` chain.
+    fn apply_readme_title_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let readme_title = (exit_code == Some(0))
+            .then(|| parse_readme_title(&String::from_utf8_lossy(stdout)))
+            .flatten();
+        let tab = self.tabs.entry(position).or_default();
+        tab.readme_title = readme_title.clone();
+        if let Some(mut pane) = tab.pane.clone() {
+            pane.readme_title = readme_title;
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// Reads the cwd's `package.json` for the `package_name` name source.
+    fn request_package_name(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        let context = build_stage_context(path, position, "package");
+        let dir = expand_tilde(path, self.home_dir.as_deref());
+        host.run_command(&["bash", "-c", PACKAGE_SCRIPT, "_", &dir], context);
+    }
+
+    /// Records the package name and re-renders the tab if it is already
+    /// named. Without a `package.json` or a name in it, the folder or the
+    /// rest of the `name_sources` chain names the tab.
+    fn apply_package_name_result(
+        &mut self,
+        position: usize,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let package_name = (exit_code == Some(0))
+            .then(|| parse_package_name(&String::from_utf8_lossy(stdout)))
+            .flatten();
+        let tab = self.tabs.entry(position).or_default();
+        tab.package_name = package_name.clone();
+        if let Some(mut pane) = tab.pane.clone() {
+            pane.package_name = package_name;
+            self.rename_from_sources(position, Some(pane), host);
+        }
+    }
+
+    /// The context for `cwd` with what the tab's side lookups found so far.
+    fn base_pane(&self, position: usize, cwd: String) -> PaneContext {
+        let tab = self.tabs.get(&position);
+        PaneContext {
+            cwd,
+            fresh: tab.and_then(|t| t.fresh).unwrap_or(false),
+            kube_context: tab.and_then(|t| t.kube_context.clone()),
+            icon: tab.and_then(|t| t.icon.clone()),
+            readme_title: tab.and_then(|t| t.readme_title.clone()),
+            package_name: tab.and_then(|t| t.package_name.clone()),
+            ..Default::default()
+        }
+    }
+
+    fn name_inputs(&self, position: usize, pane: Option<PaneContext>) -> NameInputs {
+        let tab = self.tabs.get(&position).cloned().unwrap_or_default();
+        let title = if self.config.strip_emoji {
+            strip_emoji(&tab.title)
+        } else {
+            tab.title
+        };
+        NameInputs {
+            command: tab.command,
+            title: reduce_title(&title, self.config.title_command_mode),
+            mount_labels: self.config.mount_labels.clone(),
+            repo_subpath: pane.as_ref().and_then(|p| self.monorepo_subpath(p)),
+            folder_depth: self.config.folder_depth,
+            pane,
+        }
+    }
+
+    /// Returns the project family of `cwd` when `family_segment` is set.
+    fn family(&self, cwd: &str) -> Option<String> {
+        if self.config.family_segment == 0 {
+            return None;
+        }
+        let home = self.home_dir.as_deref();
+        let base = expand_tilde(&self.config.family_base, home);
+        family_name(&expand_tilde(cwd, home), &base, self.config.family_segment)
+    }
+
+    /// Returns the cwd's path within its repo when `monorepo_mode` is on and at
+    /// least `monorepo_min_tabs` tabs are in that repo.
+    fn monorepo_subpath(&self, pane: &PaneContext) -> Option<String> {
+        if !self.config.monorepo_mode {
+            return None;
+        }
+        let root = pane.repo_root.as_deref()?;
+        if self.tabs_in_repo(root).count() < self.config.monorepo_min_tabs {
+            return None;
+        }
+        let cwd = expand_tilde(&pane.cwd, self.home_dir.as_deref());
+        repo_subpath(&cwd, root)
+    }
+
+    fn tabs_in_repo<'a>(&'a self, root: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.tabs
+            .iter()
+            .filter(move |(_, t)| {
+                t.pane.as_ref().and_then(|p| p.repo_root.as_deref()) == Some(root)
+            })
+            .map(|(position, _)| *position)
+    }
+
+    /// Re-renders the other tabs in the repo at `root`, since a tab joining
+    /// or leaving it can switch `monorepo_mode` on or off for all of them.
+    fn rerender_repo(&mut self, root: &str, except: usize, host: &mut impl Host) {
+        // Names from name_command are used verbatim and never show the folder
+        if !self.config.monorepo_mode || !self.config.name_command.is_empty() {
+            return;
+        }
+        let positions: Vec<usize> = self.tabs_in_repo(root).filter(|p| *p != except).collect();
+        for position in positions {
+            let pane = self.tabs[&position].pane.clone();
+            self.rename_from_sources(position, pane, host);
+        }
+    }
+
+    /// Renames the tab from the name-source chain. Returns whether a name resolved.
+    fn rename_from_sources(
+        &mut self,
+        position: usize,
+        pane: Option<PaneContext>,
+        host: &mut impl Host,
+    ) -> bool {
+        let branch = pane.as_ref().and_then(|p| p.branch.clone());
+        let status = pane.as_ref().and_then(|p| p.status);
+        let remote = pane.as_ref().and_then(|p| p.remote.clone());
+        let fresh = pane.as_ref().is_some_and(|p| p.fresh);
+        let dirty = pane.as_ref().is_some_and(|p| p.dirty);
+        let outside_vcs = pane.as_ref().is_some_and(|p| p.outside_vcs);
+        let kube_context = pane.as_ref().and_then(|p| p.kube_context.clone());
+        let forge = pane.as_ref().and_then(|p| p.forge.clone());
+        let family = pane.as_ref().and_then(|p| self.family(&p.cwd));
+        let base_ahead = pane.as_ref().and_then(|p| p.base_ahead);
+        let server = pane
+            .as_ref()
+            .and_then(|p| p.unc_server().map(str::to_string));
+        let icon = pane.as_ref().and_then(|p| p.icon.clone());
+        let command = self.tabs.get(&position).and_then(|t| t.command.clone());
+        let layout = self
+            .tabs
+            .get(&position)
+            .map(|t| t.layout)
+            .unwrap_or_default();
+        self.tabs.entry(position).or_default().pane = pane.clone();
+        let inputs = self.name_inputs(position, pane);
+        let config = self.config_for(position);
+        let shell = shell_icon(command.as_deref(), &config.shell_icons);
+        // A machine-readable name is used exactly as formatted, badges and all
+        let keep = if config.machine_format.is_empty() {
+            let shows_branch = config.show_branch || !config.name_template.is_empty();
+            // Only badges that would actually render count against max_badges
+            let present: Vec<Badge> = [
+                (
+                    Badge::Status,
+                    shows_branch
+                        && branch.is_some()
+                        && (status.is_some_and(|s| !format_status(&s, &config).is_empty())
+                            || is_stale(status.as_ref(), &config)),
+                ),
+                (
+                    Badge::Dirty,
+                    dirty && config.show_dirty && shows_branch && branch.is_some(),
+                ),
+                (
+                    Badge::Kube,
+                    config.show_kube_context && kube_context.is_some(),
+                ),
+                (Badge::Forge, config.show_forge && forge.is_some()),
+                (Badge::Server, config.show_unc_server && server.is_some()),
+                (Badge::BaseAhead, base_ahead.is_some_and(|a| a > 0)),
+                (
+                    Badge::NonVcs,
+                    outside_vcs && !config.non_vcs_symbol.is_empty(),
+                ),
+                (
+                    Badge::Icon,
+                    config.show_icon && icon.as_deref().is_some_and(|i| !i.is_empty()),
+                ),
+                (
+                    Badge::Shell,
+                    config.show_shell_icon && shell.is_some_and(|i| !i.is_empty()),
+                ),
+                (
+                    Badge::Layout,
+                    config.show_layout && !layout.badge().is_empty(),
+                ),
+            ]
+            .into_iter()
+            .filter_map(|(badge, shown)| shown.then_some(badge))
+            .collect();
+            select_badges(&present, &config)
+        } else {
+            Badge::ALL.to_vec()
+        };
+        let keep = |badge| keep.contains(&badge);
+        if let Some(base) = resolve_name(&config.name_sources, &inputs) {
+            let name = format_tab_name_with_status(
+                &base,
+                branch.as_deref(),
+                status.as_ref().filter(|_| keep(Badge::Status)),
+                remote.as_deref(),
+                fresh,
+                dirty && keep(Badge::Dirty),
+                &config,
+            );
+            let new_name = if config.machine_format.is_empty() {
+                let name = with_non_vcs_symbol(&name, outside_vcs && keep(Badge::NonVcs), &config);
+                let base_ahead = base_ahead.filter(|_| keep(Badge::BaseAhead));
+                let name = with_base_ahead(&name, base_ahead);
+                let layout = if keep(Badge::Layout) {
+                    layout
+                } else {
+                    PaneLayout::Single
+                };
+                let name = with_layout(&name, layout, &config);
+                let icon = icon.as_deref().filter(|_| keep(Badge::Icon));
+                let name = with_project_icon(&name, icon, &config);
+                let name = with_shell_icon(&name, shell.filter(|_| keep(Badge::Shell)), &config);
+                let name = with_family_label(&name, family.as_deref(), &config);
+                let name = with_group_label(&name, position, &config);
+                let kube_context = kube_context.as_deref().filter(|_| keep(Badge::Kube));
+                let name = with_kube_context(&name, kube_context, &config);
+                let forge = forge.as_deref().filter(|_| keep(Badge::Forge));
+                let name = with_forge_label(&name, forge, &config);
+                let server = server.as_deref().filter(|_| keep(Badge::Server));
+                let name = with_unc_server(&name, server, &config);
+                with_tab_prefix(&name, &config)
+            } else {
+                name
+            };
+            self.rename(position, new_name, host);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the config to format the tab at `position` with, fitted to the
+    /// plugin's width and with `{index}` and the ssh `{host}` filled into
+    /// `name_template`.
+    fn config_for(&self, position: usize) -> Cow<'_, FormatterConfig> {
+        let mut config = self.config.for_width(self.cols);
+        if config.name_template.contains("{index}") {
+            config.to_mut().name_template = template_for_tab(&config.name_template, position);
+        }
+        let ssh_host = self.tabs.get(&position).and_then(|t| t.ssh_host.as_deref());
+        if let Some(host) = ssh_host.filter(|_| config.name_template.contains("{host}")) {
+            config.to_mut().name_template = config.name_template.replace("{host}", host);
+        }
+        config
+    }
+
+    /// Names the tab from its folder while the branch lookup runs, marked
+    /// with `busy_symbol` when `show_busy` is on.
+    fn rename_interim(&mut self, position: usize, cwd: &str, host: &mut impl Host) {
+        let pane = PaneContext {
+            cwd: cwd.to_string(),
+            ..Default::default()
+        };
+        let inputs = self.name_inputs(position, Some(pane));
+        let config = self.config_for(position);
+        if let Some(base) = resolve_name(&config.name_sources, &inputs) {
+            let interim = if config.show_busy {
+                format_busy_name(&base, &config)
+            } else {
+                format_tab_name(&base, None, &config)
+            };
+            let interim = with_family_label(&interim, self.family(cwd).as_deref(), &config);
+            let interim = with_group_label(&interim, position, &config);
+            self.rename(position, with_tab_prefix(&interim, &config), host);
+        }
+    }
+
+    /// Renames the tab if the name changed, remembering what was set so a
+    /// later rename is never skipped when it arrives before the next TabUpdate.
+    fn rename(&mut self, position: usize, name: String, host: &mut impl Host) {
+        let tab = self.tabs.entry(position).or_default();
+        tab.plain_name = name.clone();
+        // Panes running a blocklisted program keep whatever name the tab had
+        if self.config.skips_command(tab.command.as_deref()) {
+            return;
+        }
+        // A tab the user renamed keeps that name until its cwd changes
+        if tab.manual {
+            return;
+        }
+        // While frozen, remember the name so unfreezing can catch up
+        if self.frozen {
+            tab.pending_name = Some(name);
+            return;
+        }
+        // A machine-readable name is used exactly as formatted
+        let name = if self.config.machine_format.is_empty() {
+            with_attention(&name, tab.attention, &self.config)
+        } else {
+            name
+        };
+        if tab.name != name {
+            host.rename_tab(position as u32, &name);
+            let replaced = std::mem::replace(&mut tab.name, name.clone());
+            for known in [replaced, name] {
+                if !tab.recent_names.contains(&known) {
+                    tab.recent_names.push_back(known);
+                }
+            }
+            while tab.recent_names.len() > RECENT_NAMES {
+                tab.recent_names.pop_front();
+            }
+        }
+    }
+
+    /// Starts the branch lookup with the first system in `vcs_order`.
+    ///
+    /// Paths under `slow_repos` skip the lookup and are named from the
+    /// folder alone.
+    fn request_git_branch(&mut self, position: usize, path: &str, host: &mut impl Host) {
+        if self.is_slow_repo(path) {
+            let pane = self.base_pane(position, path.to_string());
+            self.rename_from_sources(position, Some(pane), host);
+            return;
+        }
+        if let Some(lookup) = self.git_cache.get(path).cloned() {
+            self.apply_lookup(position, path.to_string(), lookup, host);
+            return;
+        }
+        if self.config.detect_vcs {
+            let context = build_stage_context(path, position, "detect");
+            let dir = expand_tilde(path, self.home_dir.as_deref());
+            host.run_command(&["bash", "-c", VCS_DETECT_SCRIPT, "_", &dir], context);
+            return;
+        }
+        let vcs = self.config.vcs_order.first().copied().unwrap_or(Vcs::Git);
+        self.request_vcs_lookup(position, path, vcs, false, host);
+    }
+
+    /// Looks the branch up with the system `vcs=auto` found, or names the tab
+    /// from its folder, as outside any VCS, when the probe found none.
+    fn apply_vcs_detect_result(
+        &mut self,
+        position: usize,
+        path: String,
+        exit_code: Option<i32>,
+        stdout: &[u8],
+        host: &mut impl Host,
+    ) {
+        let vcs = (exit_code == Some(0))
+            .then(|| first_output_line(stdout))
+            .flatten()
+            .and_then(|v| Vcs::parse(&v));
+        match vcs {
+            Some(vcs) => self.request_vcs_lookup(position, &path, vcs, false, host),
+            None => {
+                let lookup = CachedLookup {
+                    timer_id: self.next_timer_id,
+                    branch: None,
+                    repo_root: None,
+                    status: None,
+                    remote: None,
+                    forge: None,
+                    base_ahead: None,
+                    outside_vcs: true,
+                };
+                self.cache_lookup(&path, &lookup, host);
+                self.apply_lookup(position, path, lookup, host);
+            }
+        }
+    }
+
+    /// Returns whether naming the tab at `position` in `path` has to wait for
+    /// a branch lookup.
+    fn needs_lookup(&self, position: usize, path: &str) -> bool {
+        self.config.needs_git()
+            && !self.is_remote(position)
+            && !self.is_slow_repo(path)
+            && !self.git_cache.contains_key(path)
+    }
+
+    /// Returns whether the tab at `position` shows a cwd on another machine.
+    fn is_remote(&self, position: usize) -> bool {
+        self.tabs
+            .get(&position)
+            .is_some_and(|t| t.ssh_host.is_some())
+    }
+
+    /// Returns whether `path` is at or below one of the `slow_repos`.
+    fn is_slow_repo(&self, path: &str) -> bool {
+        let home = self.home_dir.as_deref();
+        let path = expand_tilde(path, home);
+        self.config
+            .slow_repos
+            .iter()
+            .any(|repo| is_within(&path, &expand_tilde(repo, home)))
+    }
+
+    /// Looks up the branch with `vcs`, tagging the command with its `kind` so
+    /// a failure can fall through to the next system. `vcs_error` records that
+    /// an earlier system failed inside a repository, so the cwd is not
+    /// reported as outside any VCS.
+    fn request_vcs_lookup(
+        &mut self,
+        position: usize,
+        path: &str,
+        vcs: Vcs,
+        vcs_error: bool,
+        host: &mut impl Host,
+    ) {
+        let mut context = build_command_context(path, position);
+        context.insert("kind".to_string(), vcs.name().to_string());
+        if vcs_error {
+            context.insert("vcs_error".to_string(), "true".to_string());
+        }
+        // bash does not expand a quoted `~`, so hand the VCS an absolute path
+        let vcs_path = expand_tilde(path, self.home_dir.as_deref());
+        let custom = vcs == Vcs::Git && !self.config.git_branch_command.is_empty();
+        let script = match vcs {
+            Vcs::Git if custom => self.config.git_branch_command.clone(),
+            Vcs::Git => git_script(
+                self.config.needs_status(),
+                self.config.show_nonprimary_remote,
+                self.config.show_forge,
+                !self.config.base_branch.is_empty(),
+                self.config.stale_days > 0,
+            ),
+            Vcs::Hg => HG_BRANCH_SCRIPT.to_string(),
+            Vcs::Jj => JJ_BRANCH_SCRIPT.to_string(),
+        };
+        let mut args = vec!["bash", "-c", &script, "_", &vcs_path];
+        // A custom command only gets the path
+        if vcs == Vcs::Git && !custom {
+            if !self.config.base_branch.is_empty() {
+                args.push(&self.config.base_branch);
+            }
+            args.push(&self.config.git_binary);
+            args.extend(self.config.git_extra_args.iter().map(String::as_str));
+        }
+        host.run_command(&args, context);
+    }
+}
+
+/// Classifies how a tab's terminal panes are arranged.
+///
+/// Suppressed panes are ignored. Zellij reports no stacks as such, but a pane
+/// collapsed into a stack is a single row tall.
+fn classify_layout(panes: &[&PaneInfo]) -> PaneLayout {
+    let shown = panes.iter().filter(|p| !p.is_suppressed);
+    let (floating, tiled): (Vec<&&PaneInfo>, Vec<_>) = shown.partition(|p| p.is_floating);
+    if floating.len() > tiled.len() {
+        PaneLayout::Floating
+    } else if tiled.len() < 2 {
+        PaneLayout::Single
+    } else if tiled.iter().any(|p| p.pane_rows == 1) {
+        PaneLayout::Stacked
+    } else {
+        PaneLayout::Split
+    }
+}
+
+/// Builds the git lookup script for the details the configuration needs.
+///
+/// The branch lookup decides whether the path is a repo. Any extra lookups
+/// after it are best-effort and never fail the script once the branch is known.
+///
+/// With `base`, the base branch comes as `$2`, ahead of the git command, and
+/// is moved out of the arguments before anything else runs.
+fn git_script(status: bool, upstream: bool, origin: bool, base: bool, age: bool) -> String {
+    if !status && !upstream && !origin && !base && !age {
+        return GIT_BRANCH_SCRIPT.to_string();
+    }
+
+    let mut lines = Vec::new();
+    if base {
+        lines.push(r#"base="$2"; set -- "$1" "${@:3}""#.to_string());
+    }
+    lines.push(format!("{} || exit 1", GIT_BRANCH_SCRIPT));
+    if upstream {
+        lines.push(GIT_UPSTREAM_LINE.to_string());
+    }
+    if origin {
+        lines.push(GIT_ORIGIN_LINE.to_string());
+    }
+    if base {
+        lines.push(GIT_BASE_LINE.to_string());
+    }
+    if age {
+        lines.push(GIT_AGE_LINE.to_string());
+    }
+    if status {
+        lines.push(GIT_STATUS_LINE.to_string());
+    }
+    lines.push("exit 0".to_string());
+    lines.join("\n")
+}
+
+/// Turns a `name_command` template into a script taking the path as `$1` and
+/// the branch as `$2`.
+///
+/// Values are passed as arguments rather than pasted into the script so paths
+/// and branch names can never inject shell syntax.
+fn name_command_script(template: &str) -> String {
+    template
+        .replace("{path}", r#""$1""#)
+        .replace("{branch}", r#""$2""#)
+}
+
+/// Returns the first line of command output with control characters removed,
+/// or `None` if it is blank.
+fn first_output_line(stdout: &[u8]) -> Option<String> {
+    let output = String::from_utf8_lossy(stdout);
+    let line: String = output
+        .lines()
+        .next()?
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect();
+    let line = line.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+fn tab_cwd_unknown(state: &State, position: usize) -> bool {
+    state.tabs.get(&position).is_none_or(|t| t.cwd.is_none())
+}
+
+/// A simplified Zellij event for driving [`State`] end to end in tests.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub enum TestEvent {
+    /// Tabs named `names` exist, with `active` focused.
+    TabUpdate { active: usize, names: Vec<String> },
+    /// The focused pane of tab `tab` now has `title`.
+    PaneUpdate { tab: usize, title: String },
+    /// The git lookup for `path` in tab `tab` finished with this output.
+    GitResult {
+        tab: usize,
+        path: String,
+        exit_code: i32,
+        stdout: String,
+    },
+    /// A timer set through [`Scheduler`] fired after this many seconds.
+    Timer(f64),
+}
+
+#[cfg(test)]
+impl State {
+    /// Feeds `event` through the same handlers `update` dispatches to.
+    pub fn apply_event(&mut self, event: TestEvent, host: &mut impl Host) {
+        use std::collections::HashMap;
+
+        match event {
+            TestEvent::TabUpdate { active, names } => {
+                let tab_info: Vec<TabInfo> = names
+                    .into_iter()
+                    .enumerate()
+                    .map(|(position, name)| TabInfo {
+                        position,
+                        name,
+                        active: position == active,
+                        ..Default::default()
+                    })
+                    .collect();
+                self.handle_tab_update(&tab_info);
+            }
+            TestEvent::PaneUpdate { tab, title } => {
+                let pane = PaneInfo {
+                    is_focused: true,
+                    title,
+                    ..Default::default()
+                };
+                let manifest = PaneManifest {
+                    panes: HashMap::from([(tab, vec![pane])]),
+                };
+                self.handle_pane_update(manifest, host);
+            }
+            TestEvent::GitResult {
+                tab,
+                path,
+                exit_code,
+                stdout,
+            } => {
+                let context = build_command_context(&path, tab);
+                self.handle_command_result(
+                    Some(exit_code),
+                    stdout.into_bytes(),
+                    Vec::new(),
+                    context,
+                    host,
+                );
+            }
+            TestEvent::Timer(elapsed) => self.handle_timer(elapsed, host),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Records every side effect instead of talking to Zellij.
+    #[derive(Default)]
+    struct FakeHost {
+        renames: Vec<(u32, String)>,
+        sessions: Vec<String>,
+        commands: Vec<(Vec<String>, BTreeMap<String, String>)>,
+        timeouts: Vec<f64>,
+    }
+
+    impl Renamer for FakeHost {
+        fn rename_tab(&mut self, position: u32, name: &str) {
+            self.renames.push((position, name.to_string()));
+        }
+
+        fn rename_session(&mut self, name: &str) {
+            self.sessions.push(name.to_string());
+        }
+    }
+
+    impl CommandRunner for FakeHost {
+        fn run_command(&mut self, args: &[&str], context: BTreeMap<String, String>) {
+            let args = args.iter().map(|a| a.to_string()).collect();
+            self.commands.push((args, context));
+        }
+    }
+
+    impl Scheduler for FakeHost {
+        fn set_timeout(&mut self, secs: f64) {
+            self.timeouts.push(secs);
+        }
+    }
+
+    fn tabs(active: usize, count: usize) -> Vec<TabInfo> {
+        (0..count)
+            .map(|position| TabInfo {
+                position,
+                name: format!("Tab #{}", position + 1),
+                active: position == active,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn manifest(position: usize, title: &str) -> PaneManifest {
+        let pane = PaneInfo {
+            is_focused: true,
+            title: title.to_string(),
+            ..Default::default()
+        };
+        PaneManifest {
+            panes: HashMap::from([(position, vec![pane])]),
+        }
+    }
+
+    fn git_ok(
+        state: &mut State,
+        context: &BTreeMap<String, String>,
+        out: &str,
+        host: &mut FakeHost,
+    ) {
+        state.handle_command_result(
+            Some(0),
+            out.as_bytes().to_vec(),
+            Vec::new(),
+            context.clone(),
+            host,
+        );
+    }
+
+    #[test]
+    fn test_pane_update_requests_git_with_tab_context() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "zsh: /home/me/proj"), &mut host);
+
+        assert_eq!(host.commands.len(), 1);
+        let (args, context) = &host.commands[0];
+        assert_eq!(args[4], "/home/me/proj");
+        assert_eq!(
+            context.get("path").map(String::as_str),
+            Some("/home/me/proj")
+        );
+        assert_eq!(context.get("tab").map(String::as_str), Some("0"));
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_interleaved_tabs_results_out_of_order() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+        assert_eq!(host.commands.len(), 2);
+
+        // Tab 1's result lands first, then tab 0's
+        let api = host.commands[0].1.clone();
+        let web = host.commands[1].1.clone();
+        git_ok(&mut state, &web, "dev\n/work/web\n", &mut host);
+        git_ok(&mut state, &api, "next\n/work/api\n", &mut host);
+
+        assert_eq!(
+            host.renames,
+            vec![(1, "web:dev".to_string()), (0, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_stale_result_for_left_cwd_is_ignored() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/old"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/new"), &mut host);
+
+        let old = host.commands[0].1.clone();
+        let new = host.commands[1].1.clone();
+        git_ok(&mut state, &new, "next\n/work/new\n", &mut host);
+        git_ok(&mut state, &old, "next\n/work/old\n", &mut host);
+
+        assert_eq!(host.renames, vec![(0, "new:next".to_string())]);
+    }
+
+    #[test]
+    fn test_same_cwd_in_two_tabs_names_both() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/api"), &mut host);
+
+        assert_eq!(host.commands.len(), 2);
+        for (_, context) in host.commands.clone() {
+            git_ok(&mut state, &context, "next\n/work/api\n", &mut host);
+        }
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next".to_string()), (1, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_cwd_does_not_requery() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 1);
+    }
+
+    #[test]
+    fn test_result_without_tab_uses_active_tab() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let mut context = host.commands[0].1.clone();
+        context.remove("tab");
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    #[test]
+    fn test_foreign_and_incomplete_results_ignored() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let foreign = BTreeMap::from([("source".to_string(), "other".to_string())]);
+        git_ok(&mut state, &foreign, "next\n", &mut host);
+        let no_path = BTreeMap::from([("source".to_string(), "namey".to_string())]);
+        git_ok(&mut state, &no_path, "next\n", &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_failed_git_names_folder_only() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+
+        let context = host.commands[0].1.clone();
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), context, &mut host);
+        assert_eq!(host.renames, vec![(0, "scratch".to_string())]);
+    }
+
+    #[test]
+    fn test_change_counts_use_status_script() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_change_counts: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, context) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(true, false, false, false, false));
+        let out =
+            "next\n/work/api\n# branch.oid abc123\n1 M. N... 100644 100644 100644 a b src/lib.rs\n";
+        git_ok(&mut state, &context, out, &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next +1".to_string())]);
+    }
+
+    #[test]
+    fn test_show_clean_marks_clean_repo_only() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_clean: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/tmp/scratch"), &mut host);
+
+        let (args, repo) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(true, false, false, false, false));
+        let plain = host.commands[1].1.clone();
+        let out = "next\n/work/api\n# branch.oid abc123\n";
+        git_ok(&mut state, &repo, out, &mut host);
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), plain, &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next ✓".to_string()), (1, "scratch".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_failed_status_keeps_branch_without_status() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_clean: true,
+                show_dirty: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        // The branch lookup succeeded but `git status` printed nothing
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n/work/api\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    #[test]
+    fn test_show_dirty_marks_dirty_repo_only() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_dirty: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+
+        let (args, dirty) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(true, false, false, false, false));
+        let clean = host.commands[1].1.clone();
+        let out =
+            "next\n/work/api\n# branch.oid abc123\n1 .M N... 100644 100644 100644 a b src/lib.rs\n";
+        git_ok(&mut state, &dirty, out, &mut host);
+        git_ok(
+            &mut state,
+            &clean,
+            "next\n/work/web\n# branch.oid abc123\n",
+            &mut host,
+        );
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next*".to_string()), (1, "web:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_machine_format_skips_decorations() {
+        let mut state = State {
+            config: FormatterConfig {
+                machine_format: "{folder}|{branch}|{dirty}".to_string(),
+                tab_prefix: "work/".to_string(),
+                group_size: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/my_project_name"), &mut host);
+        let context = host.commands[0].1.clone();
+        let out = "feature/login\n/work/my_project_name\n# branch.oid abc123\n";
+        git_ok(&mut state, &context, out, &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "my_project_name|feature/login|0".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_slow_repos_skip_branch_lookup() {
+        let mut state = State {
+            config: FormatterConfig {
+                slow_repos: vec!["~/mono".to_string()],
+                show_busy: true,
+                ..Default::default()
+            },
+            home_dir: Some("/home/me".to_string()),
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 3));
+        state.handle_pane_update(manifest(0, "/home/me/mono/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 3));
+        state.handle_pane_update(manifest(1, "~/mono"), &mut host);
+        assert!(host.commands.is_empty());
+        assert_eq!(
+            host.renames,
+            vec![(0, "api".to_string()), (1, "mono".to_string())]
+        );
+
+        // Other repos, including ones sharing the name prefix, still look up
+        state.handle_tab_update(&tabs(2, 3));
+        state.handle_pane_update(manifest(2, "/home/me/monolith"), &mut host);
+        assert_eq!(host.commands.len(), 1);
+        assert_eq!(host.commands[0].1["path"], "/home/me/monolith");
+    }
+
+    #[test]
+    fn test_home_dir_expands_tilde_for_git_only() {
+        let mut state = State {
+            home_dir: Some("/home/me".to_string()),
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "zsh: ~/proj"), &mut host);
+
+        let (args, context) = &host.commands[0];
+        assert_eq!(args[4], "/home/me/proj");
+        assert_eq!(context.get("path").map(String::as_str), Some("~/proj"));
+    }
+
+    #[test]
+    fn test_busy_then_final_rename() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_busy: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        // The folder-only name shows while the lookup is still running
+        assert_eq!(host.renames, vec![(0, "api⋯".to_string())]);
+        assert_eq!(host.commands.len(), 1);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n/work/api\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api⋯".to_string()), (0, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_show_branch_false_spawns_no_command() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_branch: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        assert!(host.commands.is_empty());
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+    }
+
+    #[test]
+    fn test_title_without_cwd_names_from_title() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+
+        assert!(host.commands.is_empty());
+        assert_eq!(host.renames, vec![(0, "htop".to_string())]);
+    }
+
+    #[test]
+    fn test_strip_emoji_from_title_name() {
+        let mut state = State {
+            config: FormatterConfig {
+                strip_emoji: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "🚀 deploy"), &mut host);
+
+        assert_eq!(host.renames, vec![(0, "deploy".to_string())]);
+    }
+
+    #[test]
+    fn test_rename_skipped_when_name_already_matches() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        let mut info = tabs(0, 1);
+        info[0].name = "htop".to_string();
+        state.handle_tab_update(&info);
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_reset_when_nothing_resolves() {
+        let mut state = State {
+            config: FormatterConfig {
+                reset_to: crate::formatter::ResetMode::Index,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "  "), &mut host);
+        assert_eq!(host.renames, vec![(1, "Tab 2".to_string())]);
+    }
+
+    #[test]
+    fn test_no_reset_when_tab_had_cwd_or_mode_keep() {
+        let mut state = State {
+            config: FormatterConfig {
+                reset_to: crate::formatter::ResetMode::Index,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, ""), &mut host);
+        assert!(host.renames.is_empty());
+
+        let mut state = State::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, ""), &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_pane_update_renames_background_tab() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        // Only tab 0 has a focused pane; tab 1 is active
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 1);
+        let context = host.commands[0].1.clone();
+        assert_eq!(context.get("tab").map(String::as_str), Some("0"));
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    #[test]
+    fn test_pane_update_names_each_tab_from_its_own_pane() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        let mut panes = manifest(0, "/work/api");
+        panes.panes.extend(manifest(1, "/work/web").panes);
+        state.handle_pane_update(panes, &mut host);
+
+        assert_eq!(host.commands.len(), 2);
+        let contexts: Vec<_> = host.commands.iter().map(|(_, c)| c.clone()).collect();
+        assert_eq!(
+            contexts[0].get("path").map(String::as_str),
+            Some("/work/api")
+        );
+        assert_eq!(
+            contexts[1].get("path").map(String::as_str),
+            Some("/work/web")
+        );
+        git_ok(&mut state, &contexts[1], "dev\n", &mut host);
+        git_ok(&mut state, &contexts[0], "next\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(1, "web:dev".to_string()), (0, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_closed_tabs_are_dropped() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(0, 1));
+
+        // The pending result targets a tab that no longer exists
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_closing_a_middle_tab_shifts_later_tabs_down() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 3));
+        for (position, path) in ["/work/api", "/work/web", "/work/db"].iter().enumerate() {
+            state.handle_pane_update(manifest(position, path), &mut host);
+            let context = host.commands[position].1.clone();
+            git_ok(
+                &mut state,
+                &context,
+                &format!("next\n{}\n", path),
+                &mut host,
+            );
+        }
+
+        let remaining: Vec<TabInfo> = [(0, "api:next"), (1, "db:next")]
+            .iter()
+            .map(|(position, name)| TabInfo {
+                position: *position,
+                name: name.to_string(),
+                active: *position == 0,
+                ..Default::default()
+            })
+            .collect();
+        state.handle_tab_update(&remaining);
+
+        // The last tab keeps its own state at its new position
+        assert_eq!(state.tabs[&0].cwd.as_deref(), Some("/work/api"));
+        let db = &state.tabs[&1];
+        assert_eq!(db.cwd.as_deref(), Some("/work/db"));
+        assert!(!db.manual);
+        assert_eq!(state.tabs.len(), 2);
+        let commands = host.commands.len();
+        state.handle_pane_update(manifest(1, "/work/db"), &mut host);
+        assert_eq!(host.commands.len(), commands);
+        state.handle_pane_update(manifest(1, "/work/ops"), &mut host);
+        let context = host.commands[commands].1.clone();
+        git_ok(&mut state, &context, "next\n/work/ops\n", &mut host);
+        assert_eq!(host.renames.last(), Some(&(1, "ops:next".to_string())));
+    }
+
+    #[test]
+    fn test_tab_without_panes_is_never_renamed() {
+        let mut state = State {
+            config: FormatterConfig {
+                reset_to: crate::formatter::ResetMode::Index,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+
+        let empty = PaneManifest {
+            panes: HashMap::from([(0, Vec::new())]),
+        };
+        state.handle_pane_update(empty, &mut host);
+        let plugin_only = PaneManifest {
+            panes: HashMap::from([(
+                0,
+                vec![PaneInfo {
+                    is_plugin: true,
+                    is_focused: true,
+                    title: "/work/api".to_string(),
+                    ..Default::default()
+                }],
+            )]),
+        };
+        state.handle_pane_update(plugin_only, &mut host);
+
+        assert!(host.renames.is_empty());
+        assert!(host.commands.is_empty());
+        let tab = &state.tabs[&0];
+        assert!(tab.cwd.is_none());
+        assert!(tab.title.is_empty());
+    }
+
+    #[test]
+    fn test_tab_without_focused_pane_is_left_alone() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        let unfocused = PaneManifest {
+            panes: HashMap::from([(
+                0,
+                vec![PaneInfo {
+                    title: "/work/api".to_string(),
+                    ..Default::default()
+                }],
+            )]),
+        };
+        state.handle_pane_update(unfocused, &mut host);
+        assert!(host.commands.is_empty());
+        assert!(state.tabs[&0].cwd.is_none());
+    }
+
+    fn manifest_with_command(position: usize, title: &str, command: &str) -> PaneManifest {
+        let mut manifest = manifest(position, title);
+        manifest.panes.get_mut(&position).unwrap()[0].terminal_command = Some(command.to_string());
+        manifest
+    }
+
+    #[test]
+    fn test_skip_commands_keeps_prior_name() {
+        let mut state = State {
+            config: FormatterConfig {
+                skip_commands: vec!["htop".to_string()],
+                reset_to: crate::formatter::ResetMode::Index,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(
+            manifest_with_command(0, "htop", "/usr/bin/htop -d 10"),
+            &mut host,
+        );
+        // The lookup issued before htop started lands while it runs
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_skip_commands_other_command_proceeds() {
+        let mut state = State {
+            config: FormatterConfig {
+                skip_commands: vec!["htop".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest_with_command(0, "vim", "vim notes.md"), &mut host);
+        assert_eq!(host.renames, vec![(0, "vim".to_string())]);
+    }
+
+    #[test]
+    fn test_git_lookup_uses_default_git() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(&host.commands[0].0[3..], ["_", "/work/api", "git"]);
+    }
+
+    #[test]
+    fn test_git_lookup_uses_custom_binary_and_args() {
+        let mut state = State {
+            config: FormatterConfig {
+                git_binary: "/opt/git/bin/git".to_string(),
+                git_extra_args: vec!["-c".to_string(), "safe.directory=*".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(
+            &host.commands[0].0[3..],
+            [
+                "_",
+                "/work/api",
+                "/opt/git/bin/git",
+                "-c",
+                "safe.directory=*"
+            ]
+        );
+    }
+
+    // ==================== vcs_order Tests ====================
+
+    fn vcs_state(order: &str) -> State {
+        State {
+            config: FormatterConfig {
+                vcs_order: Vcs::parse_list(order),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_vcs_order_falls_through_to_second_vcs() {
+        let mut state = vcs_state("git,hg,jj");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/legacy"), &mut host);
+
+        let git = host.commands[0].1.clone();
+        assert_eq!(git.get("kind").map(String::as_str), Some("git"));
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), git, &mut host);
+        assert!(host.renames.is_empty());
+
+        let (args, hg) = host.commands[1].clone();
+        assert_eq!(hg.get("kind").map(String::as_str), Some("hg"));
+        assert_eq!(&args[2..], [HG_BRANCH_SCRIPT, "_", "/work/legacy"]);
+        git_ok(&mut state, &hg, "dev\n/work/legacy\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "legacy:dev".to_string())]);
+        assert_eq!(host.commands.len(), 2);
+    }
+
+    #[test]
+    fn test_vcs_order_stops_at_first_success() {
+        let mut state = vcs_state("jj,git");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, jj) = host.commands[0].clone();
+        assert_eq!(args[2], JJ_BRANCH_SCRIPT);
+        git_ok(&mut state, &jj, "trunk\n/work/api\n", &mut host);
+        assert_eq!(host.commands.len(), 1);
+        assert_eq!(host.renames, vec![(0, "api:trunk".to_string())]);
+    }
+
+    #[test]
+    fn test_jj_change_id_without_bookmark() {
+        let mut state = vcs_state("jj");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let jj = host.commands[0].1.clone();
+        assert_eq!(jj.get("kind").map(String::as_str), Some("jj"));
+        git_ok(&mut state, &jj, "\n/work/api\nkxqpz\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:kxqpz".to_string())]);
+    }
+
+    fn detect_state() -> State {
+        State {
+            config: FormatterConfig {
+                detect_vcs: true,
+                non_vcs_symbol: " ∅".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_vcs_auto_detects_hg() {
+        let mut state = detect_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/legacy"), &mut host);
+
+        let (args, detect) = host.commands[0].clone();
+        assert_eq!(args, ["bash", "-c", VCS_DETECT_SCRIPT, "_", "/work/legacy"]);
+        assert_eq!(detect.get("stage").map(String::as_str), Some("detect"));
+        git_ok(&mut state, &detect, "hg\n", &mut host);
+        assert!(host.renames.is_empty());
+
+        let (args, hg) = host.commands[1].clone();
+        assert_eq!(args[2], HG_BRANCH_SCRIPT);
+        assert_eq!(hg.get("kind").map(String::as_str), Some("hg"));
+        git_ok(&mut state, &hg, "default\n/work/legacy\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "legacy:defau".to_string())]);
+    }
+
+    #[test]
+    fn test_vcs_auto_without_repo_names_folder() {
+        let mut state = detect_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+        let detect = host.commands[0].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), detect, &mut host);
+        assert_eq!(host.commands.len(), 1);
+        assert_eq!(host.renames, vec![(0, "scratch ∅".to_string())]);
+    }
+
+    #[test]
+    fn test_vcs_order_exhausted_names_folder_only() {
+        let mut state = vcs_state("git,hg");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+        let git = host.commands[0].1.clone();
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), git, &mut host);
+        let hg = host.commands[1].1.clone();
+        state.handle_command_result(Some(255), Vec::new(), Vec::new(), hg, &mut host);
+        assert_eq!(host.renames, vec![(0, "scratch".to_string())]);
+        assert_eq!(host.commands.len(), 2);
+    }
+
+    // ==================== non_vcs_symbol Tests ====================
+
+    const NOT_A_REPO: &str =
+        "fatal: not a git repository (or any of the parent directories): .git\n";
+
+    fn non_vcs_state(order: &str) -> State {
+        State {
+            config: FormatterConfig {
+                non_vcs_symbol: " ∅".to_string(),
+                vcs_order: Vcs::parse_list(order),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn lookup_failed(
+        state: &mut State,
+        context: &BTreeMap<String, String>,
+        exit_code: i32,
+        stderr: &str,
+        host: &mut FakeHost,
+    ) {
+        state.handle_command_result(
+            Some(exit_code),
+            Vec::new(),
+            stderr.as_bytes().to_vec(),
+            context.clone(),
+            host,
+        );
+    }
+
+    #[test]
+    fn test_non_vcs_symbol_outside_repo() {
+        let mut state = non_vcs_state("git");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+        let git = host.commands[0].1.clone();
+        lookup_failed(&mut state, &git, 128, NOT_A_REPO, &mut host);
+        assert_eq!(host.renames, vec![(0, "scratch ∅".to_string())]);
+    }
+
+    #[test]
+    fn test_non_vcs_symbol_absent_for_error_in_repo() {
+        let mut state = non_vcs_state("git");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/fresh"), &mut host);
+        let git = host.commands[0].1.clone();
+        let no_commits = "fatal: ambiguous argument 'HEAD': unknown revision\n";
+        lookup_failed(&mut state, &git, 128, no_commits, &mut host);
+        assert_eq!(host.renames, vec![(0, "fresh".to_string())]);
+    }
+
+    #[test]
+    fn test_non_vcs_symbol_needs_every_vcs_to_agree() {
+        let mut state = non_vcs_state("git,hg");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+        let git = host.commands[0].1.clone();
+        lookup_failed(&mut state, &git, 128, NOT_A_REPO, &mut host);
+        let hg = host.commands[1].1.clone();
+        lookup_failed(
+            &mut state,
+            &hg,
+            255,
+            "abort: no repository found in '/tmp/scratch'!\n",
+            &mut host,
+        );
+        assert_eq!(host.renames, vec![(0, "scratch ∅".to_string())]);
+
+        // git failing for another reason means the folder may well be a repo
+        let mut state = non_vcs_state("git,hg");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let git = host.commands[0].1.clone();
+        lookup_failed(
+            &mut state,
+            &git,
+            128,
+            "fatal: detected dubious ownership\n",
+            &mut host,
+        );
+        let hg = host.commands[1].1.clone();
+        assert_eq!(hg.get("vcs_error").map(String::as_str), Some("true"));
+        lookup_failed(
+            &mut state,
+            &hg,
+            255,
+            "abort: no repository found in '/work/api'!\n",
+            &mut host,
+        );
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+    }
+
+    #[test]
+    fn test_git_branch_command_replaces_script() {
+        let command = r#"git --git-dir="$1/.bare" --work-tree="$1" rev-parse --abbrev-ref HEAD"#;
+        let mut state = State {
+            config: FormatterConfig {
+                git_branch_command: command.to_string(),
+                git_extra_args: vec!["-c".to_string(), "safe.directory=*".to_string()],
+                show_change_counts: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, context) = host.commands[0].clone();
+        assert_eq!(args, ["bash", "-c", command, "_", "/work/api"]);
+        git_ok(&mut state, &context, "dev\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:dev".to_string())]);
+    }
+
+    #[test]
+    fn test_git_branch_command_empty_uses_builtin() {
+        let config = FormatterConfig::from_config(&BTreeMap::from([(
+            "git_branch_command".to_string(),
+            "  ".to_string(),
+        )]));
+        let mut state = State {
+            config,
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands[0].0[2], GIT_BRANCH_SCRIPT);
+    }
+
+    #[test]
+    fn test_git_script_variants() {
+        assert_eq!(
+            git_script(false, false, false, false, false),
+            GIT_BRANCH_SCRIPT
+        );
+        let status = git_script(true, false, false, false, false);
+        assert!(status.starts_with(GIT_BRANCH_SCRIPT));
+        assert!(status.contains(GIT_STATUS_LINE));
+        assert!(!status.contains(GIT_UPSTREAM_LINE));
+        assert!(status.ends_with("exit 0"));
+        let both = git_script(true, true, false, false, false);
+        // Upstream comes first so it is never mixed into the status lines
+        assert!(both.find(GIT_UPSTREAM_LINE) < both.find(GIT_STATUS_LINE));
+        let all = git_script(true, true, true, false, false);
+        assert!(all.find(GIT_ORIGIN_LINE) < all.find(GIT_STATUS_LINE));
+        assert!(!both.contains(GIT_ORIGIN_LINE));
+        let base = git_script(false, false, false, true, false);
+        // The base branch is moved out of the arguments before git runs
+        assert!(base.starts_with("base=\"$2\""));
+        assert!(base.contains(GIT_BASE_LINE));
+        let age = git_script(true, false, false, false, true);
+        assert!(age.find(GIT_AGE_LINE) < age.find(GIT_STATUS_LINE));
+    }
+
+    #[test]
+    fn test_stale_days_styles_old_branches() {
+        let mut state = State {
+            config: FormatterConfig {
+                stale_days: 14,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+
+        let (args, api) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(true, false, false, false, true));
+        let web = host.commands[1].1.clone();
+        let old = "dev\n/work/api\nage 1702592000 1700000000\n# branch.oid abc123\n";
+        git_ok(&mut state, &api, old, &mut host);
+        let recent = "dev\n/work/web\nage 1700086400 1700000000\n# branch.oid abc123\n";
+        git_ok(&mut state, &web, recent, &mut host);
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "api:\x1b[2;31mdev\x1b[0m".to_string()),
+                (1, "web:dev".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_base_branch_appends_commits_ahead() {
+        let mut state = State {
+            config: FormatterConfig {
+                base_branch: "next".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, context) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(false, false, false, true, false));
+        assert_eq!(&args[3..], ["_", "/work/api", "next", "git"]);
+        git_ok(&mut state, &context, "dev\n/work/api\nbase 7\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:dev ↑7".to_string())]);
+    }
+
+    #[test]
+    fn test_base_branch_missing_shows_no_count() {
+        let mut state = State {
+            config: FormatterConfig {
+                base_branch: "trunk".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "dev\n/work/api\nbase \n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:dev".to_string())]);
+    }
+
+    #[test]
+    fn test_show_forge_prepends_origin_badge() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_forge: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+
+        let (args, api) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(false, false, true, false, false));
+        let web = host.commands[1].1.clone();
+        let out = "next\n/work/api\norigin git@github.com:o/api.git\n";
+        git_ok(&mut state, &api, out, &mut host);
+        git_ok(&mut state, &web, "next\n/work/web\norigin \n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "gh api:next".to_string()), (1, "web:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_family_segment_prefixes_client() {
+        let mut state = State {
+            config: FormatterConfig {
+                family_segment: 1,
+                family_base: "~/work".to_string(),
+                ..Default::default()
+            },
+            home_dir: Some("/home/me".to_string()),
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "~/work/acme/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "~/work"), &mut host);
+
+        let api = host.commands[0].1.clone();
+        let work = host.commands[1].1.clone();
+        git_ok(&mut state, &api, "next\n", &mut host);
+        git_ok(&mut state, &work, "next\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "acme:api:next".to_string()),
+                (1, "work:next".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_template_fills_tab_index() {
+        let mut state = State {
+            config: FormatterConfig {
+                name_template: "{index}:{folder}@{branch}".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/api"), &mut host);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(1, "2:api@next".to_string())]);
+    }
+
+    #[test]
+    fn test_name_template_fills_ssh_host() {
+        let mut state = State {
+            config: FormatterConfig {
+                name_template: "{host}:{folder}".to_string(),
+                show_branch: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "ssh myhost: /srv/app"), &mut host);
+        state.handle_pane_update(manifest(0, "/srv/app"), &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "myhost:app".to_string()), (0, "app".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_ssh_cwd_is_named_without_local_lookups() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_dir_freshness: true,
+                name_sources: vec![NameSource::ReadmeTitle, NameSource::Folder],
+                name_template: "{host}:{folder}".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/srv/app"), &mut host);
+        let local = host.commands.len();
+        assert!(local > 0);
+
+        state.handle_pane_update(manifest(0, "ssh myhost: /srv/app"), &mut host);
+        assert_eq!(host.commands.len(), local);
+        assert_eq!(host.renames, vec![(0, "myhost:app".to_string())]);
+
+        // The local lookup for the same path no longer applies
+        for (_, context) in host.commands.clone() {
+            git_ok(&mut state, &context, "next\n/srv/app\n", &mut host);
+        }
+        assert_eq!(host.renames, vec![(0, "myhost:app".to_string())]);
+    }
+
+    #[test]
+    fn test_unc_path_names_folder_with_server_badge() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_unc_server: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, r"\\fs01\share\api"), &mut host);
+
+        let context = host.commands[0].1.clone();
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), context, &mut host);
+        assert_eq!(host.renames, vec![(0, "fs01 api".to_string())]);
+    }
+
+    #[test]
+    fn test_nonprimary_remote_shown_after_branch() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_nonprimary_remote: true,
+                branch_max_len: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+
+        let (args, api) = host.commands[0].clone();
+        assert_eq!(args[2], git_script(false, true, false, false, false));
+        let web = host.commands[1].1.clone();
+        git_ok(
+            &mut state,
+            &api,
+            "next\n/work/api\nupstream upstream/next\n",
+            &mut host,
+        );
+        git_ok(
+            &mut state,
+            &web,
+            "next\n/work/web\nupstream origin/next\n",
+            &mut host,
+        );
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "api:next@upstream".to_string()),
+                (1, "web:next".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tab_prefix_applies_to_busy_and_final_names() {
+        let mut state = State {
+            config: FormatterConfig {
+                tab_prefix: "work/".to_string(),
+                show_busy: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "work/api⋯".to_string()),
+                (0, "work/api:next".to_string())
+            ]
+        );
+    }
+
+    // ==================== initial_name Tests ====================
+
+    fn initial_name_state() -> State {
+        State {
+            config: FormatterConfig {
+                initial_name: "namey…".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_initial_name_renames_active_tab_on_load() {
+        let mut state = initial_name_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_permissions_granted(&mut host);
+        assert_eq!(host.renames, vec![(1, "namey…".to_string())]);
+    }
+
+    #[test]
+    fn test_initial_name_before_first_tab_update() {
+        let mut state = initial_name_state();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        assert_eq!(host.renames, vec![(0, "namey…".to_string())]);
+    }
+
+    #[test]
+    fn test_initial_name_skips_named_tab_and_when_unset() {
+        let mut state = initial_name_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/notes"), &mut host);
+        let context = host.commands[0].1.clone();
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), context, &mut host);
+        state.handle_permissions_granted(&mut host);
+        assert_eq!(host.renames, vec![(0, "notes".to_string())]);
+
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    // ==================== name_ttl_secs Tests ====================
+
+    fn ttl_state() -> State {
+        State {
+            config: FormatterConfig {
+                name_ttl_secs: 30,
+                sticky_cwd_ms: 1500,
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_name_ttl_arms_refresh_on_start() {
+        let mut state = ttl_state();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        assert_eq!(host.timeouts, vec![30.0]);
+
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        assert!(host.timeouts.is_empty());
+    }
+
+    #[test]
+    fn test_name_ttl_refreshes_every_tab() {
+        let mut state = ttl_state();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+        let api = host.commands[0].1.clone();
+        let web = host.commands[1].1.clone();
+        git_ok(&mut state, &api, "next\n", &mut host);
+        git_ok(&mut state, &web, "next\n", &mut host);
+
+        state.apply_event(TestEvent::Timer(30.0), &mut host);
+        assert_eq!(host.commands.len(), 4);
+        assert_eq!(host.commands[2].1, api);
+        assert_eq!(host.commands[3].1, web);
+        assert_eq!(host.timeouts, vec![30.0, 30.0]);
+
+        git_ok(&mut state, &api, "dev\n", &mut host);
+        git_ok(&mut state, &web, "dev\n", &mut host);
+        assert_eq!(
+            host.renames[2..],
+            [(0, "api:dev".to_string()), (1, "web:dev".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_name_ttl_timer_told_apart_from_sticky_hold() {
+        let mut state = ttl_state();
+        let mut host = FakeHost::default();
+        state.handle_permissions_granted(&mut host);
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        assert_eq!(host.timeouts, vec![30.0, 1.5]);
+        // The hold fires first despite being set second
+        state.apply_event(TestEvent::Timer(1.502), &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "vim".to_string())));
+        assert_eq!(host.commands.len(), 1);
+
+        // The cwd was forgotten with the hold, so there is nothing to refresh
+        state.apply_event(TestEvent::Timer(30.01), &mut host);
+        assert_eq!(host.commands.len(), 1);
+        assert_eq!(host.timeouts, vec![30.0, 1.5, 30.0]);
+    }
+
+    // ==================== git_cache_ttl_ms Tests ====================
+
+    #[test]
+    fn test_git_cache_reuses_recent_lookup() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let api = host.commands[0].1.clone();
+        git_ok(&mut state, &api, "next\n/work/api\n", &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        let web = host.commands[1].1.clone();
+        git_ok(&mut state, &web, "dev\n/work/web\n", &mut host);
+        assert_eq!(host.timeouts, vec![5.0, 5.0]);
+
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 2);
+        assert_eq!(host.renames.last(), Some(&(0, "api:next".to_string())));
+    }
+
+    #[test]
+    fn test_git_cache_expires_after_ttl() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let api = host.commands[0].1.clone();
+        git_ok(&mut state, &api, "next\n", &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        let web = host.commands[1].1.clone();
+        git_ok(&mut state, &web, "next\n", &mut host);
+
+        // The first entry expires; the branch was switched in the meantime
+        state.apply_event(TestEvent::Timer(5.0), &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 3);
+        git_ok(&mut state, &api, "dev\n", &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "api:dev".to_string())));
+
+        // The second entry is still fresh
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        assert_eq!(host.commands.len(), 3);
+    }
+
+    #[test]
+    fn test_git_cache_disabled() {
+        let mut state = State {
+            config: FormatterConfig {
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let api = host.commands[0].1.clone();
+        git_ok(&mut state, &api, "next\n", &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 3);
+        assert!(host.timeouts.is_empty());
+    }
+
+    // ==================== respect_manual_rename Tests ====================
+
+    fn named_tab(name: &str) -> Vec<TabInfo> {
+        vec![TabInfo {
+            position: 0,
+            name: name.to_string(),
+            active: true,
+            ..Default::default()
+        }]
+    }
+
+    fn named_api_tab(state: &mut State, host: &mut FakeHost) {
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), host);
+        let context = host.commands[0].1.clone();
+        git_ok(state, &context, "next\n", host);
+        state.handle_tab_update(&named_tab("api:next"));
+    }
+
+    #[test]
+    fn test_manual_rename_kept_until_cwd_changes() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        named_api_tab(&mut state, &mut host);
+
+        state.handle_tab_update(&named_tab("scratchpad"));
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        let context = host.commands.last().unwrap().1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "web:next".to_string())));
+    }
+
+    #[test]
+    fn test_stale_tab_update_is_not_a_manual_rename() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        named_api_tab(&mut state, &mut host);
+
+        // A TabUpdate sent before the rename landed still shows the old name
+        state.handle_tab_update(&named_tab("Tab #1"));
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "vim".to_string())));
+    }
+
+    #[test]
+    fn test_manual_rename_overwritten_when_not_respected() {
+        let mut state = State {
+            config: FormatterConfig {
+                respect_manual_rename: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        named_api_tab(&mut state, &mut host);
+
+        state.handle_tab_update(&named_tab("scratchpad"));
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "vim".to_string())));
+    }
+
+    // ==================== eager_folder Tests ====================
+
+    #[test]
+    fn test_eager_folder_renames_before_branch_lookup() {
+        let mut state = State {
+            config: FormatterConfig {
+                eager_folder: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api".to_string()), (0, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_eager_folder_off_waits_for_branch_lookup() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert!(host.renames.is_empty());
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    #[test]
+    fn test_folder_depth_includes_parent() {
+        let mut state = State {
+            config: FormatterConfig {
+                folder_depth: 2,
+                folder_max_len: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/backend/src"), &mut host);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "backend/src:next".to_string())]);
+    }
+
+    // ==================== single_rename Tests ====================
+
+    fn single_rename_state() -> State {
+        State {
+            config: FormatterConfig {
+                single_rename: true,
+                eager_folder: true,
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_single_rename_waits_for_branch_lookup() {
+        let mut state = single_rename_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        // No interim name even with eager_folder
+        assert!(host.renames.is_empty());
+        assert_eq!(host.timeouts, vec![0.3]);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        state.apply_event(TestEvent::Timer(0.3), &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    #[test]
+    fn test_single_rename_names_folder_after_timeout() {
+        let mut state = single_rename_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.apply_event(TestEvent::Timer(0.3), &mut host);
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api".to_string()), (0, "api:next".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_single_rename_timeout_dropped_after_cwd_change() {
+        let mut state = single_rename_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        // The first deferral belongs to a cwd the tab has left
+        state.apply_event(TestEvent::Timer(0.3), &mut host);
+        assert!(host.renames.is_empty());
+        state.apply_event(TestEvent::Timer(0.3), &mut host);
+        assert_eq!(host.renames, vec![(0, "web".to_string())]);
+    }
+
+    // ==================== settle_ms Tests ====================
+
+    fn settle_state() -> State {
+        State {
+            config: FormatterConfig {
+                settle_ms: 150,
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_settle_applies_last_update_of_burst() {
+        let mut state = settle_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/docs"), &mut host);
+        assert!(host.commands.is_empty());
+        assert!(host.renames.is_empty());
+        assert_eq!(host.timeouts, vec![0.15; 3]);
+
+        // Only the last timer finds its update still pending
+        for _ in 0..3 {
+            state.apply_event(TestEvent::Timer(0.15), &mut host);
+        }
+        assert_eq!(host.commands.len(), 1);
+        let context = host.commands[0].1.clone();
+        assert_eq!(context["path"], "/work/docs");
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "docs:next".to_string())]);
+    }
+
+    #[test]
+    fn test_settle_mixed_changes_rename_once() {
+        let mut state = settle_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        // A title change, a cd and a focus switch land in quick succession
+        state.handle_pane_update(manifest(0, "vim notes.md"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(
+            manifest_with_ids(0, &[(1, "/work/api", false), (2, "/work/web", true)]),
+            &mut host,
+        );
+        assert!(host.commands.is_empty());
+        assert!(host.renames.is_empty());
+
+        for _ in 0..3 {
+            state.apply_event(TestEvent::Timer(0.15), &mut host);
+        }
+        assert_eq!(host.commands.len(), 1);
+        let context = host.commands[0].1.clone();
+        assert_eq!(context["path"], "/work/web");
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "web:next".to_string())]);
+    }
+
+    #[test]
+    fn test_settle_disabled_applies_immediately() {
+        let mut state = State {
+            config: FormatterConfig {
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        assert_eq!(host.commands.len(), 2);
+        assert!(host.timeouts.is_empty());
+    }
+
+    // ==================== Sticky cwd Tests ====================
+
+    fn sticky_state() -> State {
+        State {
+            config: FormatterConfig {
+                sticky_cwd_ms: 1500,
+                git_cache_ttl_ms: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sticky_cwd_holds_through_brief_title() {
+        let mut state = sticky_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+
+        state.handle_pane_update(manifest(0, "cargo build"), &mut host);
+        state.handle_pane_update(manifest(0, "cargo test"), &mut host);
+        assert_eq!(host.timeouts, vec![1.5]);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.apply_event(TestEvent::Timer(1.5), &mut host);
+
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+        assert_eq!(host.commands.len(), 1);
+    }
+
+    #[test]
+    fn test_sticky_cwd_releases_sustained_title() {
+        let mut state = sticky_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        state.apply_event(TestEvent::Timer(1.5), &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next".to_string()), (0, "vim".to_string())]
+        );
+
+        // Returning to the same cwd looks it up again
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.commands.len(), 2);
+    }
+
+    #[test]
+    fn test_sticky_cwd_ignores_timer_from_earlier_hold() {
+        let mut state = sticky_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+
+        state.handle_pane_update(manifest(0, "ls"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        state.apply_event(TestEvent::Timer(1.5), &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+
+        state.apply_event(TestEvent::Timer(1.5), &mut host);
+        assert_eq!(host.renames.last(), Some(&(0, "vim".to_string())));
+    }
+
+    #[test]
+    fn test_sticky_cwd_disabled_switches_to_title() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        state.handle_pane_update(manifest(0, "vim"), &mut host);
+        // Only the git cache entry's expiry; no hold was set
+        assert_eq!(host.timeouts, vec![5.0]);
+        assert_eq!(host.renames.last(), Some(&(0, "vim".to_string())));
+    }
+
+    // ==================== Freshness Tests ====================
+
+    fn fresh_state(show_branch: bool) -> State {
+        State {
+            config: FormatterConfig {
+                show_dir_freshness: true,
+                show_branch,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_freshness_result_marks_named_tab() {
+        let mut state = fresh_state(true);
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+
+        let (args, fresh) = host.commands[0].clone();
+        assert_eq!(&args[2..], [FRESHNESS_SCRIPT, "_", "/tmp/scratch", "10"]);
+        let git = host.commands[1].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), git, &mut host);
+        git_ok(&mut state, &fresh, "/tmp/scratch\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "scratch".to_string()), (0, "scratch~".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_freshness_known_before_git_result() {
+        let mut state = fresh_state(true);
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let fresh = host.commands[0].1.clone();
+        let git = host.commands[1].1.clone();
+        git_ok(&mut state, &fresh, "/work/api\n", &mut host);
+        assert!(host.renames.is_empty());
+        git_ok(&mut state, &git, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api~:next".to_string())]);
+    }
+
+    #[test]
+    fn test_freshness_stale_dir_has_no_marker() {
+        let mut state = fresh_state(false);
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/old"), &mut host);
+        assert_eq!(host.commands.len(), 1);
+        let fresh = host.commands[0].1.clone();
+        git_ok(&mut state, &fresh, "", &mut host);
+        assert_eq!(host.renames, vec![(0, "old".to_string())]);
+    }
+
+    // ==================== monorepo_mode Tests ====================
+
+    fn monorepo_state() -> State {
+        let mut state = State {
+            config: FormatterConfig {
+                monorepo_mode: true,
+                folder_max_len: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        state.handle_tab_update(&tabs(0, 3));
+        state
+    }
+
+    /// Points tab `position` at `cwd` inside the repo at `root`.
+    fn open_in_repo(
+        state: &mut State,
+        host: &mut FakeHost,
+        position: usize,
+        cwd: &str,
+        root: &str,
+    ) {
+        state.active_tab = position;
+        state.handle_pane_update(manifest(position, cwd), host);
+        let context = host.commands.last().unwrap().1.clone();
+        git_ok(state, &context, &format!("next\n{}\n", root), host);
+    }
+
+    #[test]
+    fn test_monorepo_mode_switches_with_enough_tabs() {
+        let mut state = monorepo_state();
+        let mut host = FakeHost::default();
+        open_in_repo(&mut state, &mut host, 0, "/mono/services/api", "/mono");
+        open_in_repo(&mut state, &mut host, 1, "/mono/services/web", "/mono");
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next".to_string()), (1, "web:next".to_string())]
+        );
+
+        open_in_repo(&mut state, &mut host, 2, "/mono/tools", "/mono");
+        assert_eq!(
+            host.renames[2..],
+            [
+                (2, "tools:next".to_string()),
+                (0, "services/api:next".to_string()),
+                (1, "services/web:next".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_monorepo_mode_switches_back_when_a_tab_leaves() {
+        let mut state = monorepo_state();
+        let mut host = FakeHost::default();
+        open_in_repo(&mut state, &mut host, 0, "/mono/services/api", "/mono");
+        open_in_repo(&mut state, &mut host, 1, "/mono/services/web", "/mono");
+        open_in_repo(&mut state, &mut host, 2, "/mono/tools", "/mono");
+        host.renames.clear();
+
+        open_in_repo(&mut state, &mut host, 2, "/elsewhere", "/elsewhere");
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "api:next".to_string()),
+                (1, "web:next".to_string()),
+                (2, "elsewhere:next".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_monorepo_mode_ignores_different_repos() {
+        let mut state = monorepo_state();
+        let mut host = FakeHost::default();
+        open_in_repo(&mut state, &mut host, 0, "/a/src", "/a");
+        open_in_repo(&mut state, &mut host, 1, "/b/src", "/b");
+        open_in_repo(&mut state, &mut host, 2, "/c/src", "/c");
+        assert!(host.renames.iter().all(|(_, name)| name == "src:next"));
+    }
+
+    // ==================== new_pane_name Tests ====================
+
+    fn new_pane_state() -> State {
+        State {
+            config: FormatterConfig {
+                new_pane_name: "new".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_new_pane_name_for_untitled_pane() {
+        let mut state = new_pane_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, ""), &mut host);
+        assert_eq!(host.renames, vec![(0, "new".to_string())]);
+    }
+
+    #[test]
+    fn test_new_pane_name_skips_titled_pane() {
+        let mut state = new_pane_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+        assert_eq!(host.renames, vec![(0, "htop".to_string())]);
+    }
+
+    #[test]
+    fn test_new_pane_name_skips_tab_with_known_cwd() {
+        let mut state = new_pane_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        state.handle_pane_update(manifest(0, ""), &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    // ==================== Inspect Tests ====================
+
+    #[test]
+    fn test_inspect_reports_active_tab() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "zsh: /work/my_api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n/work/my_api\n", &mut host);
+
+        let reply = state.handle_pipe_message("namey:inspect", &mut host);
+        assert_eq!(
+            reply.as_deref(),
+            Some("tab=1\ntitle=zsh: /work/my_api\ncwd=/work/my_api\nfolder=my_api\nbranch=next\n")
+        );
+        // Inspecting never renames anything
+        assert_eq!(host.renames.len(), 1);
+    }
+
+    #[test]
+    fn test_inspect_without_cwd_or_branch() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+        assert_eq!(
+            state.inspect(),
+            "tab=0\ntitle=htop\ncwd=\nfolder=htop\nbranch=\n"
+        );
+        assert_eq!(state.handle_pipe_message("namey:freeze", &mut host), None);
+    }
+
+    // ==================== Freeze Tests ====================
+
+    #[test]
+    fn test_freeze_skips_renames() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pipe_message("namey:freeze", &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_unfreeze_catches_up_with_latest_names() {
+        let mut state = State {
+            config: FormatterConfig {
+                rename_session: true,
+                show_busy: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let api = host.commands[0].1.clone();
+        git_ok(&mut state, &api, "next\n", &mut host);
+        host.renames.clear();
+
+        state.handle_pipe_message("namey:freeze", &mut host);
+        state.handle_pane_update(manifest(0, "/work/web"), &mut host);
+        let web = host.commands[1].1.clone();
+        git_ok(&mut state, &web, "dev\n", &mut host);
+        assert!(host.renames.is_empty());
+        assert_eq!(host.sessions, vec!["api"]);
+
+        state.handle_pipe_message("namey:unfreeze", &mut host);
+        assert_eq!(host.renames, vec![(0, "web:dev".to_string())]);
+        assert_eq!(host.sessions, vec!["api", "web"]);
+
+        // Nothing is pending twice, and unknown commands are ignored
+        state.handle_pipe_message("namey:unfreeze", &mut host);
+        state.handle_pipe_message("other:plugin", &mut host);
+        assert_eq!(host.renames.len(), 1);
+    }
+
+    #[test]
+    fn test_unfreeze_without_changes_renames_nothing() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        state.handle_pipe_message("namey:freeze", &mut host);
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        state.handle_pipe_message("namey:unfreeze", &mut host);
+        assert_eq!(host.renames, vec![(0, "api:next".to_string())]);
+    }
+
+    // ==================== rename_session Tests ====================
+
+    #[test]
+    fn test_rename_session_follows_active_tab() {
+        let mut state = State {
+            config: FormatterConfig {
+                rename_session: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/My API"), &mut host);
+        state.handle_pane_update(manifest(0, "/work/My API"), &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "/work/web"), &mut host);
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/My API"), &mut host);
+        assert_eq!(host.sessions, vec!["My-API", "web", "My-API"]);
+    }
+
+    #[test]
+    fn test_rename_session_disabled() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert!(host.sessions.is_empty());
+    }
+
+    // ==================== Kube context Tests ====================
+
+    fn kube_state() -> State {
+        State {
+            config: FormatterConfig {
+                show_kube_context: true,
+                show_branch: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_kube_context_badge_added_when_lookup_returns() {
+        let mut state = kube_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/infra/deploy"), &mut host);
+
+        let (args, kube) = host.commands[0].clone();
+        assert_eq!(args, ["kubectl", "config", "current-context"]);
+        git_ok(
+            &mut state,
+            &kube,
+            "arn:aws:eks:us-east-1:123456789012:cluster/prod\n",
+            &mut host,
+        );
+        assert_eq!(
+            host.renames,
+            vec![(0, "deploy".to_string()), (0, "⎈prod deploy".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_kube_context_absent_leaves_name_alone() {
+        let mut state = kube_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/infra/deploy"), &mut host);
+        let kube = host.commands[0].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), kube, &mut host);
+        assert_eq!(host.renames, vec![(0, "deploy".to_string())]);
+    }
+
+    // ==================== max_badges Tests ====================
+
+    fn badge_state(badge_priority: Vec<Badge>) -> State {
+        State {
+            config: FormatterConfig {
+                show_kube_context: true,
+                show_dirty: true,
+                max_badges: 1,
+                badge_priority,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Answers the git and kube lookups for a dirty `api` checkout on `next`
+    /// in the `kind-dev` context.
+    fn answer_badge_lookups(state: &mut State, host: &mut FakeHost) {
+        let commands = host.commands.clone();
+        for (args, context) in commands {
+            if args[0] == "kubectl" {
+                git_ok(state, &context, "kind-dev\n", host);
+            } else {
+                let out = "next\n/work/api\n# branch.oid abc123\n1 .M N... 100644 100644 100644 a b src/lib.rs\n";
+                git_ok(state, &context, out, host);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_badges_drops_lower_priority_badge() {
+        let mut state = badge_state(Badge::ALL.to_vec());
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        answer_badge_lookups(&mut state, &mut host);
+        assert_eq!(host.renames.last().unwrap().1, "api:next*");
+    }
+
+    #[test]
+    fn test_max_badges_follows_badge_priority() {
+        let mut state = badge_state(vec![Badge::Kube]);
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        answer_badge_lookups(&mut state, &mut host);
+        assert_eq!(host.renames.last().unwrap().1, "⎈kind-dev api:next");
+    }
+
+    #[test]
+    fn test_max_badges_keeps_all_within_cap() {
+        let mut state = badge_state(Badge::ALL.to_vec());
+        state.config.max_badges = 2;
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        answer_badge_lookups(&mut state, &mut host);
+        assert_eq!(host.renames.last().unwrap().1, "⎈kind-dev api:next*");
+    }
+
+    // ==================== attention_symbol Tests ====================
+
+    fn attention_state() -> State {
+        State {
+            config: FormatterConfig {
+                attention_symbol: " !".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// A manifest for `position` whose focused pane sits in `/work/web`
+    /// next to a command pane that has exited or is still running.
+    fn command_manifest(position: usize, exited: bool) -> PaneManifest {
+        let mut manifest = manifest(position, "/work/web");
+        let command = PaneInfo {
+            id: 1,
+            title: "cargo build".to_string(),
+            exited,
+            ..Default::default()
+        };
+        manifest.panes.get_mut(&position).unwrap().push(command);
+        manifest
+    }
+
+    /// Names tab 1 of two `web:next` while tab 0 is active.
+    fn named_background_tab(state: &mut State, host: &mut FakeHost) {
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(command_manifest(1, false), host);
+        let git = host.commands[0].1.clone();
+        git_ok(state, &git, "next\n/work/web\n", host);
+    }
+
+    #[test]
+    fn test_attention_set_when_background_command_exits() {
+        let mut state = attention_state();
+        let mut host = FakeHost::default();
+        named_background_tab(&mut state, &mut host);
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(1, "web:next".to_string()), (1, "web:next !".to_string())]
+        );
+
+        // The same exited pane does not flag the tab again once it is cleared
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        assert_eq!(host.renames.last().unwrap(), &(1, "web:next".to_string()));
+    }
+
+    #[test]
+    fn test_attention_cleared_when_tab_focused() {
+        let mut state = attention_state();
+        let mut host = FakeHost::default();
+        named_background_tab(&mut state, &mut host);
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+
+        // Other tabs' updates leave the marker alone
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        assert_eq!(host.renames[1], (1, "web:next !".to_string()));
+
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        assert_eq!(host.renames.last().unwrap(), &(1, "web:next".to_string()));
+    }
+
+    #[test]
+    fn test_attention_ignores_active_tab_and_disabled_symbol() {
+        let mut state = attention_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(command_manifest(1, false), &mut host);
+        let git = host.commands[0].1.clone();
+        git_ok(&mut state, &git, "next\n/work/web\n", &mut host);
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        assert_eq!(host.renames, vec![(1, "web:next".to_string())]);
+
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        named_background_tab(&mut state, &mut host);
+        state.handle_pane_update(command_manifest(1, true), &mut host);
+        assert_eq!(host.renames, vec![(1, "web:next".to_string())]);
+    }
+
+    // ==================== show_layout Tests ====================
+
+    fn pane(id: u32, floating: bool, rows: usize) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_floating: floating,
+            pane_rows: rows,
+            title: "/work/api".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn layout_of(panes: &[PaneInfo]) -> PaneLayout {
+        classify_layout(&panes.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_classify_layout_tiled_shapes() {
+        assert_eq!(layout_of(&[pane(1, false, 40)]), PaneLayout::Single);
+        assert_eq!(
+            layout_of(&[pane(1, false, 40), pane(2, false, 40)]),
+            PaneLayout::Split
+        );
+        // A collapsed pane is one row tall
+        let stack = [pane(1, false, 38), pane(2, false, 1), pane(3, false, 1)];
+        assert_eq!(layout_of(&stack), PaneLayout::Stacked);
+    }
+
+    #[test]
+    fn test_classify_layout_floating_shapes() {
+        let floating = [pane(1, false, 40), pane(2, true, 10), pane(3, true, 10)];
+        assert_eq!(layout_of(&floating), PaneLayout::Floating);
+        // One floating pane over a tiled one is not floating-heavy
+        assert_eq!(
+            layout_of(&[pane(1, false, 40), pane(2, true, 10)]),
+            PaneLayout::Single
+        );
+        let mut hidden = pane(2, true, 10);
+        hidden.is_suppressed = true;
+        assert_eq!(
+            layout_of(&[pane(1, false, 40), pane(3, false, 40), hidden]),
+            PaneLayout::Split
+        );
+    }
+
+    #[test]
+    fn test_show_layout_rerenders_when_panes_split() {
+        let mut state = State {
+            config: FormatterConfig {
+                show_layout: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+
+        let mut focused = pane(1, false, 20);
+        focused.is_focused = true;
+        let split = PaneManifest {
+            panes: HashMap::from([(0, vec![focused, pane(2, false, 20)])]),
+        };
+        state.handle_pane_update(split, &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next".to_string()), (0, "api:next ⊞".to_string())]
+        );
+        assert_eq!(host.commands.len(), 1);
+    }
+
+    // ==================== show_icon Tests ====================
+
+    fn icon_state() -> State {
+        State {
+            config: FormatterConfig {
+                show_icon: true,
+                show_branch: false,
+                project_icons: vec![
+                    ("Cargo.toml".to_string(), "R".to_string()),
+                    ("go.mod".to_string(), "G".to_string()),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_project_icon_prepended_when_marker_found() {
+        let mut state = icon_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, icon) = host.commands[0].clone();
+        assert_eq!(
+            args,
+            [
+                "bash",
+                "-c",
+                PROJECT_SCRIPT,
+                "_",
+                "/work/api",
+                "Cargo.toml",
+                "go.mod"
+            ]
+        );
+        git_ok(&mut state, &icon, "go.mod\n", &mut host);
+        assert_eq!(
+            host.renames,
+            vec![(0, "api".to_string()), (0, "G api".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_project_icon_absent_outside_projects() {
+        let mut state = icon_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/tmp/scratch"), &mut host);
+        let icon = host.commands[0].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), icon, &mut host);
+        assert_eq!(host.renames, vec![(0, "scratch".to_string())]);
+    }
+
+    // ==================== readme_title Tests ====================
+
+    fn readme_state() -> State {
+        State {
+            config: FormatterConfig {
+                show_branch: false,
+                folder_max_len: 20,
+                name_sources: vec![NameSource::ReadmeTitle, NameSource::Folder],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_readme_title_names_tab() {
+        let mut state = readme_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+
+        let (args, readme) = host.commands[0].clone();
+        assert_eq!(args, ["bash", "-c", README_SCRIPT, "_", "/work/api", "git"]);
+        assert_eq!(readme.get("stage").map(String::as_str), Some("readme"));
+        git_ok(
+            &mut state,
+            &readme,
+            "[![ci](b.svg)](ci)\n\n# Billing API\n",
+            &mut host,
+        );
+        assert_eq!(
+            host.renames,
+            vec![(0, "api".to_string()), (0, "Billing API".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_readme_title_missing_falls_back_to_folder() {
+        let mut state = readme_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let readme = host.commands[0].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), readme, &mut host);
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+    }
+
+    // ==================== package_name Tests ====================
+
+    fn package_state() -> State {
+        State {
+            config: FormatterConfig {
+                show_branch: false,
+                folder_max_len: 20,
+                name_sources: vec![NameSource::PackageName, NameSource::Folder],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_package_name_names_tab() {
+        let mut state = package_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/mono/pkgs/bill"), &mut host);
+
+        let (args, package) = host.commands[0].clone();
+        assert_eq!(
+            args,
+            ["bash", "-c", PACKAGE_SCRIPT, "_", "/work/mono/pkgs/bill"]
+        );
+        git_ok(
+            &mut state,
+            &package,
+            r#"{"name": "@acme/billing"}"#,
+            &mut host,
+        );
+        assert_eq!(
+            host.renames,
+            vec![(0, "bill".to_string()), (0, "@acme/billing".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_package_name_missing_falls_back_to_folder() {
+        let mut state = package_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let package = host.commands[0].1.clone();
+        state.handle_command_result(Some(1), Vec::new(), Vec::new(), package, &mut host);
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+    }
+
+    // ==================== name_command Tests ====================
+
+    fn name_command_state(template: &str) -> State {
+        State {
+            config: FormatterConfig {
+                name_command: template.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_name_command_script_substitution() {
+        assert_eq!(
+            name_command_script("my-namer {path} --branch={branch}"),
+            r#"my-namer "$1" --branch="$2""#
+        );
+        assert_eq!(
+            name_command_script("echo {path} {path}"),
+            r#"echo "$1" "$1""#
+        );
+        assert_eq!(name_command_script("hostname"), "hostname");
+    }
+
+    #[test]
+    fn test_first_output_line() {
+        assert_eq!(
+            first_output_line(b"api-next\nignored\n"),
+            Some("api-next".to_string())
+        );
+        assert_eq!(
+            first_output_line(b"  a\x1b[1mb \n"),
+            Some("a[1mb".to_string())
+        );
+        assert_eq!(first_output_line(b"  \nsecond\n"), None);
+        assert_eq!(first_output_line(b""), None);
+    }
+
+    #[test]
+    fn test_name_command_runs_after_branch_lookup() {
+        let mut state = name_command_state("namer {path} {branch}");
+        state.home_dir = Some("/home/me".to_string());
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "~/api"), &mut host);
+        let git = host.commands[0].1.clone();
+        git_ok(&mut state, &git, "next\n", &mut host);
+
+        assert!(host.renames.is_empty());
+        let (args, context) = host.commands[1].clone();
+        assert_eq!(
+            &args[2..],
+            [r#"namer "$1" "$2""#, "_", "/home/me/api", "next"]
+        );
+        assert_eq!(context.get("stage").map(String::as_str), Some("name"));
+
+        git_ok(&mut state, &context, "API (next)\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "API (next)".to_string())]);
+    }
+
+    #[test]
+    fn test_name_command_failure_falls_back_to_regular_name() {
+        let mut state = name_command_state("namer {path}");
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let git = host.commands[0].1.clone();
+        state.handle_command_result(Some(128), Vec::new(), Vec::new(), git, &mut host);
+
+        // Outside a repo the branch argument is empty
+        let (args, context) = host.commands[1].clone();
+        assert_eq!(args.last().map(String::as_str), Some(""));
+        state.handle_command_result(
+            Some(1),
+            b"oops".to_vec(),
+            Vec::new(),
+            context.clone(),
+            &mut host,
+        );
+        git_ok(&mut state, &context, "\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "api".to_string())]);
+    }
+
+    #[test]
+    fn test_name_command_names_ignore_resize() {
+        let mut state = name_command_state("namer");
+        state.config.narrow_threshold = 30;
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+        state.handle_resize(10, &mut host);
+        assert_eq!(host.renames, vec![(0, "htop".to_string())]);
+    }
+
+    fn manifest_with_ids(position: usize, panes: &[(u32, &str, bool)]) -> PaneManifest {
+        let panes = panes
+            .iter()
+            .map(|(id, title, is_focused)| PaneInfo {
+                id: *id,
+                title: title.to_string(),
+                is_focused: *is_focused,
+                ..Default::default()
+            })
+            .collect();
+        PaneManifest {
+            panes: HashMap::from([(position, panes)]),
+        }
+    }
+
+    #[test]
+    fn test_rename_on_focus_change_ignores_background_updates() {
+        let mut state = State {
+            config: FormatterConfig {
+                rename_on: RenameOn::FocusChange,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest_with_ids(0, &[(1, "/work/api", true)]), &mut host);
+        // Same focused pane: a cd, or a background pane changing its title
+        state.handle_pane_update(
+            manifest_with_ids(0, &[(1, "/work/web", true), (2, "/tmp", false)]),
+            &mut host,
+        );
+        assert_eq!(host.commands.len(), 1);
+
+        // Focus moves to pane 2
+        state.handle_pane_update(
+            manifest_with_ids(0, &[(1, "/work/web", false), (2, "/tmp", true)]),
+            &mut host,
+        );
+        assert_eq!(host.commands.len(), 2);
+        assert_eq!(
+            host.commands[1].1.get("path").map(String::as_str),
+            Some("/tmp")
+        );
+    }
+
+    #[test]
+    fn test_rename_on_any_update_follows_same_pane() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest_with_ids(0, &[(1, "/work/api", true)]), &mut host);
+        state.handle_pane_update(manifest_with_ids(0, &[(1, "/work/web", true)]), &mut host);
+        assert_eq!(host.commands.len(), 2);
+    }
+
+    // ==================== handle_resize() Tests ====================
+
+    fn narrow_state() -> State {
+        State {
+            config: FormatterConfig {
+                narrow_threshold: 30,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resize_crossing_threshold_rerenders() {
+        let mut state = narrow_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 2));
+        state.handle_pane_update(manifest(0, "/work/my_project_name"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "feature\n", &mut host);
+        state.handle_tab_update(&tabs(1, 2));
+        state.handle_pane_update(manifest(1, "htop"), &mut host);
+
+        state.handle_resize(20, &mut host);
+        state.handle_resize(25, &mut host);
+        state.handle_resize(80, &mut host);
+
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "my_pr…name:featu".to_string()),
+                (1, "htop".to_string()),
+                (0, "my_…me:fe…re".to_string()),
+                (0, "my_pr…name:featu".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resize_learned_width_applies_to_later_names() {
+        let mut state = narrow_state();
+        let mut host = FakeHost::default();
+        state.handle_resize(20, &mut host);
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/my_project_name"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "next\n", &mut host);
+        assert_eq!(host.renames, vec![(0, "my_…me:next".to_string())]);
+    }
+
+    #[test]
+    fn test_resize_skips_tab_with_lookup_in_flight() {
+        let mut state = narrow_state();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/my_project_name"), &mut host);
+        state.handle_resize(20, &mut host);
+        assert!(host.renames.is_empty());
+    }
+
+    #[test]
+    fn test_resize_switches_separator() {
+        let mut state = State {
+            config: FormatterConfig {
+                separator_wide: " : ".to_string(),
+                separator_narrow: ":".to_string(),
+                separator_width_threshold: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "/work/api"), &mut host);
+        let context = host.commands[0].1.clone();
+        git_ok(&mut state, &context, "dev\n", &mut host);
+
+        state.handle_resize(120, &mut host);
+        state.handle_resize(80, &mut host);
+        state.handle_resize(90, &mut host);
+        state.handle_resize(100, &mut host);
+        assert_eq!(
+            host.renames,
+            vec![
+                (0, "api : dev".to_string()),
+                (0, "api:dev".to_string()),
+                (0, "api : dev".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resize_without_threshold_does_nothing() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        state.handle_tab_update(&tabs(0, 1));
+        state.handle_pane_update(manifest(0, "htop"), &mut host);
+        state.handle_resize(10, &mut host);
+        assert_eq!(host.renames, vec![(0, "htop".to_string())]);
+    }
+
+    // ==================== apply_event() Scenario Tests ====================
+
+    fn tab_update(active: usize, names: &[&str]) -> TestEvent {
+        TestEvent::TabUpdate {
+            active,
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    fn pane_update(tab: usize, title: &str) -> TestEvent {
+        TestEvent::PaneUpdate {
+            tab,
+            title: title.to_string(),
+        }
+    }
+
+    fn git_result(tab: usize, path: &str, exit_code: i32, stdout: &str) -> TestEvent {
+        TestEvent::GitResult {
+            tab,
+            path: path.to_string(),
+            exit_code,
+            stdout: stdout.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_scenario_new_repo_tab() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        for event in [
+            tab_update(0, &["Tab #1"]),
+            tab_update(1, &["api:next", "Tab #2"]),
+            pane_update(1, "zsh: /work/web"),
+            git_result(1, "/work/web", 0, "next\n/work/web\n"),
+        ] {
+            state.apply_event(event, &mut host);
+        }
+        assert_eq!(host.commands.len(), 1);
+        assert_eq!(host.renames, vec![(1, "web:next".to_string())]);
+    }
+
+    #[test]
+    fn test_scenario_branch_change() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        for event in [
+            tab_update(0, &["Tab #1"]),
+            pane_update(0, "/work/api"),
+            git_result(0, "/work/api", 0, "next\n/work/api\n"),
+            // Checked out another branch in a worktree, then moved there
+            pane_update(0, "/work/api-wt"),
+            git_result(0, "/work/api-wt", 0, "dev\n/work/api-wt\n"),
+        ] {
+            state.apply_event(event, &mut host);
+        }
+        assert_eq!(
+            host.renames,
+            vec![(0, "api:next".to_string()), (0, "api-wt:dev".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_scenario_non_repo_dir() {
+        let mut state = State::default();
+        let mut host = FakeHost::default();
+        for event in [
+            tab_update(0, &["Tab #1"]),
+            pane_update(0, "/tmp/downloads"),
+            git_result(0, "/tmp/downloads", 128, ""),
+        ] {
+            state.apply_event(event, &mut host);
+        }
+        assert_eq!(host.renames, vec![(0, "downloads".to_string())]);
+    }
+}
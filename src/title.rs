@@ -0,0 +1,415 @@
+//! Parsing of pane titles.
+//!
+//! Shells and editors often put the working directory in the pane title,
+//! either as a plain path, after a prompt such as `zsh: ` or as an OSC 7
+//! `file://` URL. This module recovers that path so a tab can be named after
+//! it, and cleans up titles that are used as names themselves.
+
+use crate::context::is_unc;
+
+/// What a pane title says about where its shell is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedTitle {
+    /// The remote host from an `ssh <host>:` prefix, without any `user@`.
+    pub host: Option<String>,
+    /// The working directory, see [`extract_cwd_from_title`].
+    pub cwd: Option<String>,
+}
+
+/// Parses a pane title into the cwd and, for an ssh session, the host.
+///
+/// A title starting with `ssh <host>:` names the remote host, so
+/// `ssh deploy@web1: /srv/app` gives host `web1` and cwd `/srv/app`. See
+/// [`extract_cwd_from_title`] for how `trailing` and `separators` find the cwd.
+///
+/// # Examples
+///
+/// - `"ssh myhost: /srv/app"` → host `myhost`, cwd `/srv/app`
+/// - `"zsh: ~/proj"` → no host, cwd `~/proj`
+pub fn parse_title(title: &str, trailing: &[String], separators: &[String]) -> ParsedTitle {
+    let stripped = strip_trailing_metadata(title, trailing).trim();
+    ParsedTitle {
+        host: parse_ssh_host(stripped),
+        cwd: parse_cwd(title, trailing, separators),
+    }
+}
+
+/// Extracts the cwd from a pane title.
+///
+/// Trailing metadata after any of `trailing` is dropped, so `~/proj — 80×24`
+/// and `~/proj • main` both yield `~/proj`. The path is then taken from an
+/// OSC 7 style `file://host/path` URL, from after one of `separators` tried
+/// in order, such as `: ` in `zsh: /path` or `:` in `vim:/path`,
+/// or from a title that is itself a path. Windows UNC paths such as
+/// `\\server\share\project` count as paths too.
+///
+/// This is [`parse_title`] without the host.
+pub fn extract_cwd_from_title(
+    title: &str,
+    trailing: &[String],
+    separators: &[String],
+) -> Option<String> {
+    parse_title(title, trailing, separators).cwd
+}
+
+/// Returns the host of an `ssh <host>:` title, dropping any `user@`.
+fn parse_ssh_host(title: &str) -> Option<String> {
+    let (host, _) = title.strip_prefix("ssh ")?.split_once(':')?;
+    let host = host.trim();
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    (!host.is_empty() && !host.contains(char::is_whitespace)).then(|| host.to_string())
+}
+
+/// Finds the cwd in `title`, see [`extract_cwd_from_title`].
+///
+/// Separators are tried in order, each splitting the title once at its last
+/// occurrence, so a separator inside the prefix, as in `vim — a — ~/proj`,
+/// never ends up in the path. A split that gives no path moves on to the
+/// next separator.
+fn parse_cwd(title: &str, trailing: &[String], separators: &[String]) -> Option<String> {
+    let stripped = strip_trailing_metadata(title, trailing).trim();
+    if stripped.is_empty() {
+        return None;
+    }
+
+    // Checked first, since the `:/` format would otherwise take `//host/path`
+    if let Some(idx) = stripped.find("file://") {
+        return parse_file_url(&stripped[idx..]);
+    }
+
+    for sep in separators.iter().filter(|sep| !sep.is_empty()) {
+        // A separator without spaces, like `:`, can sit inside a path, so a
+        // title that is itself a path wins over it
+        if !sep.contains(char::is_whitespace) && is_path_start(stripped) {
+            return Some(stripped.to_string());
+        }
+        if let Some((_, after)) = stripped.rsplit_once(sep.as_str()) {
+            let after = after.trim();
+            if is_path_start(after) {
+                return Some(after.to_string());
+            }
+        }
+    }
+
+    is_path_start(stripped).then(|| stripped.to_string())
+}
+
+fn is_path_start(s: &str) -> bool {
+    s.starts_with('/') || s.starts_with('~') || is_unc(s)
+}
+
+/// Removes emoji from `s`, collapsing the whitespace left behind.
+///
+/// Pictographs, dingbats, regional indicator flags and the joiners, variation
+/// selectors, skin tone modifiers and tags that combine them into a single
+/// emoji are all dropped. Other symbols, such as `⎈` or `→`, are kept.
+///
+/// # Examples
+///
+/// `"🚀 deploy ✨ prod"` → `"deploy prod"`.
+pub fn strip_emoji(s: &str) -> String {
+    let kept: String = s.chars().filter(|c| !is_emoji(*c)).collect();
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        // Pictographs, emoticons, transport, flags and their modifiers
+        0x1F000..=0x1FAFF
+            // Misc symbols and dingbats, e.g. ☀ and ✨
+            | 0x2600..=0x27BF
+            // Watch, hourglass and media controls
+            | 0x231A..=0x231B
+            | 0x23E9..=0x23FA
+            // Stars and circles used as emoji, e.g. ⭐
+            | 0x2B50..=0x2B55
+            // Zero width joiner, keycap, variation selectors and tags
+            | 0x200D
+            | 0x20E3
+            | 0xFE00..=0xFE0F
+            | 0xE0020..=0xE007F
+    )
+}
+
+/// Cuts a title at the first ` <separator>`, dropping the metadata after it.
+fn strip_trailing_metadata<'a>(title: &'a str, separators: &[String]) -> &'a str {
+    separators
+        .iter()
+        .filter(|sep| !sep.is_empty())
+        .filter_map(|sep| title.find(&format!(" {}", sep)))
+        .min()
+        .map_or(title, |idx| &title[..idx])
+}
+
+/// Extracts the path from an OSC 7 style `file://<host>/<path>` URL.
+///
+/// The host may be empty (`file:///path`) or name any machine; only the path
+/// is returned, with percent-encoding such as `%20` decoded. The URL ends at
+/// the first whitespace. Returns `None` when there is no path.
+fn parse_file_url(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("file://")?;
+    let rest = rest.split_whitespace().next()?;
+    let path = &rest[rest.find('/')?..];
+    Some(percent_decode(path))
+}
+
+/// Decodes `%XX` escapes, leaving malformed escapes as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_emoji_from_title() {
+        assert_eq!(strip_emoji("🚀 deploy ✨ prod"), "deploy prod");
+        assert_eq!(strip_emoji("build⭐️"), "build");
+        // Joined sequences and flags go as a whole
+        assert_eq!(strip_emoji("👩‍💻 dev 🇳🇱"), "dev");
+        assert_eq!(strip_emoji("🔥"), "");
+    }
+
+    #[test]
+    fn test_strip_emoji_plain_title_unaffected() {
+        assert_eq!(strip_emoji("vim src/main.rs"), "vim src/main.rs");
+        assert_eq!(strip_emoji("⎈ café → prod"), "⎈ café → prod");
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_unc() {
+        assert_eq!(
+            extract_cwd_from_title(r"\\fs01\share\project", &seps(), &delims()),
+            Some(r"\\fs01\share\project".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title(r"pwsh: \\fs01\share — 80×24", &seps(), &delims()),
+            Some(r"\\fs01\share".to_string())
+        );
+        assert_eq!(extract_cwd_from_title(r"\\", &seps(), &delims()), None);
+    }
+
+    fn seps() -> Vec<String> {
+        vec!["—".to_string(), "•".to_string()]
+    }
+
+    fn delims() -> Vec<String> {
+        vec![": ".to_string(), ":".to_string()]
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_formats() {
+        assert_eq!(
+            extract_cwd_from_title("zsh: /home/me/proj", &seps(), &delims()),
+            Some("/home/me/proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("  ~/proj  ", &seps(), &delims()),
+            Some("~/proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("vim:/etc", &seps(), &delims()),
+            Some("/etc".to_string())
+        );
+        assert_eq!(extract_cwd_from_title("htop", &seps(), &delims()), None);
+        assert_eq!(extract_cwd_from_title("   ", &seps(), &delims()), None);
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_osc7_url() {
+        assert_eq!(
+            extract_cwd_from_title("file://hostname/home/me/proj", &seps(), &delims()),
+            Some("/home/me/proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("file:///home/me/my%20proj", &seps(), &delims()),
+            Some("/home/me/my proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title(
+                "zsh: file://box.local/srv/caf%C3%A9 — 80×24",
+                &seps(),
+                &delims()
+            ),
+            Some("/srv/café".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_osc7_without_path() {
+        assert_eq!(
+            extract_cwd_from_title("file://hostname", &seps(), &delims()),
+            None
+        );
+        assert_eq!(
+            extract_cwd_from_title("file:///tmp/100%", &seps(), &delims()),
+            Some("/tmp/100%".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_em_dash_metadata() {
+        assert_eq!(
+            extract_cwd_from_title("~/proj — 80×24", &seps(), &delims()),
+            Some("~/proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("zsh: /work/api — 120×40", &seps(), &delims()),
+            Some("/work/api".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_bullet_metadata() {
+        assert_eq!(
+            extract_cwd_from_title("~/proj • main", &seps(), &delims()),
+            Some("~/proj".to_string())
+        );
+        // The earliest separator wins
+        assert_eq!(
+            extract_cwd_from_title("/a • b — c", &seps(), &delims()),
+            Some("/a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_without_separators() {
+        assert_eq!(
+            extract_cwd_from_title("~/proj — 80×24", &[], &delims()),
+            Some("~/proj — 80×24".to_string())
+        );
+        // Empty entries never cut the title
+        assert_eq!(
+            extract_cwd_from_title("/a b", &[String::new()], &delims()),
+            Some("/a b".to_string())
+        );
+        // A separator glued to a path segment is part of the path
+        assert_eq!(
+            extract_cwd_from_title("/tmp/a—b", &seps(), &delims()),
+            Some("/tmp/a—b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_em_dash_separator() {
+        let dash = vec![" — ".to_string()];
+        let bullet = vec!["•".to_string()];
+        assert_eq!(
+            extract_cwd_from_title("zsh — /work/api", &bullet, &dash),
+            Some("/work/api".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("zsh — ~/proj • 80×24", &bullet, &dash),
+            Some("~/proj".to_string())
+        );
+        // Only the last occurrence splits, so nothing before it leaks in
+        assert_eq!(
+            extract_cwd_from_title("vim — a — ~/proj", &bullet, &dash),
+            Some("~/proj".to_string())
+        );
+        assert_eq!(extract_cwd_from_title("vim — a — b", &bullet, &dash), None);
+        assert_eq!(
+            extract_cwd_from_title("vim — ~/a — b", &bullet, &dash),
+            None
+        );
+        // The default separators do not know the prompt
+        assert_eq!(
+            extract_cwd_from_title("zsh — /work/api", &seps(), &delims()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_cwd_from_title_multiple_separators() {
+        let separators = vec![" > ".to_string(), ": ".to_string()];
+        // Separators are tried in order
+        assert_eq!(
+            extract_cwd_from_title("zsh: /a > /b", &seps(), &separators),
+            Some("/b".to_string())
+        );
+        // A later separator is used when an earlier one leads to no path
+        assert_eq!(
+            extract_cwd_from_title("build > step: ~/proj", &seps(), &separators),
+            Some("~/proj".to_string())
+        );
+        assert_eq!(
+            extract_cwd_from_title("build: ~/proj", &seps(), &separators),
+            Some("~/proj".to_string())
+        );
+        // A later separator inside the path is left alone
+        assert_eq!(
+            extract_cwd_from_title("vim: /srv/a:b", &seps(), &delims()),
+            Some("/srv/a:b".to_string())
+        );
+        // The defaults fall back to a bare `:` when `: ` leads to no path
+        assert_eq!(
+            extract_cwd_from_title("vim: notes:/etc/hosts", &seps(), &delims()),
+            Some("/etc/hosts".to_string())
+        );
+        // No separators still takes a title that is itself a path
+        assert_eq!(
+            extract_cwd_from_title("/srv/app", &seps(), &[]),
+            Some("/srv/app".to_string())
+        );
+        assert_eq!(extract_cwd_from_title("zsh: /srv", &seps(), &[]), None);
+    }
+
+    #[test]
+    fn test_parse_title_ssh_host() {
+        assert_eq!(
+            parse_title("ssh myhost: /srv/app", &seps(), &delims()),
+            ParsedTitle {
+                host: Some("myhost".to_string()),
+                cwd: Some("/srv/app".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_title("ssh deploy@web1: ~/app — 80×24", &seps(), &delims()),
+            ParsedTitle {
+                host: Some("web1".to_string()),
+                cwd: Some("~/app".to_string()),
+            }
+        );
+        // An ssh title without a path still names the host
+        assert_eq!(
+            parse_title("ssh myhost: top", &seps(), &delims())
+                .host
+                .as_deref(),
+            Some("myhost")
+        );
+    }
+
+    #[test]
+    fn test_parse_title_local_path_has_no_host() {
+        assert_eq!(
+            parse_title("zsh: /home/me/proj", &seps(), &delims()),
+            ParsedTitle {
+                host: None,
+                cwd: Some("/home/me/proj".to_string()),
+            }
+        );
+        assert_eq!(parse_title("/srv/app", &seps(), &delims()).host, None);
+        assert_eq!(parse_title("sshd: /srv", &seps(), &delims()).host, None);
+    }
+}